@@ -0,0 +1,3 @@
+// the depth is written by the fixed function stages, without any color
+void main() {
+}
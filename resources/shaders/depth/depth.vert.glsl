@@ -0,0 +1,12 @@
+// positions only, at the locations of the mesh program's, see mesh.vert.glsl
+layout(location = 0) in vec3 position;
+// per instance, left to its default of 0 by meshes which aren't instanced
+layout(location = 4) in vec3 instance_offset;
+
+uniform mat4 projection;
+uniform mat4 view_transform;
+uniform mat4 model_transform;
+
+void main() {
+    gl_Position = projection * view_transform * model_transform * vec4(position + instance_offset, 1.0);
+}
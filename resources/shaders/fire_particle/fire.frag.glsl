@@ -11,7 +11,38 @@ uniform vec4 fire_color;
 uniform vec4 early_smoke_color;
 uniform vec4 end_smoke_color;
 
+// set while drawing into the shadow maps, where only the smoke blocks the light
+uniform int depth_only;
+// share of the light blocked by the young smoke, fading out with its lifetime
+uniform float smoke_shadow_opacity;
+
+// 4x4 ordered dithering thresholds, so that a partial coverage of the texels is averaged into a
+// lighter shadow by the filtering
+const float DITHER[16] = float[16](
+    0.0, 8.0, 2.0, 10.0,
+    12.0, 4.0, 14.0, 6.0,
+    3.0, 11.0, 1.0, 9.0,
+    15.0, 7.0, 13.0, 5.0
+);
+
+void discard_light_through() {
+    // the flames glow rather than block the light, and the billboards are round puffs
+    if (in_lifetime < max_fire_lifetime || length(in_uv - 0.5) > 0.5) {
+        discard;
+    }
+    float coverage = smoke_shadow_opacity
+        * (1.0 - smoothstep(max_fire_lifetime, max_smoke_lifetime, in_lifetime));
+    ivec2 texel = ivec2(gl_FragCoord.xy) % 4;
+    if (coverage <= (DITHER[texel.y * 4 + texel.x] + 0.5) / 16.0) {
+        discard;
+    }
+}
+
 void main() {
+    if (depth_only != 0) {
+        discard_light_through();
+        return;
+    }
     color = mix(
         fire_color,
         early_smoke_color,
@@ -22,4 +53,4 @@ void main() {
         end_smoke_color,
         smoothstep(max_fire_lifetime, max_smoke_lifetime, in_lifetime)
     );
-}
\ No newline at end of file
+}
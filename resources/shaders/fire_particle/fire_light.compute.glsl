@@ -0,0 +1,51 @@
+#line 1
+layout(local_size_x = 256) in;
+struct FireParticle
+{
+    float lifetime;
+    float rotation;
+    vec3 position;
+    float angular_velocity;
+    vec3 velocity;
+    vec3 initial_position;
+};
+
+layout(std430, binding = 1) buffer particle_buffer {
+    FireParticle particles[];
+};
+
+// per work group, the weighted positions in xyz and the weights in w
+layout(std430, binding = 2) buffer light_buffer {
+    vec4 group_sums[];
+};
+
+uniform uint particle_count;
+uniform float max_fire_lifetime;
+
+shared vec4 sums[gl_WorkGroupSize.x];
+
+void main() {
+    uint idx = gl_GlobalInvocationID.x;
+    uint local_idx = gl_LocalInvocationID.x;
+
+    vec4 value = vec4(0.0);
+    if (idx < particle_count) {
+        FireParticle particle = particles[idx];
+        // young flames are the brightest, smoke doesn't glow
+        float weight = max(1.0 - particle.lifetime / max_fire_lifetime, 0.0);
+        value = vec4(particle.position * weight, weight);
+    }
+    sums[local_idx] = value;
+    barrier();
+
+    for (uint stride = gl_WorkGroupSize.x / 2; stride > 0; stride /= 2) {
+        if (local_idx < stride) {
+            sums[local_idx] += sums[local_idx + stride];
+        }
+        barrier();
+    }
+
+    if (local_idx == 0) {
+        group_sums[gl_WorkGroupID.x] = sums[0];
+    }
+}
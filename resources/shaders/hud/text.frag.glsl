@@ -0,0 +1,14 @@
+layout(location = 1) in vec2 in_uv;
+layout(location = 2) in vec4 in_color;
+
+layout(location = 0) out vec4 color;
+
+uniform sampler2D font_atlas;
+
+void main() {
+    float coverage = texture(font_atlas, in_uv).r;
+    if (coverage == 0.0) {
+        discard;
+    }
+    color = vec4(in_color.rgb, in_color.a * coverage);
+}
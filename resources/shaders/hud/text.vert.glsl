@@ -0,0 +1,16 @@
+layout(location = 0) in vec2 in_position;
+layout(location = 1) in vec2 in_uv;
+layout(location = 2) in vec4 in_color;
+
+layout(location = 1) out vec2 out_uv;
+layout(location = 2) out vec4 out_color;
+
+// size of the window in pixels, positions are in pixels from the top left corner
+uniform vec2 screen_size;
+
+void main() {
+    vec2 ndc = in_position / screen_size * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+    out_uv = in_uv;
+    out_color = in_color;
+}
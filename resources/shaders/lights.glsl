@@ -0,0 +1,68 @@
+// light list uploaded by the engine, see lights.rs, MAX_LIGHTS being defined by the engine and the
+// shadow maps by shadows.glsl
+
+const int LIGHT_DIRECTIONAL = 0;
+const int LIGHT_POINT = 1;
+const int LIGHT_SPOT = 2;
+
+struct Light {
+    vec3 position;
+    int kind;
+    // direction the light travels in
+    vec3 direction;
+    float range;
+    // multiplied by the intensity
+    vec3 color;
+    float cos_inner;
+    float cos_outer;
+    // layers of the light's shadow maps, without shadows if there are none
+    int shadow_layer;
+    int shadow_layer_count;
+};
+
+layout(std140, binding = 0) uniform Lights {
+    vec3 ambient_light;
+    int light_count;
+    vec3 camera_position;
+    Light lights[MAX_LIGHTS];
+};
+
+// light reaching a point, with the direction towards its source
+vec3 incoming_light(Light light, vec3 position, out vec3 to_light) {
+    if (light.kind == LIGHT_DIRECTIONAL) {
+        to_light = -light.direction;
+        return light.color;
+    }
+    vec3 offset = light.position - position;
+    float distance = length(offset);
+    to_light = offset / max(distance, 1e-4);
+    // inverse square falloff, smoothly reaching 0 at the range
+    float fade = clamp(1.0 - pow(distance / light.range, 4.0), 0.0, 1.0);
+    float attenuation = fade * fade / (1.0 + distance * distance);
+    if (light.kind == LIGHT_SPOT) {
+        attenuation *= smoothstep(light.cos_outer, light.cos_inner, dot(-to_light, light.direction));
+    }
+    return light.color * attenuation;
+}
+
+// Blinn-Phong reflection of the lights towards the camera, plus the ambient light, the shadow
+// casters hiding the lights if `receive_shadows` is set
+vec3 shade(vec3 diffuse_color, vec3 specular_color, float shininess, vec3 position, vec3 normal,
+        bool receive_shadows) {
+    vec3 to_camera = normalize(camera_position - position);
+    vec3 result = ambient_light * diffuse_color;
+    for (int i = 0; i < min(light_count, MAX_LIGHTS); i++) {
+        vec3 to_light;
+        vec3 light = incoming_light(lights[i], position, to_light);
+        float diffuse = max(dot(normal, to_light), 0.0);
+        if (diffuse == 0.0) {
+            continue;
+        }
+        if (receive_shadows && lights[i].shadow_layer_count > 0) {
+            light *= shadow_factor(lights[i].shadow_layer, lights[i].shadow_layer_count, position, normal);
+        }
+        float specular = pow(max(dot(normal, normalize(to_light + to_camera)), 0.0), shininess);
+        result += light * (diffuse * diffuse_color + specular * specular_color);
+    }
+    return result;
+}
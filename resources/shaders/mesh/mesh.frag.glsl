@@ -0,0 +1,29 @@
+layout(location = 0) in vec3 world_normal;
+layout(location = 1) in vec2 frag_uv;
+layout(location = 2) in vec4 vertex_color;
+layout(location = 3) in vec3 world_position;
+
+layout(location = 0) out vec4 color;
+
+uniform vec4 object_color;
+uniform vec3 specular_color;
+uniform float shininess;
+// set when a base color texture is bound to unit 0
+uniform int use_texture;
+uniform sampler2D base_color_texture;
+// set when the shadows of the casters darken the drawable
+uniform int receive_shadows;
+
+void main() {
+    vec4 base_color = object_color * vertex_color;
+    if (use_texture != 0) {
+        base_color *= texture(base_color_texture, frag_uv);
+    }
+    vec3 normal = normalize(world_normal);
+    // lit from both sides, e.g. planes seen from below
+    if (!gl_FrontFacing) {
+        normal = -normal;
+    }
+    color = vec4(shade(base_color.rgb, specular_color, shininess, world_position, normal,
+            receive_shadows != 0), base_color.a);
+}
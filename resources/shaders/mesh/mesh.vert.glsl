@@ -0,0 +1,25 @@
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec2 uv;
+layout(location = 3) in vec4 color;
+// per instance, left to its default of 0 by meshes which aren't instanced
+layout(location = 4) in vec3 instance_offset;
+
+uniform mat4 projection;
+uniform mat4 view_transform;
+uniform mat4 model_transform;
+
+layout(location = 0) out vec3 world_normal;
+layout(location = 1) out vec2 frag_uv;
+layout(location = 2) out vec4 vertex_color;
+layout(location = 3) out vec3 world_position;
+
+void main() {
+    // model transforms are expected to scale uniformly
+    world_normal = mat3(model_transform) * normal;
+    frag_uv = uv;
+    vertex_color = color;
+    vec4 world = model_transform * vec4(position + instance_offset, 1.0);
+    world_position = world.xyz;
+    gl_Position = projection * view_transform * world;
+}
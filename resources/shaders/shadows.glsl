@@ -0,0 +1,45 @@
+// shadow maps rendered by the engine, see shadows.rs, MAX_SHADOW_MAPS being defined by the engine
+
+layout(std140, binding = 1) uniform Shadows {
+    // from world space to the [0, 1] coordinates and depth of each layer
+    mat4 shadow_matrices[MAX_SHADOW_MAPS];
+    float shadow_texel_size;
+    float shadow_bias;
+    // world space offset along the normal, against the acne of surfaces at grazing angles
+    float shadow_normal_offset;
+    // samples on each side of the filtered texel, 0 for a single hardware filtered sample
+    int pcf_radius;
+};
+
+layout(binding = 1) uniform sampler2DArrayShadow shadow_maps;
+
+// fraction of a shadow map's texels around the point lit, averaged over the PCF kernel
+float filtered_shadow(int layer, vec3 coordinates) {
+    float lit = 0.0;
+    for (int x = -pcf_radius; x <= pcf_radius; x++) {
+        for (int y = -pcf_radius; y <= pcf_radius; y++) {
+            vec2 offset = vec2(x, y) * shadow_texel_size;
+            lit += texture(shadow_maps, vec4(coordinates.xy + offset, layer, coordinates.z - shadow_bias));
+        }
+    }
+    float side = float(2 * pcf_radius + 1);
+    return lit / (side * side);
+}
+
+// fraction of the light reaching a point, in the first of the layers covering it, the cascades
+// of a directional light going from the nearest to the farthest
+float shadow_factor(int first_layer, int layer_count, vec3 position, vec3 normal) {
+    vec3 offset_position = position + normal * shadow_normal_offset;
+    // keeps the whole kernel in the layer
+    float margin = float(pcf_radius + 1) * shadow_texel_size;
+    for (int layer = first_layer; layer < min(first_layer + layer_count, MAX_SHADOW_MAPS); layer++) {
+        vec4 projected = shadow_matrices[layer] * vec4(offset_position, 1.0);
+        vec3 coordinates = projected.xyz / projected.w;
+        if (all(greaterThan(coordinates.xy, vec2(margin))) && all(lessThan(coordinates.xy, vec2(1.0 - margin)))
+                && coordinates.z <= 1.0) {
+            return filtered_shadow(layer, coordinates);
+        }
+    }
+    // out of the shadow maps
+    return 1.0;
+}
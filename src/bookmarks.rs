@@ -0,0 +1,103 @@
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use cgmath::Point3;
+use gl::types::GLfloat;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera_path::Pose,
+    error::{EngineError, EngineResult},
+    objects::Camera,
+};
+
+/// Shared bookmarks, used until the user's bookmarks file exists and never written
+pub const DEFAULT_BOOKMARKS_FILE: &str = "resources/bookmarks/default.json";
+
+/// Saved camera pose
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bookmark {
+    pub position: [GLfloat; 3],
+    pub pitch: GLfloat,
+    pub yaw: GLfloat,
+    /// Vertical field of view in radians
+    pub fov: GLfloat,
+    /// Number key recalling the bookmark, from 1 to 9
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<u8>,
+}
+
+impl Bookmark {
+    pub fn from_camera(camera: &Camera, slot: Option<u8>) -> Self {
+        Self {
+            position: (*camera.position()).into(),
+            pitch: camera.pitch(),
+            yaw: camera.yaw(),
+            fov: camera.fov(),
+            slot,
+        }
+    }
+
+    pub fn pose(&self) -> Pose {
+        Pose {
+            position: Point3::from(self.position),
+            pitch: self.pitch,
+            yaw: self.yaw,
+            fov: self.fov,
+        }
+    }
+}
+
+/// Named camera poses, saved as a JSON object so that they can be shared
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Bookmarks {
+    bookmarks: BTreeMap<String, Bookmark>,
+}
+
+impl Bookmarks {
+    /// Loads the bookmarks of a file, none if it doesn't exist yet
+    pub fn load(path: &Path) -> EngineResult<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(EngineError::io(path, e)),
+        };
+        serde_json::from_str(&content).map_err(|e| EngineError::json(path, 1, &e))
+    }
+
+    pub fn save(&self, path: &Path) -> EngineResult<()> {
+        let content =
+            serde_json::to_string_pretty(self).map_err(|e| EngineError::io(path, e.into()))?;
+        fs::write(path, content).map_err(|e| EngineError::io(path, e))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Bookmark)> {
+        self.bookmarks.iter()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.get(name)
+    }
+
+    /// Name and bookmark recalled by a number key
+    pub fn get_slot(&self, slot: u8) -> Option<(&String, &Bookmark)> {
+        self.bookmarks.iter().find(|(_, b)| b.slot == Some(slot))
+    }
+
+    /// Adds or replaces a bookmark, taking its slot from any other bookmark
+    pub fn insert(&mut self, name: &str, bookmark: Bookmark) {
+        if let Some(slot) = bookmark.slot {
+            for other in self.bookmarks.values_mut() {
+                if other.slot == Some(slot) {
+                    other.slot = None;
+                }
+            }
+        }
+        self.bookmarks.insert(name.to_owned(), bookmark);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Bookmark> {
+        self.bookmarks.remove(name)
+    }
+}
@@ -0,0 +1,231 @@
+use cgmath::{EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, Transform, Vector4};
+use gl::types::GLfloat;
+
+/// Axis aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<GLfloat>,
+    pub max: Point3<GLfloat>,
+}
+
+impl Aabb {
+    pub fn new(min: Point3<GLfloat>, max: Point3<GLfloat>) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest box containing all the points, `None` if there are none
+    pub fn from_points(points: impl IntoIterator<Item = Point3<GLfloat>>) -> Option<Self> {
+        points.into_iter().fold(None, |aabb, point| {
+            Some(match aabb {
+                None => Self::new(point, point),
+                Some(aabb) => aabb.including(point),
+            })
+        })
+    }
+
+    /// Smallest box containing this one and the point
+    pub fn including(&self, point: Point3<GLfloat>) -> Self {
+        Self {
+            min: Point3::new(
+                self.min.x.min(point.x),
+                self.min.y.min(point.y),
+                self.min.z.min(point.z),
+            ),
+            max: Point3::new(
+                self.max.x.max(point.x),
+                self.max.y.max(point.y),
+                self.max.z.max(point.z),
+            ),
+        }
+    }
+
+    pub fn center(&self) -> Point3<GLfloat> {
+        self.min.midpoint(self.max)
+    }
+
+    /// Radius of the sphere centered on the box and passing through its corners
+    pub fn radius(&self) -> GLfloat {
+        (self.max - self.min).magnitude() / 2.
+    }
+
+    pub fn corners(&self) -> [Point3<GLfloat>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Box containing this one once transformed
+    pub fn transformed(&self, transform: &Matrix4<GLfloat>) -> Self {
+        Self::from_points(self.corners().map(|c| transform.transform_point(c))).unwrap()
+    }
+}
+
+/// Volume seen by a camera, as the planes bounding it
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    /// Planes as `(a, b, c, d)` with `ax + by + cz + d >= 0` inside
+    planes: [Vector4<GLfloat>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a projection times view matrix, whose clip depth goes from 0 at
+    /// the far plane to 1 at the near one if `reversed_z`, from -1 at the near plane to 1 at the
+    /// far one otherwise
+    pub fn from_matrix(view_projection: &Matrix4<GLfloat>, reversed_z: bool) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_projection.row(i));
+        let (near, far) = if reversed_z {
+            (w - z, z)
+        } else {
+            (w + z, w - z)
+        };
+        Self {
+            planes: [w + x, w - x, w + y, w - y, near, far],
+        }
+    }
+
+    /// Same frustum extending infinitely towards the camera, e.g. for depth clamped rendering
+    /// where what is before the near plane is still drawn
+    pub fn without_near_plane(mut self) -> Self {
+        // always satisfied
+        self.planes[4] = Vector4::new(0., 0., 0., 1.);
+        self
+    }
+
+    /// Whether some of the box may be inside the frustum, conservatively: boxes near the
+    /// frustum's edges may be kept while entirely outside
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // corner of the box the furthest along the plane's normal
+            let pick = |normal: GLfloat, min: GLfloat, max: GLfloat| {
+                if normal >= 0. {
+                    max
+                } else {
+                    min
+                }
+            };
+            let corner = Point3::new(
+                pick(plane.x, aabb.min.x, aabb.max.x),
+                pick(plane.y, aabb.min.y, aabb.max.y),
+                pick(plane.z, aabb.min.z, aabb.max.z),
+            );
+            plane.truncate().dot(corner.to_vec()) + plane.w >= 0.
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, Vector3};
+
+    use super::*;
+    use crate::projection::{Projection, ProjectionKind};
+
+    /// Frustum of a camera at the origin looking down -Z, from 0.5 to 100 units away
+    fn frustum(kind: ProjectionKind, reversed_z: bool) -> Frustum {
+        let projection = Projection {
+            kind,
+            near: 0.5,
+            far: 100.,
+            reversed_z,
+            ..Default::default()
+        };
+        Frustum::from_matrix(&projection.matrix(), reversed_z)
+    }
+
+    /// Cube of side `2 * half_size` around `center`
+    fn cube(center: [GLfloat; 3], half_size: GLfloat) -> Aabb {
+        let center = Point3::from(center);
+        let half = Vector3::new(half_size, half_size, half_size);
+        Aabb::new(center - half, center + half)
+    }
+
+    #[test]
+    fn boxes_inside_outside_and_straddling_the_sides() {
+        for kind in [ProjectionKind::Perspective, ProjectionKind::Orthographic] {
+            for reversed_z in [false, true] {
+                let frustum = frustum(kind, reversed_z);
+                let case = format!("{kind} reversed_z={reversed_z}");
+                assert!(frustum.intersects(&cube([0., 0., -10.], 1.)), "{case}");
+                assert!(!frustum.intersects(&cube([200., 0., -10.], 1.)), "{case}");
+                assert!(!frustum.intersects(&cube([0., -200., -10.], 1.)), "{case}");
+                // across the left plane
+                let straddling = Aabb::new(Point3::new(-200., -1., -11.), Point3::new(0., 1., -9.));
+                assert!(frustum.intersects(&straddling), "{case}");
+            }
+        }
+    }
+
+    #[test]
+    fn boxes_behind_the_camera_are_outside() {
+        for reversed_z in [false, true] {
+            let frustum = frustum(ProjectionKind::Perspective, reversed_z);
+            assert!(!frustum.intersects(&cube([0., 0., 10.], 1.)));
+            assert!(!frustum
+                .without_near_plane()
+                .intersects(&cube([0., 0., 10.], 1.)));
+        }
+    }
+
+    #[test]
+    fn near_plane_clips_unless_removed() {
+        for kind in [ProjectionKind::Perspective, ProjectionKind::Orthographic] {
+            for reversed_z in [false, true] {
+                let frustum = frustum(kind, reversed_z);
+                let before_near = cube([0., 0., -0.25], 0.05);
+                let case = format!("{kind} reversed_z={reversed_z}");
+                assert!(!frustum.intersects(&before_near), "{case}");
+                assert!(
+                    frustum.without_near_plane().intersects(&before_near),
+                    "{case}"
+                );
+                let across_near = cube([0., 0., -0.5], 0.1);
+                assert!(frustum.intersects(&across_near), "{case}");
+            }
+        }
+    }
+
+    #[test]
+    fn far_plane_clips() {
+        // reversed-Z perspective projections have their far plane at infinity
+        let cases = [
+            (ProjectionKind::Perspective, false),
+            (ProjectionKind::Orthographic, false),
+            (ProjectionKind::Orthographic, true),
+        ];
+        for (kind, reversed_z) in cases {
+            let frustum = frustum(kind, reversed_z);
+            let case = format!("{kind} reversed_z={reversed_z}");
+            assert!(!frustum.intersects(&cube([0., 0., -150.], 1.)), "{case}");
+            assert!(frustum.intersects(&cube([0., 0., -100.], 1.)), "{case}");
+        }
+        let infinite = frustum(ProjectionKind::Perspective, true);
+        assert!(infinite.intersects(&cube([0., 0., -1e5], 1.)));
+    }
+
+    #[test]
+    fn transformed_boxes_contain_the_transformed_corners() {
+        let aabb = Aabb::new(Point3::new(0., 0., 0.), Point3::new(1., 2., 3.));
+        let translated = aabb.transformed(&Matrix4::from_translation(Vector3::new(1., -1., 2.)));
+        assert_eq!(
+            translated,
+            Aabb::new(Point3::new(1., -1., 2.), Point3::new(2., 1., 5.))
+        );
+
+        let rotated = aabb.transformed(&Matrix4::from_angle_z(Deg(90.)));
+        let expected = Aabb::new(Point3::new(-2., 0., 0.), Point3::new(0., 1., 3.));
+        for (value, expected) in [(rotated.min, expected.min), (rotated.max, expected.max)] {
+            assert!(
+                (value - expected).magnitude() < 1e-5,
+                "{value:?} != {expected:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::{fs, path::Path};
+
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Quaternion, Rad, Rotation, Rotation3, Vector3};
+use gl::types::GLfloat;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{EngineError, EngineResult},
+    objects::Camera,
+};
+
+/// Time between a recorded keyframe and the previous one
+const RECORDED_KEYFRAME_INTERVAL: f32 = 2.0;
+
+/// Spline the positions are interpolated along
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// Passes through every keyframe with a smooth velocity
+    #[default]
+    CatmullRom,
+    /// Cubic Bézier curves, shaped by the keyframes' handles when they have some
+    Bezier,
+}
+
+/// Camera pose at a point in time
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe {
+    /// Time in seconds from the start of the path
+    pub time: f32,
+    pub position: [GLfloat; 3],
+    pub pitch: GLfloat,
+    pub yaw: GLfloat,
+    /// Vertical field of view in radians
+    pub fov: GLfloat,
+    /// Bézier control point before the keyframe, derived from the neighbours if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle_in: Option<[GLfloat; 3]>,
+    /// Bézier control point after the keyframe, derived from the neighbours if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle_out: Option<[GLfloat; 3]>,
+}
+
+impl Keyframe {
+    /// Keyframe at the camera's current pose
+    pub fn from_camera(camera: &Camera, time: f32) -> Self {
+        Self {
+            time,
+            position: (*camera.position()).into(),
+            pitch: camera.pitch(),
+            yaw: camera.yaw(),
+            fov: camera.fov(),
+            handle_in: None,
+            handle_out: None,
+        }
+    }
+
+    fn position(&self) -> Vector3<GLfloat> {
+        self.position.into()
+    }
+
+    fn rotation(&self) -> Quaternion<GLfloat> {
+        // rotates the x axis to the camera's forward vector
+        Quaternion::from_angle_z(Rad(self.yaw)) * Quaternion::from_angle_y(Rad(-self.pitch))
+    }
+}
+
+/// Interpolated camera pose
+#[derive(Clone, Copy, Debug)]
+pub struct Pose {
+    pub position: Point3<GLfloat>,
+    pub pitch: GLfloat,
+    pub yaw: GLfloat,
+    pub fov: GLfloat,
+}
+
+impl Pose {
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.set_position(self.position);
+        camera.set_pitch(self.pitch);
+        camera.set_yaw(self.yaw);
+        camera.set_fov(self.fov);
+    }
+}
+
+/// Keyframes ordered by time, saved as JSON
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraPath {
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(default)]
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn load(path: &Path) -> EngineResult<Self> {
+        let content = fs::read_to_string(path).map_err(|e| EngineError::io(path, e))?;
+        let mut camera_path: Self =
+            serde_json::from_str(&content).map_err(|e| EngineError::json(path, 1, &e))?;
+        camera_path
+            .keyframes
+            .sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(camera_path)
+    }
+
+    pub fn save(&self, path: &Path) -> EngineResult<()> {
+        let content =
+            serde_json::to_string_pretty(self).map_err(|e| EngineError::io(path, e.into()))?;
+        fs::write(path, content).map_err(|e| EngineError::io(path, e))
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Inserts a keyframe, keeping them ordered by time
+    pub fn insert(&mut self, keyframe: Keyframe) {
+        let index = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+    }
+
+    /// Adds a keyframe at the camera's pose, a fixed interval after the last one
+    pub fn record(&mut self, camera: &Camera) -> &Keyframe {
+        let time = self
+            .keyframes
+            .last()
+            .map_or(0., |k| k.time + RECORDED_KEYFRAME_INTERVAL);
+        self.keyframes.push(Keyframe::from_camera(camera, time));
+        self.keyframes.last().unwrap()
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+    }
+
+    pub fn start_time(&self) -> f32 {
+        self.keyframes.first().map_or(0., |k| k.time)
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0., |k| k.time) - self.start_time()
+    }
+
+    /// Pose along the path at `time`, clamped to the first and last keyframes
+    pub fn sample(&self, time: f32) -> Option<Pose> {
+        let keys = &self.keyframes;
+        let last = keys.len().checked_sub(1)?;
+        // index of the segment's first keyframe
+        let i = keys
+            .partition_point(|k| k.time <= time)
+            .saturating_sub(1)
+            .min(last.saturating_sub(1));
+        let (k1, k2) = (&keys[i], &keys[(i + 1).min(last)]);
+        let span = k2.time - k1.time;
+        let t = if span > 0. {
+            ((time - k1.time) / span).clamp(0., 1.)
+        } else {
+            0.
+        };
+
+        let k0 = &keys[i.saturating_sub(1)];
+        let k3 = &keys[(i + 2).min(last)];
+        let position = match self.interpolation {
+            Interpolation::CatmullRom => catmull_rom(
+                k0.position(),
+                k1.position(),
+                k2.position(),
+                k3.position(),
+                t,
+            ),
+            Interpolation::Bezier => {
+                // without handles, the curve matches the Catmull-Rom spline
+                let control_1 = k1.handle_out.map_or_else(
+                    || k1.position() + (k2.position() - k0.position()) / 6.,
+                    Vector3::from,
+                );
+                let control_2 = k2.handle_in.map_or_else(
+                    || k2.position() - (k3.position() - k1.position()) / 6.,
+                    Vector3::from,
+                );
+                bezier(k1.position(), control_1, control_2, k2.position(), t)
+            }
+        };
+
+        let (r1, mut r2) = (k1.rotation(), k2.rotation());
+        // takes the shortest way around
+        if r1.dot(r2) < 0. {
+            r2 = -r2;
+        }
+        let forward = r1.slerp(r2, t).rotate_vector(Vector3::unit_x());
+
+        Some(Pose {
+            position: Point3::from_vec(position),
+            pitch: forward.z.clamp(-1., 1.).asin(),
+            yaw: forward.y.atan2(forward.x),
+            fov: k1.fov + (k2.fov - k1.fov) * t,
+        })
+    }
+}
+
+fn catmull_rom(
+    p0: Vector3<GLfloat>,
+    p1: Vector3<GLfloat>,
+    p2: Vector3<GLfloat>,
+    p3: Vector3<GLfloat>,
+    t: f32,
+) -> Vector3<GLfloat> {
+    let (t2, t3) = (t * t, t * t * t);
+    (p1 * 2.
+        + (p2 - p0) * t
+        + (p0 * 2. - p1 * 5. + p2 * 4. - p3) * t2
+        + (p1 * 3. - p0 - p2 * 3. + p3) * t3)
+        * 0.5
+}
+
+fn bezier(
+    p0: Vector3<GLfloat>,
+    p1: Vector3<GLfloat>,
+    p2: Vector3<GLfloat>,
+    p3: Vector3<GLfloat>,
+    t: f32,
+) -> Vector3<GLfloat> {
+    let u = 1. - t;
+    p0 * (u * u * u) + p1 * (3. * u * u * t) + p2 * (3. * u * t * t) + p3 * (t * t * t)
+}
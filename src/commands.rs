@@ -0,0 +1,1077 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use cgmath::{Point3, Vector3};
+use clap::ValueEnum;
+
+use crate::{
+    camera_path::Interpolation,
+    engine::Engine,
+    input::{bindings::Action, InputState},
+    lights::{Light, LightKind},
+    objects::CameraSettings,
+    primitives::{PrimitiveShape, MAX_DETAIL},
+    program::{uniform::UniformValue, Program},
+    projection::Projection,
+    shadows::ShadowSettings,
+    view::{RenderTarget, View, ViewLayout, Viewport},
+};
+
+pub type CommandResult = Result<String, String>;
+type Handler = Rc<dyn Fn(&mut Engine, &[&str]) -> CommandResult>;
+type Completer = Rc<dyn Fn(&Engine, usize) -> Vec<String>>;
+
+struct Command {
+    usage: &'static str,
+    help: &'static str,
+    handler: Handler,
+    completer: Option<Completer>,
+}
+
+/// Named commands executable from the console, objects can register their own.
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<String, Command>,
+}
+
+impl CommandRegistry {
+    /// Registers a command, replacing any command with the same name.
+    ///
+    /// `usage` describes the arguments, `help` what the command does.
+    pub fn register(
+        &mut self,
+        name: &str,
+        usage: &'static str,
+        help: &'static str,
+        handler: impl Fn(&mut Engine, &[&str]) -> CommandResult + 'static,
+    ) {
+        self.commands.insert(
+            name.to_owned(),
+            Command {
+                usage,
+                help,
+                handler: Rc::new(handler),
+                completer: None,
+            },
+        );
+    }
+
+    /// Sets the function listing the candidates of a command's argument, given its index
+    pub fn set_completer(
+        &mut self,
+        name: &str,
+        completer: impl Fn(&Engine, usize) -> Vec<String> + 'static,
+    ) {
+        if let Some(command) = self.commands.get_mut(name) {
+            command.completer = Some(Rc::new(completer));
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.commands.keys()
+    }
+
+    fn handler(&self, name: &str) -> Option<Handler> {
+        self.commands.get(name).map(|c| c.handler.clone())
+    }
+
+    /// Candidates for the word at `index` of `words`, the first word being the command name
+    pub fn candidates(&self, engine: &Engine, words: &[&str], index: usize) -> Vec<String> {
+        if index == 0 {
+            return self.commands.keys().cloned().collect();
+        }
+        let Some(completer) = words
+            .first()
+            .and_then(|name| self.commands.get(*name))
+            .and_then(|c| c.completer.as_ref())
+        else {
+            return vec![];
+        };
+        completer(engine, index - 1)
+    }
+
+    fn help(&self, name: Option<&str>) -> CommandResult {
+        match name {
+            Some(name) => {
+                let command = self
+                    .commands
+                    .get(name)
+                    .ok_or_else(|| format!("unknown command `{name}`"))?;
+                Ok(format!("{name} {}\n  {}", command.usage, command.help))
+            }
+            None => Ok(self
+                .commands
+                .iter()
+                .map(|(name, command)| format!("{name} {}", command.usage))
+                .collect::<Vec<_>>()
+                .join("\n")),
+        }
+    }
+}
+
+/// Executes a command line on the engine
+pub fn execute(engine: &mut Engine, line: &str) -> CommandResult {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((name, args)) = words.split_first() else {
+        return Ok(String::new());
+    };
+    let handler = engine
+        .commands()
+        .handler(name)
+        .ok_or_else(|| format!("unknown command `{name}`, try `help`"))?;
+    handler(engine, args)
+}
+
+/// Readable and writable values reachable from the `get` and `set` commands, as `object.member`.
+fn targets(engine: &Engine) -> Vec<String> {
+    let mut targets: Vec<String> = [
+        "camera.position",
+        "camera.pitch",
+        "camera.yaw",
+        "camera.mode",
+        "camera.target",
+        "camera.distance",
+    ]
+    .iter()
+    .map(|t| t.to_string())
+    .collect();
+    targets.extend(Projection::NAMES.iter().map(|n| format!("camera.{n}")));
+    targets.extend(CameraSettings::NAMES.iter().map(|n| format!("camera.{n}")));
+    targets.push("light.ambient".to_owned());
+    for light in engine.lights().lights() {
+        targets.extend(
+            Light::NAMES
+                .iter()
+                .map(|n| format!("light.{}.{n}", light.name)),
+        );
+    }
+    targets.extend(ShadowSettings::NAMES.iter().map(|n| format!("shadows.{n}")));
+    for system in engine.particle_systems() {
+        let name = system.borrow().name().to_owned();
+        for member in ["time_scale", "paused", "particle_count"] {
+            targets.push(format!("{name}.{member}"));
+        }
+        if system.borrow().light().is_some() {
+            for member in ["light", "light_intensity", "light_flicker"] {
+                targets.push(format!("{name}.{member}"));
+            }
+        }
+    }
+    let mut programs: Vec<_> = engine.programs().iter().collect();
+    programs.sort_by_key(|(name, _)| *name);
+    for (name, program) in programs {
+        for (uniform, _) in program.borrow().uniforms() {
+            targets.push(format!("{name}.{uniform}"));
+        }
+    }
+    targets
+}
+
+fn get(engine: &mut Engine, target: &str) -> CommandResult {
+    let (object, member) = target
+        .split_once('.')
+        .ok_or_else(|| format!("expected `object.member`, got `{target}`"))?;
+
+    if object == "camera" {
+        let camera = engine.main_camera().ok_or("no camera")?.borrow();
+        return match member {
+            "position" => {
+                let p = camera.position();
+                Ok(format!("{} {} {}", p.x, p.y, p.z))
+            }
+            "pitch" => Ok(camera.pitch().to_string()),
+            "yaw" => Ok(camera.yaw().to_string()),
+            "mode" => Ok(camera.mode().to_string()),
+            "target" => {
+                let t = camera.target();
+                Ok(format!("{} {} {}", t.x, t.y, t.z))
+            }
+            "distance" => Ok(camera.distance().to_string()),
+            _ => camera
+                .projection()
+                .get(member)
+                .or_else(|| camera.settings().get(member))
+                .ok_or_else(|| format!("unknown camera member `{member}`")),
+        };
+    }
+
+    if object == "light" {
+        let lights = engine.lights();
+        if member == "ambient" {
+            let a = lights.ambient;
+            return Ok(format!("{} {} {}", a.x, a.y, a.z));
+        }
+        let (name, member) = member.split_once('.').ok_or_else(|| {
+            format!("expected `light.ambient` or `light.name.member`, got `{target}`")
+        })?;
+        let light = lights
+            .light(name)
+            .ok_or_else(|| format!("no light `{name}`"))?;
+        return light
+            .get(member)
+            .ok_or_else(|| format!("unknown light member `{member}`"));
+    }
+
+    if object == "shadows" {
+        return engine
+            .shadow_settings()
+            .get(member)
+            .ok_or_else(|| format!("unknown shadow setting `{member}`"));
+    }
+
+    if let Some(system) = engine.particle_system(object) {
+        let system = system.borrow();
+        return match member {
+            "time_scale" => Ok(system.time_scale().to_string()),
+            "paused" => Ok(system.is_paused().to_string()),
+            "particle_count" => Ok(system.particle_count().to_string()),
+            "light" | "light_intensity" | "light_flicker" => {
+                let light = system
+                    .light()
+                    .ok_or_else(|| format!("particle system `{object}` has no light"))?;
+                Ok(match member {
+                    "light" => light.is_enabled().to_string(),
+                    "light_intensity" => light.settings().intensity.to_string(),
+                    _ => light.settings().flicker.to_string(),
+                })
+            }
+            _ => Err(format!("unknown particle system member `{member}`")),
+        };
+    }
+
+    let program = engine
+        .programs()
+        .get(object)
+        .ok_or_else(|| format!("unknown object `{object}`"))?
+        .borrow();
+    let uniform = program
+        .uniform(member)
+        .ok_or_else(|| format!("program `{object}` has no uniform `{member}`"))?
+        .borrow();
+    uniform
+        .get()
+        .map(|v| v.to_string())
+        .ok_or_else(|| format!("uniform `{member}` can't be read"))
+}
+
+fn parse_floats<const N: usize>(values: &[&str]) -> Result<[f32; N], String> {
+    if values.len() != N {
+        return Err(format!("expected {N} value(s), got {}", values.len()));
+    }
+    let mut result = [0.; N];
+    for (r, v) in result.iter_mut().zip(values) {
+        *r = v.parse().map_err(|_| format!("invalid number `{v}`"))?;
+    }
+    Ok(result)
+}
+
+fn set(engine: &mut Engine, target: &str, values: &[&str]) -> CommandResult {
+    let (object, member) = target
+        .split_once('.')
+        .ok_or_else(|| format!("expected `object.member`, got `{target}`"))?;
+
+    if object == "camera" {
+        let mut camera = engine.main_camera().ok_or("no camera")?.borrow_mut();
+        match member {
+            "position" => camera.set_position(Point3::from(parse_floats::<3>(values)?)),
+            "pitch" => camera.set_pitch(parse_floats::<1>(values)?[0]),
+            "yaw" => camera.set_yaw(parse_floats::<1>(values)?[0]),
+            "mode" => match values {
+                [mode] => camera.set_mode(mode.parse()?),
+                _ => return Err("expected `free` or `orbit`".to_owned()),
+            },
+            "target" => camera.set_target(Point3::from(parse_floats::<3>(values)?)),
+            "distance" => camera.set_distance(parse_floats::<1>(values)?[0]),
+            _ if Projection::NAMES.contains(&member) => match values {
+                [value] => {
+                    let mut projection = *camera.projection();
+                    projection.set(member, value)?;
+                    camera.set_projection(projection);
+                }
+                _ => return Err("expected a single value".to_owned()),
+            },
+            _ if CameraSettings::NAMES.contains(&member) => match values {
+                [value] => camera.settings_mut().set(member, value)?,
+                _ => return Err("expected a single value".to_owned()),
+            },
+            _ => return Err(format!("unknown camera member `{member}`")),
+        }
+        return Ok(String::new());
+    }
+
+    if object == "light" {
+        let lights = engine.lights_mut();
+        if member == "ambient" {
+            let ambient = Vector3::from(parse_floats::<3>(values)?);
+            lights.ambient = ambient.map(|c| c.max(0.));
+            return Ok(String::new());
+        }
+        let (name, member) = member.split_once('.').ok_or_else(|| {
+            format!("expected `light.ambient` or `light.name.member`, got `{target}`")
+        })?;
+        let light = lights
+            .light_mut(name)
+            .ok_or_else(|| format!("no light `{name}`"))?;
+        light.set(member, values)?;
+        return Ok(String::new());
+    }
+
+    if object == "shadows" {
+        let [value] = values else {
+            return Err("expected a single value".to_owned());
+        };
+        let mut settings = *engine.shadow_settings();
+        settings.set(member, value)?;
+        engine
+            .set_shadow_settings(settings)
+            .map_err(|e| e.chain())?;
+        return Ok(String::new());
+    }
+
+    if let Some(system) = engine.particle_system(object) {
+        let mut system = system.borrow_mut();
+        match (member, values) {
+            ("time_scale", _) => system.set_time_scale(parse_floats::<1>(values)?[0]),
+            ("paused", [value]) => {
+                system.set_paused(value.parse().map_err(|_| "expected `true` or `false`")?)
+            }
+            ("paused", _) => return Err("expected `true` or `false`".to_owned()),
+            ("light" | "light_intensity" | "light_flicker", _) => {
+                let light = system
+                    .light_mut()
+                    .ok_or_else(|| format!("particle system `{object}` has no light"))?;
+                match (member, values) {
+                    ("light", [value]) => {
+                        light.set_enabled(value.parse().map_err(|_| "expected `true` or `false`")?)
+                    }
+                    ("light", _) => return Err("expected `true` or `false`".to_owned()),
+                    ("light_intensity", _) => {
+                        light.settings_mut().intensity = parse_floats::<1>(values)?[0].max(0.)
+                    }
+                    _ => light.settings_mut().flicker = parse_floats::<1>(values)?[0].clamp(0., 1.),
+                }
+            }
+            _ => return Err(format!("unknown or read-only member `{member}`")),
+        }
+        return Ok(String::new());
+    }
+
+    let program = engine
+        .programs()
+        .get(object)
+        .ok_or_else(|| format!("unknown object `{object}`"))?
+        .borrow();
+    let mut uniform = program
+        .uniform(member)
+        .ok_or_else(|| format!("program `{object}` has no uniform `{member}`"))?
+        .borrow_mut();
+    let value = UniformValue::parse(uniform.value_type(), values)?;
+    uniform.set(&value);
+    Ok(String::new())
+}
+
+fn light(engine: &mut Engine, args: &[&str]) -> CommandResult {
+    let lights = engine.lights_mut();
+    match args {
+        ["list"] => Ok(lights
+            .lights()
+            .iter()
+            .map(|light| {
+                let state = if light.enabled { "" } else { ", disabled" };
+                format!("{} ({}{state})", light.name, light.kind)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        ["add", name, kind] => {
+            if name.contains('.') {
+                return Err(format!("light names can't contain `.`, got `{name}`"));
+            }
+            let kind = LightKind::from_str(kind, true)?;
+            lights.add(Light::new(*name, kind));
+            Ok(format!("{kind} light `{name}` added"))
+        }
+        ["remove", name] => {
+            lights
+                .remove(name)
+                .ok_or_else(|| format!("no light `{name}`"))?;
+            Ok(format!("light `{name}` removed"))
+        }
+        _ => Err("usage: light list|add <name> <directional|point|spot>|remove <name>".to_owned()),
+    }
+}
+
+fn shadow(engine: &mut Engine, args: &[&str]) -> CommandResult {
+    let usage = "usage: shadow [on|off]|list|cast <drawable> <on|off>|receive <drawable> <on|off>";
+    let switch = |value: &str| match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(usage.to_owned()),
+    };
+    match args {
+        [] | ["on" | "off"] => {
+            let mut settings = *engine.shadow_settings();
+            settings.enabled = match args {
+                [value] => switch(value)?,
+                _ => !settings.enabled,
+            };
+            engine
+                .set_shadow_settings(settings)
+                .map_err(|e| e.chain())?;
+            Ok(format!(
+                "shadows {}",
+                if settings.enabled { "on" } else { "off" }
+            ))
+        }
+        ["list"] => Ok(engine
+            .drawables()
+            .iter()
+            .map(|d| {
+                let d = d.borrow();
+                let flags: Vec<&str> = [
+                    (d.casts_shadows(), "casts"),
+                    (d.receives_shadows(), "receives"),
+                ]
+                .into_iter()
+                .filter_map(|(set, flag)| set.then_some(flag))
+                .collect();
+                format!("{}: {}", d.name(), flags.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        [flag @ ("cast" | "receive"), name, value] => {
+            let value = switch(value)?;
+            let mut drawable = engine
+                .drawable(name)
+                .ok_or_else(|| format!("no drawable `{name}`"))?
+                .borrow_mut();
+            if *flag == "cast" {
+                drawable.set_casts_shadows(value)?;
+            } else {
+                drawable.set_receives_shadows(value)?;
+            }
+            Ok(String::new())
+        }
+        _ => Err(usage.to_owned()),
+    }
+}
+
+fn list(engine: &mut Engine, args: &[&str]) -> CommandResult {
+    let lines: Vec<String> = match args {
+        ["programs"] => {
+            let mut names: Vec<_> = engine.programs().keys().cloned().collect();
+            names.sort();
+            names
+        }
+        ["uniforms", program] => {
+            let program = engine
+                .programs()
+                .get(*program)
+                .ok_or_else(|| format!("unknown program `{program}`"))?
+                .borrow();
+            program
+                .uniforms()
+                .iter()
+                .map(|(name, uniform)| {
+                    let uniform = uniform.borrow();
+                    let value = uniform.get().map(|v| v.to_string()).unwrap_or_default();
+                    format!(
+                        "{name}: {} = {value}",
+                        crate::program::uniform::stringify_type(uniform.value_type())
+                    )
+                })
+                .collect()
+        }
+        ["particles"] => engine
+            .particle_systems()
+            .iter()
+            .map(|s| {
+                let s = s.borrow();
+                format!("{}: {} particles", s.name(), s.particle_count())
+            })
+            .collect(),
+        ["commands"] => engine.commands().names().cloned().collect(),
+        ["bindings"] => {
+            let bindings = unsafe { InputState::get() }.bindings();
+            Action::ALL
+                .iter()
+                .map(|action| {
+                    let inputs: Vec<String> = bindings
+                        .bindings(*action)
+                        .iter()
+                        .map(|b| b.to_string())
+                        .collect();
+                    format!("{action}: {}", inputs.join(", "))
+                })
+                .collect()
+        }
+        _ => {
+            return Err(
+                "usage: list programs|uniforms <program>|particles|commands|bindings".to_owned(),
+            )
+        }
+    };
+    Ok(lines.join("\n"))
+}
+
+fn reload(engine: &mut Engine, args: &[&str]) -> CommandResult {
+    let programs: Vec<_> = match args {
+        ["shaders"] => engine.programs().iter().collect(),
+        ["shaders", name] => vec![engine
+            .programs()
+            .get_key_value(*name)
+            .ok_or_else(|| format!("unknown program `{name}`"))?],
+        _ => return Err("usage: reload shaders [program]".to_owned()),
+    };
+    let mut errors = Vec::new();
+    let mut count = 0;
+    for (name, program) in programs {
+        match Program::reload(program) {
+            Ok(()) => count += 1,
+            Err(error) => errors.push(format!("`{name}`: {}", error.chain())),
+        }
+    }
+    if errors.is_empty() {
+        Ok(format!("{count} program(s) reloaded"))
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+fn camera_path(engine: &mut Engine, args: &[&str]) -> CommandResult {
+    match args {
+        ["play"] => {
+            engine.play_camera_path(true);
+            if !engine.is_playing_camera_path() {
+                return Err("the camera path has no keyframes".to_owned());
+            }
+            Ok(format!(
+                "playing {}s camera path",
+                engine.camera_path().duration()
+            ))
+        }
+        ["stop"] => {
+            engine.play_camera_path(false);
+            Ok(String::new())
+        }
+        ["record"] => {
+            engine.record_keyframe();
+            Ok(format!(
+                "{} keyframe(s)",
+                engine.camera_path().keyframes().len()
+            ))
+        }
+        ["clear"] => {
+            engine.play_camera_path(false);
+            engine.camera_path_mut().clear();
+            Ok(String::new())
+        }
+        ["list"] => Ok(engine
+            .camera_path()
+            .keyframes()
+            .iter()
+            .enumerate()
+            .map(|(i, k)| {
+                let [x, y, z] = k.position;
+                format!(
+                    "{i}: {}s position {x} {y} {z} pitch {} yaw {} fov {}",
+                    k.time, k.pitch, k.yaw, k.fov
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        ["interpolation", name] => {
+            engine.camera_path_mut().interpolation = match *name {
+                "catmull_rom" => Interpolation::CatmullRom,
+                "bezier" => Interpolation::Bezier,
+                _ => return Err(format!("unknown interpolation `{name}`")),
+            };
+            Ok(String::new())
+        }
+        ["load", path] => {
+            engine
+                .load_camera_path(Path::new(path))
+                .map_err(|e| e.chain())?;
+            Ok(format!(
+                "{} keyframe(s) loaded",
+                engine.camera_path().keyframes().len()
+            ))
+        }
+        ["save", rest @ ..] if rest.len() <= 1 => {
+            let path = rest
+                .first()
+                .map(PathBuf::from)
+                .or_else(|| engine.camera_path_file().map(Path::to_owned))
+                .ok_or("no file to save the camera path to, give one")?;
+            engine.save_camera_path(&path).map_err(|e| e.chain())?;
+            Ok(format!("camera path saved to `{}`", path.display()))
+        }
+        _ => Err(
+            "usage: path play|stop|record|clear|list|interpolation <catmull_rom|bezier>|load <file>|save [file]"
+                .to_owned(),
+        ),
+    }
+}
+
+fn bookmark(engine: &mut Engine, args: &[&str]) -> CommandResult {
+    match args {
+        ["list"] => Ok(engine
+            .bookmarks()
+            .iter()
+            .map(|(name, b)| {
+                let [x, y, z] = b.position;
+                let key = b.slot.map(|s| format!(" (key {s})")).unwrap_or_default();
+                format!(
+                    "{name}{key}: position {x} {y} {z} pitch {} yaw {} fov {}",
+                    b.pitch, b.yaw, b.fov
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        ["save", name, rest @ ..] if rest.len() <= 1 => {
+            let slot = match rest.first() {
+                Some(slot) => match slot.parse() {
+                    Ok(slot @ 1..=9) => Some(slot),
+                    _ => return Err(format!("invalid key `{slot}`, expected 1 to 9")),
+                },
+                None => None,
+            };
+            engine.add_bookmark(name, slot).map_err(|e| e.chain())?;
+            Ok(format!("camera bookmark `{name}` saved"))
+        }
+        ["go", name] => {
+            if engine.go_to_bookmark(name) {
+                Ok(String::new())
+            } else {
+                Err(format!("unknown bookmark `{name}`"))
+            }
+        }
+        ["delete", name] => match engine.remove_bookmark(name) {
+            Ok(true) => Ok(format!("camera bookmark `{name}` deleted")),
+            Ok(false) => Err(format!("unknown bookmark `{name}`")),
+            Err(error) => Err(error.chain()),
+        },
+        _ => Err("usage: bookmark list|save <name> [key]|go <name>|delete <name>".to_owned()),
+    }
+}
+
+fn view(engine: &mut Engine, args: &[&str]) -> CommandResult {
+    match args {
+        ["list"] => Ok(engine
+            .views()
+            .iter()
+            .map(|v| {
+                let controlled = engine
+                    .main_camera()
+                    .is_some_and(|c| Rc::ptr_eq(c, v.camera()));
+                let target = v
+                    .target()
+                    .map(|t| format!(" target {}x{}", t.size().0, t.size().1))
+                    .unwrap_or_default();
+                let controlled = if controlled { " (controlled)" } else { "" };
+                format!("{}{controlled}: viewport {}{target}", v.name(), v.viewport())
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        ["add", name, rest @ ..] if (4..=5).contains(&rest.len()) => {
+            let viewport = Viewport::parse(&rest[..4])?;
+            let camera = match rest.get(4) {
+                Some(other) => engine
+                    .find_camera(other)
+                    .ok_or_else(|| format!("no view or scene camera `{other}`"))?,
+                None => {
+                    let camera = engine.main_camera().ok_or("no camera")?.borrow().clone();
+                    Rc::new(RefCell::new(camera))
+                }
+            };
+            engine.add_view(View::new(*name, camera, viewport));
+            Ok(format!("view `{name}` added"))
+        }
+        ["remove", name] => {
+            engine.remove_view(name)?;
+            Ok(format!("view `{name}` removed"))
+        }
+        ["move", name, rest @ ..] => {
+            let viewport = Viewport::parse(rest)?;
+            engine
+                .view_mut(name)
+                .ok_or_else(|| format!("no view `{name}`"))?
+                .set_viewport(viewport);
+            Ok(String::new())
+        }
+        ["control", name] => {
+            if engine.control_view(name) {
+                Ok(format!("controlling the camera of view `{name}`"))
+            } else {
+                Err(format!("no view `{name}`"))
+            }
+        }
+        ["camera", name, camera] => {
+            engine.set_view_camera(name, camera)?;
+            Ok(format!("view `{name}` shows camera `{camera}`"))
+        }
+        ["target", name, "off"] => {
+            engine
+                .view_mut(name)
+                .ok_or_else(|| format!("no view `{name}`"))?
+                .set_target(None);
+            Ok(String::new())
+        }
+        ["target", name, width, height] => {
+            let parse = |v: &str| -> Result<i32, String> {
+                v.parse().map_err(|_| format!("invalid size `{v}`"))
+            };
+            let (width, height) = (parse(width)?, parse(height)?);
+            let view = engine
+                .view_mut(name)
+                .ok_or_else(|| format!("no view `{name}`"))?;
+            let target = RenderTarget::new(name, width, height).map_err(|e| e.chain())?;
+            view.set_target(Some(target));
+            Ok(format!("view `{name}` rendered at {width}x{height}"))
+        }
+        ["layout", layout] => {
+            engine.apply_view_layout(layout.parse()?);
+            Ok(String::new())
+        }
+        _ => Err("usage: view list|add <name> <x> <y> <width> <height> [camera]|remove <name>|move <name> <x> <y> <width> <height>|control <name>|camera <name> <camera>|target <name> <width> <height>|target <name> off|layout <single|split|picture-in-picture>"
+            .to_owned()),
+    }
+}
+
+/// Registers the engine's built-in commands
+pub fn register_builtins(registry: &mut CommandRegistry) {
+    registry.register(
+        "help",
+        "[command]",
+        "lists commands, or describes one",
+        |engine, args| engine.commands().help(args.first().copied()),
+    );
+    registry.set_completer("help", |engine, _| {
+        engine.commands().names().cloned().collect()
+    });
+
+    registry.register(
+        "get",
+        "<object.member>",
+        "prints a uniform, camera, light or particle system value",
+        |engine, args| match args {
+            [target] => get(engine, target),
+            _ => Err("usage: get <object.member>".to_owned()),
+        },
+    );
+    registry.set_completer("get", |engine, index| match index {
+        0 => targets(engine),
+        _ => vec![],
+    });
+
+    registry.register(
+        "set",
+        "<object.member> <values...>",
+        "sets a uniform, camera, light or particle system value, vectors and matrices are given component by component",
+        |engine, args| match args {
+            [target, values @ ..] if !values.is_empty() => set(engine, target, values),
+            _ => Err("usage: set <object.member> <values...>".to_owned()),
+        },
+    );
+    registry.set_completer("set", |engine, index| match index {
+        0 => targets(engine),
+        _ => vec![],
+    });
+
+    registry.register(
+        "list",
+        "programs|uniforms <program>|particles|commands|bindings",
+        "lists engine objects",
+        list,
+    );
+    registry.set_completer("list", |engine, index| match index {
+        0 => ["programs", "uniforms", "particles", "commands", "bindings"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        1 => engine.programs().keys().cloned().collect(),
+        _ => vec![],
+    });
+
+    registry.register(
+        "reload",
+        "shaders [program]",
+        "compiles and links programs again from their shader files",
+        reload,
+    );
+    registry.set_completer("reload", |engine, index| match index {
+        0 => vec!["shaders".to_owned()],
+        1 => engine.programs().keys().cloned().collect(),
+        _ => vec![],
+    });
+
+    registry.register(
+        "pause",
+        "",
+        "pauses or resumes the simulation",
+        |engine, _| {
+            let paused = !engine.is_paused();
+            engine.set_paused(paused);
+            Ok(if paused { "paused" } else { "resumed" }.to_owned())
+        },
+    );
+
+    registry.register(
+        "culling",
+        "[on|off]",
+        "skips drawing the objects out of the cameras' view, toggled without argument",
+        |engine, args| {
+            let culling = match args {
+                [] => !engine.is_culling(),
+                ["on"] => true,
+                ["off"] => false,
+                _ => return Err("usage: culling [on|off]".to_owned()),
+            };
+            engine.set_culling(culling);
+            Ok(format!("culling {}", if culling { "on" } else { "off" }))
+        },
+    );
+    registry.set_completer("culling", |_, index| match index {
+        0 => vec!["on".to_owned(), "off".to_owned()],
+        _ => vec![],
+    });
+
+    registry.register(
+        "step",
+        "[frames]",
+        "pauses the simulation after updating it for a number of frames, 1 by default",
+        |engine, args| {
+            let frames = match args {
+                [] => 1,
+                [frames] => frames
+                    .parse()
+                    .map_err(|_| format!("invalid frame count `{frames}`"))?,
+                _ => return Err("usage: step [frames]".to_owned()),
+            };
+            engine.step(frames);
+            Ok(String::new())
+        },
+    );
+
+    registry.register(
+        "frame",
+        "[drawable]",
+        "orbits the camera around a drawable, or the one in the center of the view",
+        |engine, args| {
+            let bounds = match args {
+                [] => engine
+                    .looked_at_bounds()
+                    .ok_or("no drawable with known bounds")?,
+                [name] => engine
+                    .drawable_bounds(name)
+                    .ok_or_else(|| format!("no drawable `{name}` with known bounds"))?,
+                _ => return Err("usage: frame [drawable]".to_owned()),
+            };
+            engine
+                .main_camera()
+                .ok_or("no camera")?
+                .borrow_mut()
+                .frame(&bounds);
+            Ok(String::new())
+        },
+    );
+    registry.set_completer("frame", |engine, index| match index {
+        0 => engine
+            .drawables()
+            .iter()
+            .map(|d| d.borrow())
+            .filter(|d| d.bounds().is_some())
+            .map(|d| d.name().to_owned())
+            .collect(),
+        _ => vec![],
+    });
+
+    registry.register(
+        "bookmark",
+        "list|save <name> [key]|go <name>|delete <name>",
+        "saves the camera pose to the bookmarks file, optionally recalled by a number key, or recalls one",
+        bookmark,
+    );
+    registry.set_completer("bookmark", |engine, index| match index {
+        0 => ["list", "save", "go", "delete"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        1 => engine.bookmarks().iter().map(|(n, _)| n.clone()).collect(),
+        _ => vec![],
+    });
+
+    registry.register(
+        "path",
+        "play|stop|record|clear|list|interpolation <catmull_rom|bezier>|load <file>|save [file]",
+        "plays, records and edits the camera path, saved to the file it was loaded from by default",
+        camera_path,
+    );
+    registry.set_completer("path", |_, index| match index {
+        0 => [
+            "play",
+            "stop",
+            "record",
+            "clear",
+            "list",
+            "interpolation",
+            "load",
+            "save",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect(),
+        1 => vec!["catmull_rom".to_owned(), "bezier".to_owned()],
+        _ => vec![],
+    });
+
+    registry.register(
+        "mesh",
+        "<file.obj>",
+        "loads a Wavefront OBJ mesh and its materials",
+        |engine, args| match args {
+            [path] => {
+                let name = engine.load_mesh(Path::new(path)).map_err(|e| e.chain())?;
+                Ok(format!("mesh `{name}` loaded"))
+            }
+            _ => Err("usage: mesh <file.obj>".to_owned()),
+        },
+    );
+
+    registry.register(
+        "scene",
+        "<file.gltf|file.glb>",
+        "loads the meshes, materials and cameras of a glTF scene",
+        |engine, args| match args {
+            [path] => {
+                let (drawables, cameras) =
+                    engine.load_scene(Path::new(path)).map_err(|e| e.chain())?;
+                let mut message = format!("{} drawables loaded", drawables.len());
+                if !cameras.is_empty() {
+                    message.push_str(&format!(", cameras: {}", cameras.join(", ")));
+                }
+                Ok(message)
+            }
+            _ => Err("usage: scene <file.gltf|file.glb>".to_owned()),
+        },
+    );
+
+    registry.register(
+        "light",
+        "list|add <name> <directional|point|spot>|remove <name>",
+        "edits the lights of meshes, their members being set with `set light.name.member`",
+        light,
+    );
+    registry.set_completer("light", |engine, index| match index {
+        0 => ["list", "add", "remove"].map(str::to_owned).to_vec(),
+        1 => engine
+            .lights()
+            .lights()
+            .iter()
+            .map(|light| light.name.clone())
+            .collect(),
+        2 => LightKind::value_variants()
+            .iter()
+            .map(LightKind::to_string)
+            .collect(),
+        _ => vec![],
+    });
+
+    registry.register(
+        "shadow",
+        "[on|off]|list|cast <drawable> <on|off>|receive <drawable> <on|off>",
+        "renders the shadows of the lights casting them, toggled without argument, or sets which drawables cast and receive them",
+        shadow,
+    );
+    registry.set_completer("shadow", |engine, index| match index {
+        0 => ["on", "off", "list", "cast", "receive"]
+            .map(str::to_owned)
+            .to_vec(),
+        1 => engine
+            .drawables()
+            .iter()
+            .map(|d| d.borrow().name().to_owned())
+            .collect(),
+        2 => vec!["on".to_owned(), "off".to_owned()],
+        _ => vec![],
+    });
+
+    registry.register(
+        "shape",
+        "<plane|uv-sphere|icosphere|cylinder|cone|torus|capsule> [detail]",
+        "adds a generated shape in front of the camera, its tessellation scaled by detail",
+        |engine, args| {
+            let (shape, detail) = match args {
+                [shape] => (shape, 1),
+                [shape, detail] => (
+                    shape,
+                    detail
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|d| (1..=MAX_DETAIL).contains(d))
+                        .ok_or_else(|| {
+                            format!("invalid detail `{detail}`, expected 1 to {MAX_DETAIL}")
+                        })?,
+                ),
+                _ => return Err(
+                    "usage: shape <plane|uv-sphere|icosphere|cylinder|cone|torus|capsule> [detail]"
+                        .to_owned(),
+                ),
+            };
+            let shape: PrimitiveShape = shape.parse()?;
+            let name = engine.add_shape(shape, detail).map_err(|e| e.chain())?;
+            Ok(format!("shape `{name}` added"))
+        },
+    );
+    registry.set_completer("shape", |_, index| match index {
+        0 => PrimitiveShape::ALL
+            .iter()
+            .map(|shape| shape.name().to_owned())
+            .collect(),
+        _ => vec![],
+    });
+
+    registry.register(
+        "view",
+        "list|add <name> <x> <y> <width> <height> [camera]|remove <name>|move <name> <x> <y> <width> <height>|control <name>|camera <name> <camera>|target <name> <width> <height>|target <name> off|layout <single|split|picture-in-picture>",
+        "renders cameras into rectangles of the window, given as fractions of its size from the bottom left corner",
+        view,
+    );
+    registry.set_completer("view", |engine, index| match index {
+        0 => [
+            "list", "add", "remove", "move", "control", "camera", "target", "layout",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect(),
+        1 => engine
+            .views()
+            .iter()
+            .map(|v| v.name().to_owned())
+            .chain(ViewLayout::value_variants().iter().map(|l| l.to_string()))
+            .collect(),
+        2 => engine
+            .views()
+            .iter()
+            .map(|v| v.name().to_owned())
+            .chain(engine.scene_cameras().iter().map(|(name, _)| name.clone()))
+            .collect(),
+        _ => vec![],
+    });
+
+    registry.register(
+        "screenshot",
+        "<path.png>",
+        "saves the next frame to a PNG file",
+        |engine, args| match args {
+            [path] => {
+                engine.request_screenshot(PathBuf::from(path));
+                Ok(format!("saving next frame to `{path}`"))
+            }
+            _ => Err("usage: screenshot <path.png>".to_owned()),
+        },
+    );
+}
@@ -0,0 +1,295 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, Write},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use cgmath::{Vector2, Vector4};
+use sdl2::{event::Event, keyboard::Keycode};
+
+use crate::{error::EngineResult, objects::TextRenderer, program::ProgramSharedPointer};
+
+const HISTORY_FILE: &str = ".pogla_history";
+const MAX_HISTORY: usize = 500;
+const MAX_OUTPUT_LINES: usize = 200;
+/// Amount of output lines displayed above the prompt
+const VISIBLE_LINES: usize = 16;
+const PROMPT: &str = "> ";
+
+const TEXT_COLOR: Vector4<f32> = Vector4::new(1., 1., 1., 1.);
+const ERROR_COLOR: Vector4<f32> = Vector4::new(1., 0.4, 0.4, 1.);
+const INPUT_COLOR: Vector4<f32> = Vector4::new(0.6, 0.9, 1., 1.);
+const BACKGROUND_COLOR: Vector4<f32> = Vector4::new(0., 0., 0., 0.75);
+
+/// Origin of a command line, the result is sent back to where the command came from
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CommandSource {
+    Window,
+    Stdin,
+}
+
+#[derive(Clone, Copy)]
+enum OutputKind {
+    Input,
+    Result,
+    Error,
+}
+
+/// Drop-down console executing engine commands, typed in the window or on the standard input.
+pub struct Console {
+    visible: bool,
+    input: String,
+    history: Vec<String>,
+    history_cursor: Option<usize>,
+    output: VecDeque<(OutputKind, String)>,
+    pending: Vec<(CommandSource, String)>,
+    completion_requested: bool,
+    stdin: Receiver<String>,
+    text: TextRenderer,
+}
+
+fn load_history() -> Vec<String> {
+    match fs::read_to_string(HISTORY_FILE) {
+        Ok(content) => content.lines().map(str::to_owned).collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+        Err(e) => {
+            log::warn!("couldn't read console history `{HISTORY_FILE}`: {e}");
+            vec![]
+        }
+    }
+}
+
+fn append_history(line: &str) {
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(HISTORY_FILE)
+        .and_then(|mut file| writeln!(file, "{line}"));
+    if let Err(e) = result {
+        log::warn!("couldn't write console history `{HISTORY_FILE}`: {e}");
+    }
+}
+
+/// Reads the standard input on a separate thread, as reading it blocks
+fn spawn_stdin_reader() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("console stdin".to_owned())
+        .spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        })
+        .expect("couldn't spawn the stdin reader thread");
+    receiver
+}
+
+/// Longest prefix shared by all the candidates
+fn common_prefix(candidates: &[&String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+    let mut prefix = first.as_str();
+    for candidate in candidates.iter().skip(1) {
+        let length = prefix
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((i, _), _)| i)
+            .unwrap_or(prefix.len().min(candidate.len()));
+        prefix = &prefix[..length];
+    }
+    prefix.to_owned()
+}
+
+impl Console {
+    pub fn new(program: ProgramSharedPointer, scale: f32) -> EngineResult<Self> {
+        let mut history = load_history();
+        let excess = history.len().saturating_sub(MAX_HISTORY);
+        history.drain(..excess);
+        Ok(Self {
+            visible: false,
+            input: String::new(),
+            history,
+            history_cursor: None,
+            output: VecDeque::new(),
+            pending: Vec::new(),
+            completion_requested: false,
+            stdin: spawn_stdin_reader(),
+            text: TextRenderer::new(program, scale)?,
+        })
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Handles a window event while the console is open, returns whether it was consumed.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        if !self.visible {
+            return false;
+        }
+        match event {
+            Event::TextInput { text, .. } => self.input.push_str(text),
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match *keycode {
+                Keycode::Return | Keycode::KpEnter => self.submit(),
+                Keycode::Backspace => {
+                    self.input.pop();
+                }
+                Keycode::Tab => self.completion_requested = true,
+                Keycode::Up => self.browse_history(true),
+                Keycode::Down => self.browse_history(false),
+                Keycode::Escape => self.visible = false,
+                _ => {}
+            },
+            Event::KeyUp { .. } => {}
+            _ => return false,
+        }
+        true
+    }
+
+    fn submit(&mut self) {
+        let line = std::mem::take(&mut self.input);
+        self.history_cursor = None;
+        if line.trim().is_empty() {
+            return;
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            append_history(&line);
+        }
+        self.pending.push((CommandSource::Window, line));
+    }
+
+    fn browse_history(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        let cursor = match (self.history_cursor, older) {
+            (None, true) => Some(self.history.len() - 1),
+            (None, false) => None,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i + 1 < self.history.len() => Some(i + 1),
+            (Some(_), false) => None,
+        };
+        self.history_cursor = cursor;
+        self.input = cursor.map(|i| self.history[i].clone()).unwrap_or_default();
+    }
+
+    /// Command lines submitted since the last call, from the window and the standard input
+    pub fn take_pending(&mut self) -> Vec<(CommandSource, String)> {
+        let mut pending = std::mem::take(&mut self.pending);
+        pending.extend(self.stdin.try_iter().map(|l| (CommandSource::Stdin, l)));
+        pending
+    }
+
+    /// The input line's words and the index of the word being completed, if completion was requested
+    pub fn take_completion_request(&mut self) -> Option<(Vec<String>, usize)> {
+        if !std::mem::take(&mut self.completion_requested) {
+            return None;
+        }
+        let mut words: Vec<String> = self.input.split_whitespace().map(str::to_owned).collect();
+        if self.input.is_empty() || self.input.ends_with(char::is_whitespace) {
+            words.push(String::new());
+        }
+        let index = words.len() - 1;
+        Some((words, index))
+    }
+
+    /// Completes the last word of the input line with the candidates it prefixes
+    pub fn complete(&mut self, candidates: &[String]) {
+        let start = self
+            .input
+            .rfind(char::is_whitespace)
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let word = &self.input[start..];
+        let matches: Vec<&String> = candidates.iter().filter(|c| c.starts_with(word)).collect();
+        match matches.as_slice() {
+            [] => {}
+            [single] => {
+                self.input.replace_range(start.., single);
+                self.input.push(' ');
+            }
+            _ => {
+                let prefix = common_prefix(&matches);
+                if prefix.len() > word.len() {
+                    self.input.replace_range(start.., &prefix);
+                } else {
+                    let list = matches
+                        .iter()
+                        .map(|s| s.as_str())
+                        .collect::<Vec<_>>()
+                        .join("  ");
+                    self.print(OutputKind::Result, &list);
+                }
+            }
+        }
+    }
+
+    /// Reports the result of a command to where it came from
+    pub fn report(&mut self, source: CommandSource, line: &str, result: &Result<String, String>) {
+        match source {
+            CommandSource::Stdin => match result {
+                Ok(output) if !output.is_empty() => println!("{output}"),
+                Ok(_) => {}
+                Err(error) => eprintln!("error: {error}"),
+            },
+            CommandSource::Window => {
+                self.print(OutputKind::Input, &format!("{PROMPT}{line}"));
+                match result {
+                    Ok(output) => self.print(OutputKind::Result, output),
+                    Err(error) => self.print(OutputKind::Error, error),
+                }
+            }
+        }
+    }
+
+    fn print(&mut self, kind: OutputKind, text: &str) {
+        for line in text.lines() {
+            if self.output.len() == MAX_OUTPUT_LINES {
+                self.output.pop_front();
+            }
+            self.output.push_back((kind, line.to_owned()));
+        }
+    }
+
+    pub fn draw(&mut self, width: i32, height: i32) {
+        if !self.visible {
+            return;
+        }
+        let glyph = self.text.glyph_size();
+        let panel_size = Vector2::new(width as f32, glyph.y * (VISIBLE_LINES + 1) as f32 + glyph.y);
+        self.text
+            .queue_rect(Vector2::new(0., 0.), panel_size, BACKGROUND_COLOR);
+
+        let margin = glyph * 0.5;
+        let first = self.output.len().saturating_sub(VISIBLE_LINES);
+        for (row, (kind, line)) in self.output.iter().skip(first).enumerate() {
+            let color = match kind {
+                OutputKind::Input => INPUT_COLOR,
+                OutputKind::Result => TEXT_COLOR,
+                OutputKind::Error => ERROR_COLOR,
+            };
+            let position = margin + Vector2::new(0., row as f32 * glyph.y);
+            self.text.queue_text(position, line, color);
+        }
+
+        let prompt_position = margin + Vector2::new(0., VISIBLE_LINES as f32 * glyph.y);
+        let prompt = format!("{PROMPT}{}_", self.input);
+        self.text.queue_text(prompt_position, &prompt, INPUT_COLOR);
+        self.text.flush(width, height);
+    }
+}
@@ -0,0 +1,474 @@
+use std::{sync::Arc, time::Instant};
+
+use cgmath::{Vector3, Vector4};
+use egui::{
+    Color32, ComboBox, DragValue, Grid, Modifiers, MouseWheelUnit, PointerButton, Pos2, Rect,
+    Slider, Ui,
+};
+use egui_glow::{glow, Painter};
+use sdl2::{
+    event::Event,
+    keyboard::{Keycode, Mod},
+    mouse::{MouseButton, MouseWheelDirection},
+    VideoSubsystem,
+};
+
+use crate::{
+    engine::Engine,
+    error::{EngineError, EngineResult},
+    gl_checked,
+    program::uniform::{stringify_type, UniformValue},
+    projection::ProjectionKind,
+};
+
+/// Immediate mode debug interface exposing the engine's programs, camera and particle systems.
+pub struct DebugUi {
+    context: egui::Context,
+    painter: Painter,
+    events: Vec<egui::Event>,
+    modifiers: Modifiers,
+    start: Instant,
+    visible: bool,
+    selected_program: Option<String>,
+}
+
+fn translate_modifiers(keymod: Mod) -> Modifiers {
+    let ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+    Modifiers {
+        alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+        ctrl,
+        shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+        mac_cmd: false,
+        command: ctrl,
+    }
+}
+
+fn translate_button(button: MouseButton) -> Option<PointerButton> {
+    match button {
+        MouseButton::Left => Some(PointerButton::Primary),
+        MouseButton::Right => Some(PointerButton::Secondary),
+        MouseButton::Middle => Some(PointerButton::Middle),
+        _ => None,
+    }
+}
+
+fn translate_key(keycode: Keycode) -> Option<egui::Key> {
+    use egui::Key;
+    let key = match keycode {
+        Keycode::Backspace => Key::Backspace,
+        Keycode::Delete => Key::Delete,
+        Keycode::Return | Keycode::KpEnter => Key::Enter,
+        Keycode::Tab => Key::Tab,
+        Keycode::Escape => Key::Escape,
+        Keycode::Home => Key::Home,
+        Keycode::End => Key::End,
+        Keycode::Left => Key::ArrowLeft,
+        Keycode::Right => Key::ArrowRight,
+        Keycode::Up => Key::ArrowUp,
+        Keycode::Down => Key::ArrowDown,
+        Keycode::A => Key::A,
+        Keycode::C => Key::C,
+        Keycode::V => Key::V,
+        Keycode::X => Key::X,
+        Keycode::Z => Key::Z,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Upper bound of a slider range containing `value`, the next power of ten above its magnitude
+fn slider_bound(value: f32) -> f32 {
+    10f32.powf(value.abs().max(1.).log10().ceil())
+}
+
+impl DebugUi {
+    pub fn new(video_subsystem: &VideoSubsystem) -> EngineResult<Self> {
+        let gl = unsafe {
+            glow::Context::from_loader_function(|s| {
+                video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void
+            })
+        };
+        #[allow(clippy::arc_with_non_send_sync)] // the painter's API requires an Arc
+        let painter = Painter::new(Arc::new(gl), "", None)
+            .map_err(|e| EngineError::Context(format!("debug interface painter: {e}")))?;
+
+        Ok(Self {
+            context: Default::default(),
+            painter,
+            events: Vec::new(),
+            modifiers: Default::default(),
+            start: Instant::now(),
+            visible: false,
+            selected_program: None,
+        })
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Forwards an SDL event to the interface.
+    ///
+    /// Returns true if the interface uses the event, in which case the engine should ignore it.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        if !self.visible {
+            return false;
+        }
+
+        match event {
+            Event::MouseMotion { x, y, .. } => {
+                self.events
+                    .push(egui::Event::PointerMoved(Pos2::new(*x as _, *y as _)));
+                self.context.wants_pointer_input()
+            }
+            Event::MouseButtonDown {
+                mouse_btn, x, y, ..
+            }
+            | Event::MouseButtonUp {
+                mouse_btn, x, y, ..
+            } => {
+                let Some(button) = translate_button(*mouse_btn) else {
+                    return false;
+                };
+                self.events.push(egui::Event::PointerButton {
+                    pos: Pos2::new(*x as _, *y as _),
+                    button,
+                    pressed: matches!(event, Event::MouseButtonDown { .. }),
+                    modifiers: self.modifiers,
+                });
+                self.context.wants_pointer_input()
+            }
+            Event::MouseWheel {
+                x, y, direction, ..
+            } => {
+                let sign = match direction {
+                    MouseWheelDirection::Flipped => -1.,
+                    _ => 1.,
+                };
+                self.events.push(egui::Event::MouseWheel {
+                    unit: MouseWheelUnit::Line,
+                    delta: egui::vec2(*x as f32, *y as f32) * sign,
+                    modifiers: self.modifiers,
+                });
+                self.context.wants_pointer_input()
+            }
+            Event::TextInput { text, .. } => {
+                if !self.context.wants_keyboard_input() {
+                    return false;
+                }
+                self.events.push(egui::Event::Text(text.clone()));
+                true
+            }
+            Event::KeyDown {
+                keycode: Some(keycode),
+                keymod,
+                repeat,
+                ..
+            }
+            | Event::KeyUp {
+                keycode: Some(keycode),
+                keymod,
+                repeat,
+                ..
+            } => {
+                self.modifiers = translate_modifiers(*keymod);
+                if !self.context.wants_keyboard_input() {
+                    return false;
+                }
+                if let Some(key) = translate_key(*keycode) {
+                    self.events.push(egui::Event::Key {
+                        key,
+                        physical_key: None,
+                        pressed: matches!(event, Event::KeyDown { .. }),
+                        repeat: *repeat,
+                        modifiers: self.modifiers,
+                    });
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Builds and draws the interface on top of the current frame
+    pub fn draw(&mut self, engine: &Engine, width: i32, height: i32) {
+        if !self.visible {
+            self.events.clear();
+            return;
+        }
+
+        let raw_input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                Pos2::ZERO,
+                egui::vec2(width as _, height as _),
+            )),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            ..Default::default()
+        };
+
+        let context = self.context.clone();
+        let output = context.run(raw_input, |ctx| {
+            egui::Window::new("Programs")
+                .default_pos((10., 200.))
+                .show(ctx, |ui| self.programs_ui(ui, engine));
+            egui::Window::new("Camera")
+                .default_pos((400., 10.))
+                .show(ctx, |ui| Self::camera_ui(ui, engine));
+            egui::Window::new("Particle systems")
+                .default_pos((400., 250.))
+                .show(ctx, |ui| Self::particle_systems_ui(ui, engine));
+        });
+
+        let primitives = context.tessellate(output.shapes, output.pixels_per_point);
+        self.painter.paint_and_update_textures(
+            [width as _, height as _],
+            output.pixels_per_point,
+            &primitives,
+            &output.textures_delta,
+        );
+
+        // restore the state the engine expects
+        unsafe {
+            gl_checked! {
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::Disable(gl::BLEND);
+                gl::Enable(gl::CULL_FACE);
+                gl::Enable(gl::DEPTH_TEST);
+                gl::Viewport(0, 0, width, height);
+                gl::BindVertexArray(0);
+                gl::UseProgram(0);
+            };
+        }
+    }
+
+    fn programs_ui(&mut self, ui: &mut Ui, engine: &Engine) {
+        let mut names: Vec<&String> = engine.programs().keys().collect();
+        names.sort();
+
+        let selected_text = self.selected_program.clone().unwrap_or_default();
+        ComboBox::from_label("program")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                for name in names {
+                    ui.selectable_value(&mut self.selected_program, Some(name.clone()), name);
+                }
+            });
+
+        let Some(program) = self
+            .selected_program
+            .as_ref()
+            .and_then(|name| engine.programs().get(name))
+        else {
+            return;
+        };
+
+        ui.separator();
+        for (name, uniform) in program.borrow().uniforms() {
+            let mut uniform = uniform.borrow_mut();
+            let Some(mut value) = uniform.get() else {
+                ui.label(format!("{name}: {}", stringify_type(uniform.value_type())));
+                continue;
+            };
+            let changed = Self::uniform_widget(ui, name, &mut value);
+            if changed {
+                uniform.set(&value);
+            }
+        }
+    }
+
+    /// Shows a widget editing `value`, returns whether the value was changed
+    fn uniform_widget(ui: &mut Ui, name: &str, value: &mut UniformValue) -> bool {
+        ui.horizontal(|ui| {
+            ui.label(name);
+            match value {
+                UniformValue::Float(v) => {
+                    let bound = slider_bound(*v);
+                    ui.add(Slider::new(v, -bound..=bound).clamp_to_range(false))
+                        .changed()
+                }
+                UniformValue::Vec2(v) => Self::vector_widget(ui, AsMut::<[f32; 2]>::as_mut(v)),
+                UniformValue::Vec3(v) => Self::vector_widget(ui, AsMut::<[f32; 3]>::as_mut(v)),
+                UniformValue::Vec4(v) => {
+                    let mut rgba: [f32; 4] = (*v).into();
+                    let changed = ui.color_edit_button_rgba_unmultiplied(&mut rgba).changed();
+                    *v = Vector4::from(rgba);
+                    changed
+                }
+                UniformValue::Int(v) => ui.add(DragValue::new(v)).changed(),
+                UniformValue::UInt(v) => ui.add(DragValue::new(v)).changed(),
+                UniformValue::Mat4(m) => {
+                    let matrix: &[[f32; 4]; 4] = m.as_ref();
+                    Grid::new(name).show(ui, |ui| {
+                        // stored column major, displayed row by row
+                        for row in 0..4 {
+                            for column in matrix.iter() {
+                                ui.monospace(format!("{:>8.3}", column[row]));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    false
+                }
+            }
+        })
+        .inner
+    }
+
+    fn vector_widget(ui: &mut Ui, values: &mut [f32]) -> bool {
+        let mut changed = false;
+        for v in values.iter_mut() {
+            changed |= ui.add(DragValue::new(v).speed(0.01)).changed();
+        }
+        changed
+    }
+
+    fn camera_ui(ui: &mut Ui, engine: &Engine) {
+        let Some(camera) = engine.main_camera() else {
+            return;
+        };
+        let mut camera = camera.borrow_mut();
+
+        let mut position = *camera.position();
+        let mut pitch = camera.pitch();
+        let mut yaw = camera.yaw();
+
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("position");
+            changed |= Self::vector_widget(ui, AsMut::<[f32; 3]>::as_mut(&mut position));
+        });
+        changed |= ui
+            .add(
+                Slider::new(
+                    &mut pitch,
+                    -std::f32::consts::FRAC_PI_2..=std::f32::consts::FRAC_PI_2,
+                )
+                .text("pitch"),
+            )
+            .changed();
+        changed |= ui
+            .add(Slider::new(&mut yaw, -std::f32::consts::TAU..=std::f32::consts::TAU).text("yaw"))
+            .changed();
+
+        if changed {
+            camera.set_position(position);
+            camera.set_pitch(pitch);
+            camera.set_yaw(yaw);
+        }
+
+        let forward: Vector3<f32> = camera.forward();
+        ui.label(format!(
+            "forward: ({:.3}, {:.3}, {:.3})",
+            forward.x, forward.y, forward.z
+        ));
+        ui.label(format!("mode: {}", camera.mode()));
+
+        ui.collapsing("projection", |ui| {
+            let mut projection = *camera.projection();
+            let mut changed = false;
+            ui.horizontal(|ui| {
+                for kind in [ProjectionKind::Perspective, ProjectionKind::Orthographic] {
+                    changed |= ui
+                        .selectable_value(&mut projection.kind, kind, kind.to_string())
+                        .changed();
+                }
+            });
+            match projection.kind {
+                ProjectionKind::Perspective => {
+                    changed |= ui
+                        .add(Slider::new(&mut projection.fov, 0.1..=3.0).text("fov"))
+                        .changed();
+                }
+                ProjectionKind::Orthographic => {
+                    changed |= ui
+                        .add(
+                            Slider::new(&mut projection.ortho_height, 0.1..=100.0)
+                                .logarithmic(true)
+                                .text("height"),
+                        )
+                        .changed();
+                }
+            }
+            changed |= ui
+                .add(
+                    Slider::new(&mut projection.near, 0.001..=10.0)
+                        .logarithmic(true)
+                        .text("near"),
+                )
+                .changed();
+            changed |= ui
+                .add_enabled(
+                    !(projection.reversed_z && projection.kind == ProjectionKind::Perspective),
+                    Slider::new(&mut projection.far, 1.0..=10000.0)
+                        .logarithmic(true)
+                        .text("far"),
+                )
+                .changed();
+            changed |= ui
+                .checkbox(&mut projection.reversed_z, "reversed Z")
+                .changed();
+            if changed {
+                camera.set_projection(projection);
+            }
+        });
+
+        ui.collapsing("settings", |ui| {
+            let settings = camera.settings_mut();
+            ui.add(Slider::new(&mut settings.speed, 0.1..=20.0).text("speed"));
+            ui.add(
+                Slider::new(&mut settings.speed_multiplier, 0.05..=20.0)
+                    .logarithmic(true)
+                    .text("speed multiplier"),
+            );
+            ui.add(Slider::new(&mut settings.sprint_multiplier, 1.0..=10.0).text("sprint"));
+            ui.add(Slider::new(&mut settings.slow_multiplier, 0.01..=1.0).text("slow"));
+            ui.add(Slider::new(&mut settings.acceleration, 0.0..=50.0).text("acceleration"));
+            ui.add(Slider::new(&mut settings.damping, 0.0..=50.0).text("damping"));
+            ui.add(
+                Slider::new(&mut settings.mouse_sensitivity, 0.001..=0.1)
+                    .logarithmic(true)
+                    .text("mouse sensitivity"),
+            );
+            ui.add(
+                Slider::new(&mut settings.stick_look_speed, 0.1..=10.0).text("stick look speed"),
+            );
+            ui.add(Slider::new(&mut settings.mouse_smoothing, 0.0..=0.5).text("mouse smoothing"));
+            ui.checkbox(&mut settings.invert_y, "invert Y");
+        });
+    }
+
+    fn particle_systems_ui(ui: &mut Ui, engine: &Engine) {
+        for system in engine.particle_systems() {
+            let mut system = system.borrow_mut();
+            ui.collapsing(system.name().to_owned(), |ui| {
+                ui.label(format!("particles: {}", system.particle_count()));
+                let mut paused = system.is_paused();
+                if ui.checkbox(&mut paused, "paused").changed() {
+                    system.set_paused(paused);
+                }
+                let mut time_scale = system.time_scale();
+                if ui
+                    .add(Slider::new(&mut time_scale, 0.0..=4.0).text("time scale"))
+                    .changed()
+                {
+                    system.set_time_scale(time_scale);
+                }
+            });
+        }
+        if engine.particle_systems().is_empty() {
+            ui.colored_label(Color32::GRAY, "no particle system");
+        }
+    }
+}
+
+impl Drop for DebugUi {
+    fn drop(&mut self) {
+        self.painter.destroy();
+    }
+}
@@ -1,13 +1,27 @@
 use std::f32::consts::FRAC_PI_2;
 
-use gl::types::GLfloat;
+use gl::types::{GLfloat, GLint};
 
 pub const MODEL_TRANSFORM_UNIFORM_NAME: &str = "model_transform";
 pub const VIEW_TRANSFORM_UNIFORM_NAME: &str = "view_transform";
 pub const PROJECTION_UNIFORM_NAME: &str = "projection";
+/// Boolean telling mesh programs whether a texture is bound to unit 0
+pub const USE_TEXTURE_UNIFORM_NAME: &str = "use_texture";
+/// Boolean telling mesh programs whether shadows darken the drawable
+pub const RECEIVE_SHADOWS_UNIFORM_NAME: &str = "receive_shadows";
+/// Boolean telling particle display programs that they draw into the shadow maps, where only
+/// the fragments blocking light are kept
+pub const DEPTH_ONLY_UNIFORM_NAME: &str = "depth_only";
+/// Program drawing the depth of the meshes into the shadow maps
+pub const DEPTH_PROGRAM_NAME: &str = "depth";
+/// Vertex attribute locations read by the depth program
+pub const DEPTH_POSITION_LOCATION: GLint = 0;
+pub const DEPTH_INSTANCE_OFFSET_LOCATION: GLint = 4;
 pub const DEFAULT_FOV: f32 = FRAC_PI_2;
 pub const DEFAULT_ZNEAR: f32 = 0.5;
 pub const DEFAULT_ZFAR: f32 = 100.;
+/// Height of the view box of orthographic projections, in world units
+pub const DEFAULT_ORTHO_HEIGHT: f32 = 10.;
 pub const DEFAULT_ASPECT_RATIO: f32 = 1.0;
 
 pub const GLSL_VERSION_SRC: &'static str = "resources/shaders/version.glsl";
@@ -1,32 +1,76 @@
-use std::{cell::RefCell, collections::HashMap, f32::consts::PI, rc::Rc, time::Instant};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    f32::consts::{FRAC_PI_2, PI},
+    mem::offset_of,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Instant,
+};
 
-use cgmath::{Matrix4, PerspectiveFov, Point3, Rad, SquareMatrix, Vector3, Vector4};
+use bytemuck::{Pod, Zeroable};
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+use gl::types::GLfloat;
 
+use rand::{rngs::StdRng, SeedableRng};
 use sdl2::{
+    controller::GameController,
     event::{Event, WindowEvent},
-    keyboard::Keycode,
-    mouse::MouseButton,
     video::Window,
     EventPump,
 };
 
 use crate::{
+    bookmarks::{self, Bookmark, Bookmarks},
+    bounds::{Aabb, Frustum},
+    camera_path::CameraPath,
+    commands::{self, CommandRegistry, CommandResult},
+    console::Console,
+    debug_ui::DebugUi,
     definitions::{self, CUBE_VERTICES_BUFFER, GLSL_VERSION_SRC, MODEL_TRANSFORM_UNIFORM_NAME},
+    error::{EngineError, EngineResult},
+    extensions::SafeNormalize,
     gl_check, gl_checked,
+    gl_debug::{self, DebugGroup},
     gl_types::DrawMode,
-    input::InputState,
-    objects::{Camera, MeshRenderer, ParticleSystem},
-    particles::FireParticle,
+    gltf_scene::GltfScene,
+    hud::{Hud, HudSettings},
+    input::{bindings::Action, InputState},
+    lights::{LightBuffer, LightSet, MAX_LIGHTS},
+    obj::{self, Material, ObjMesh},
+    objects::{
+        Camera, CameraMode, CameraPointer, MeshRenderer, MeshRendererPointer, MeshSection,
+        ParticleLightSettings, ParticleLightSource, ParticleSystem, ParticleSystemPointer,
+    },
+    particles::{FireParticle, MAX_FIRE_LIFETIME},
+    primitives::{PrimitiveMesh, PrimitiveShape},
+    profiler::Profiler,
     program::{
         shader::{Shader, ShaderType},
         uniform::Uniform,
         Program,
     },
+    projection::{Projection, ProjectionKind},
+    remote::{self, RemoteEndpoint, RemoteServer},
+    replay::{RecordedEvent, RecordedFrame, Recorder, Replayer},
+    screenshot::save_screenshot,
+    shadows::{ShadowCaster, ShadowLayout, ShadowMaps, ShadowSettings, MAX_SHADOW_MAPS},
+    statistics::{FrameStatistics, RenderStatistics},
     traits::{Drawable, Updatable},
+    vertex_layout::{ComponentType, VertexAttribute, VertexLayout},
+    view::{DrawContext, View, ViewLayout, Viewport},
 };
 
 type UniformCollection = Vec<Rc<RefCell<Uniform>>>;
-type CameraPointer = Rc<RefCell<Camera>>;
+
+/// Per instance attributes of the cube grid
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CubeInstance {
+    offset: [GLfloat; 3],
+    /// Normalized to [0, 1] by the vertex layout
+    color: [u8; 4],
+}
 
 #[derive(Default)]
 pub struct Engine {
@@ -35,22 +79,75 @@ pub struct Engine {
     window: Option<Window>,
     _gl_context: Option<sdl2::video::GLContext>,
     pump: Option<EventPump>,
+    game_controller_subsystem: Option<sdl2::GameControllerSubsystem>,
+    /// Opened game controllers, by joystick instance id
+    controllers: HashMap<u32, GameController>,
     programs: HashMap<String, Rc<RefCell<Program>>>,
     last_frame_time: Option<Instant>,
     updatables: Vec<Rc<RefCell<dyn Updatable>>>,
     drawables: Vec<Rc<RefCell<dyn Drawable>>>,
     view_transform_uniforms: UniformCollection,
     projection_uniforms: UniformCollection,
+    /// Camera moved by the input, and by the camera path and bookmarks
     main_camera: Option<CameraPointer>,
+    /// Cameras rendered into the window, in order
+    views: Vec<View>,
+    /// Cameras imported from scenes, by name
+    scene_cameras: Vec<(String, CameraPointer)>,
+    /// Views created at initialization
+    view_layout: ViewLayout,
+    /// Whether drawables out of a view's frustum are skipped
+    culling: bool,
+    profiler: RefCell<Profiler>,
+    window_size: (i32, i32),
+    statistics: RefCell<FrameStatistics>,
+    hud_settings: HudSettings,
+    hud: Option<RefCell<Hud>>,
+    debug_ui: Option<RefCell<DebugUi>>,
+    particle_systems: Vec<ParticleSystemPointer>,
+    commands: CommandRegistry,
+    console: Option<RefCell<Console>>,
+    paused: bool,
+    pending_steps: u32,
+    pending_screenshot: RefCell<Option<PathBuf>>,
+    remote: Option<RemoteServer>,
+    seed: Option<u64>,
+    recorder: Option<Recorder>,
+    replayer: Option<Replayer>,
+    camera_path: CameraPath,
+    /// File the camera path was loaded from, where it is saved by default
+    camera_path_file: Option<PathBuf>,
+    /// Time along the camera path while it is played
+    camera_path_time: Option<f32>,
+    bookmarks: Bookmarks,
+    /// File the camera bookmarks are loaded from and saved to
+    bookmarks_file: Option<PathBuf>,
+    /// Projection of the main camera when created
+    projection: Projection,
+    /// Number of cubes along each side of the instanced grid, none being drawn if 0
+    cube_grid: u32,
+    lights: LightSet,
+    light_buffer: Option<LightBuffer>,
+    shadow_settings: ShadowSettings,
+    shadow_maps: Option<ShadowMaps>,
 }
 
 static mut INSTANCE: Option<Engine> = None;
 
+const DEFAULT_WINDOW_SIZE: (u32, u32) = (1024, 1024);
+
 pub static mut BROKEN_RELATIVE_MOUSE_MODE: bool = false;
 
+pub static mut GL_DEBUG_CONTEXT: bool = true;
+
 impl Engine {
     fn new() -> Self {
+        let mut commands = CommandRegistry::default();
+        commands::register_builtins(&mut commands);
         Self {
+            window_size: (DEFAULT_WINDOW_SIZE.0 as _, DEFAULT_WINDOW_SIZE.1 as _),
+            commands,
+            culling: true,
             ..Default::default()
         }
     }
@@ -63,38 +160,468 @@ impl Engine {
         self.main_camera.as_ref()
     }
 
-    fn _init_sdl(&mut self) {
-        let sdl = sdl2::init().expect("Couldn't initialize SDL2.");
-        let video_subsystem = sdl.video().expect("Couldn't create video system.");
+    /// Lights of the `mesh` program's drawables
+    pub fn lights(&self) -> &LightSet {
+        &self.lights
+    }
+
+    pub fn lights_mut(&mut self) -> &mut LightSet {
+        &mut self.lights
+    }
+
+    pub fn shadow_settings(&self) -> &ShadowSettings {
+        &self.shadow_settings
+    }
+
+    /// Replaces the shadow settings, reallocating the shadow maps if their size changed
+    pub fn set_shadow_settings(&mut self, mut settings: ShadowSettings) -> EngineResult<()> {
+        settings.sanitize();
+        let resized = self
+            .shadow_maps
+            .as_ref()
+            .is_some_and(|maps| maps.size() != settings.map_size);
+        if resized {
+            // the previous maps are released before allocating the new ones
+            self.shadow_maps = None;
+            self.shadow_maps = Some(ShadowMaps::new(settings.map_size)?);
+        }
+        self.shadow_settings = settings;
+        Ok(())
+    }
+
+    /// Drawable named `name`, e.g. a mesh or particle system
+    pub fn drawable(&self, name: &str) -> Option<&Rc<RefCell<dyn Drawable>>> {
+        self.drawables.iter().find(|d| d.borrow().name() == name)
+    }
+
+    pub fn views(&self) -> &[View] {
+        &self.views
+    }
+
+    pub fn view_mut(&mut self, name: &str) -> Option<&mut View> {
+        self.views.iter_mut().find(|v| v.name() == name)
+    }
+
+    /// Adds a view drawn on top of the existing ones, replacing any view of the same name
+    pub fn add_view(&mut self, view: View) {
+        match self.views.iter_mut().find(|v| v.name() == view.name()) {
+            Some(existing) => *existing = view,
+            None => self.views.push(view),
+        }
+    }
+
+    /// Removes a view unless it is the last one, the input then controls the first view left if
+    /// it controlled the removed one
+    pub fn remove_view(&mut self, name: &str) -> Result<View, String> {
+        let index = self
+            .views
+            .iter()
+            .position(|v| v.name() == name)
+            .ok_or_else(|| format!("no view `{name}`"))?;
+        if self.views.len() == 1 {
+            return Err("the last view can't be removed".to_owned());
+        }
+        let view = self.views.remove(index);
+        let controlled = self
+            .main_camera
+            .as_ref()
+            .is_some_and(|c| Rc::ptr_eq(c, view.camera()));
+        let still_shown = self
+            .views
+            .iter()
+            .any(|v| Rc::ptr_eq(v.camera(), view.camera()));
+        if controlled && !still_shown {
+            self.main_camera = Some(self.views[0].camera().clone());
+        }
+        Ok(view)
+    }
+
+    /// Makes the input control the camera of the named view
+    pub fn control_view(&mut self, name: &str) -> bool {
+        let Some(view) = self.views.iter().find(|v| v.name() == name) else {
+            return false;
+        };
+        self.main_camera = Some(view.camera().clone());
+        true
+    }
+
+    /// Sets the views created at initialization, to be called before [`Engine::init`]
+    pub fn set_view_layout(&mut self, layout: ViewLayout) {
+        self.view_layout = layout;
+    }
+
+    /// Replaces the views by those of a layout, the main camera filling or splitting the window
+    pub fn apply_view_layout(&mut self, layout: ViewLayout) {
+        let Some(main_camera) = self.main_camera.clone() else {
+            return;
+        };
+        let side_camera = || {
+            let mut camera = main_camera.borrow().clone();
+            camera.set_position(Point3::new(0., -3.5, 0.));
+            camera.set_pitch(0.);
+            camera.set_yaw(FRAC_PI_2);
+            Rc::new(RefCell::new(camera))
+        };
+        let top_camera = || {
+            let mut camera = main_camera.borrow().clone();
+            camera.set_position(Point3::new(0., 0., 10.));
+            camera.set_pitch(-FRAC_PI_2);
+            camera.set_yaw(PI);
+            camera.set_projection(Projection {
+                kind: ProjectionKind::Orthographic,
+                ortho_height: 6.,
+                ..*camera.projection()
+            });
+            Rc::new(RefCell::new(camera))
+        };
+        self.views = match layout {
+            ViewLayout::Single => vec![View::new("main", main_camera.clone(), Viewport::FULL)],
+            ViewLayout::Split => vec![
+                View::new("main", main_camera.clone(), Viewport::new(0., 0., 0.5, 1.)),
+                View::new("side", side_camera(), Viewport::new(0.5, 0., 0.5, 1.)),
+            ],
+            ViewLayout::PictureInPicture => vec![
+                View::new("main", main_camera.clone(), Viewport::FULL),
+                View::new("top", top_camera(), Viewport::new(0.7, 0.7, 0.28, 0.28)),
+            ],
+        };
+    }
+
+    pub fn programs(&self) -> &HashMap<String, Rc<RefCell<Program>>> {
+        &self.programs
+    }
+
+    pub fn drawables(&self) -> &[Rc<RefCell<dyn Drawable>>] {
+        &self.drawables
+    }
+
+    /// Bounds of the named drawable, if it has some
+    pub fn drawable_bounds(&self, name: &str) -> Option<Aabb> {
+        self.drawables
+            .iter()
+            .map(|d| d.borrow())
+            .find(|d| d.name() == name)
+            .and_then(|d| d.bounds())
+    }
+
+    /// Bounds of the drawable closest to the center of the main camera's view
+    pub fn looked_at_bounds(&self) -> Option<Aabb> {
+        let camera = self.main_camera.as_ref()?.borrow();
+        let forward = camera.forward();
+        self.drawables
+            .iter()
+            .filter_map(|d| d.borrow().bounds())
+            .map(|bounds| {
+                let direction = (bounds.center() - camera.position()).safe_normalize();
+                (bounds, direction.dot(forward))
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(bounds, _)| bounds)
+    }
+
+    pub fn particle_systems(&self) -> &[ParticleSystemPointer] {
+        &self.particle_systems
+    }
+
+    pub fn particle_system(&self, name: &str) -> Option<&ParticleSystemPointer> {
+        self.particle_systems
+            .iter()
+            .find(|s| s.borrow().name() == name)
+    }
+
+    pub fn commands(&self) -> &CommandRegistry {
+        &self.commands
+    }
+
+    /// Makes a command available to the console, replacing any command with the same name
+    pub fn register_command(
+        &mut self,
+        name: &str,
+        usage: &'static str,
+        help: &'static str,
+        handler: impl Fn(&mut Engine, &[&str]) -> CommandResult + 'static,
+    ) {
+        self.commands.register(name, usage, help, handler);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses the update of every pausable object, see [`Updatable::pausable`]
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.pending_steps = 0;
+    }
+
+    pub fn is_culling(&self) -> bool {
+        self.culling
+    }
+
+    /// Skips drawing the drawables whose bounds are out of the view, see [`Drawable::bounds`]
+    pub fn set_culling(&mut self, culling: bool) {
+        self.culling = culling;
+    }
+
+    /// Pauses the simulation after updating it for `frames` more frames
+    pub fn step(&mut self, frames: u32) {
+        self.paused = true;
+        self.pending_steps += frames;
+    }
+
+    /// Saves the next rendered frame, without overlays, to a PNG file
+    pub fn request_screenshot(&self, path: PathBuf) {
+        *self.pending_screenshot.borrow_mut() = Some(path);
+    }
+
+    pub fn profiler(&self) -> &RefCell<Profiler> {
+        &self.profiler
+    }
+
+    pub fn statistics(&self) -> &RefCell<FrameStatistics> {
+        &self.statistics
+    }
+
+    /// Starts the remote control server, whose requests are executed by [`Engine::process_remote`]
+    pub fn listen(&mut self, endpoint: &RemoteEndpoint) -> EngineResult<()> {
+        self.remote = Some(RemoteServer::bind(endpoint)?);
+        Ok(())
+    }
+
+    /// Sets the seed of the random number generators, to be called before [`Engine::init`]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    /// Seed of the random number generators, picked randomly unless set
+    pub fn seed(&mut self) -> u64 {
+        *self.seed.get_or_insert_with(rand::random)
+    }
+
+    /// Records every frame's input and time step to a file, see [`Engine::start_replay`]
+    pub fn start_recording(&mut self, path: &Path) -> EngineResult<()> {
+        let seed = self.seed();
+        self.recorder = Some(Recorder::create(path, seed)?);
+        Ok(())
+    }
+
+    /// Feeds a recording's input and time steps to the engine instead of the live ones, to be
+    /// called before [`Engine::init`] so that the recording's seed is used.
+    pub fn start_replay(&mut self, path: &Path, exit_at_end: bool) -> EngineResult<()> {
+        let replayer = Replayer::load(path, exit_at_end)?;
+        self.seed = Some(replayer.seed());
+        self.replayer = Some(replayer);
+        Ok(())
+    }
+
+    pub fn camera_path(&self) -> &CameraPath {
+        &self.camera_path
+    }
+
+    pub fn camera_path_mut(&mut self) -> &mut CameraPath {
+        &mut self.camera_path
+    }
+
+    /// Replaces the camera path by the one saved in a file, which becomes its default save file
+    pub fn load_camera_path(&mut self, path: &Path) -> EngineResult<()> {
+        self.camera_path = CameraPath::load(path)?;
+        self.camera_path_file = Some(path.to_owned());
+        log::info!(
+            "loaded {} camera keyframes from `{}`",
+            self.camera_path.keyframes().len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    /// File the camera path was loaded from or last saved to
+    pub fn camera_path_file(&self) -> Option<&Path> {
+        self.camera_path_file.as_deref()
+    }
+
+    /// Saves the camera path to a file, which becomes its default save file
+    pub fn save_camera_path(&mut self, path: &Path) -> EngineResult<()> {
+        self.camera_path.save(path)?;
+        self.camera_path_file = Some(path.to_owned());
+        Ok(())
+    }
+
+    /// Adds a keyframe at the main camera's pose to the camera path
+    pub fn record_keyframe(&mut self) {
+        let Some(camera) = self.main_camera.as_ref() else {
+            return;
+        };
+        let time = self.camera_path.record(&camera.borrow()).time;
+        log::info!(
+            "camera keyframe {} recorded at {time}s",
+            self.camera_path.keyframes().len()
+        );
+    }
+
+    pub fn is_playing_camera_path(&self) -> bool {
+        self.camera_path_time.is_some()
+    }
+
+    /// Starts playing the camera path in a loop from its beginning, or stops it
+    pub fn play_camera_path(&mut self, play: bool) {
+        self.camera_path_time = if play && !self.camera_path.keyframes().is_empty() {
+            if let Some(camera) = self.main_camera.as_ref() {
+                camera.borrow_mut().set_mode(CameraMode::FreeFly);
+            }
+            Some(self.camera_path.start_time())
+        } else {
+            None
+        };
+    }
+
+    /// Moves the main camera along the camera path while it is played
+    fn _update_camera_path(&mut self, delta: f32) {
+        let Some(time) = self.camera_path_time.as_mut() else {
+            return;
+        };
+        let (start, duration) = (self.camera_path.start_time(), self.camera_path.duration());
+        *time += delta;
+        if *time > start + duration {
+            *time = if duration > 0. {
+                start + (*time - start) % duration
+            } else {
+                start
+            };
+        }
+        let (Some(pose), Some(camera)) =
+            (self.camera_path.sample(*time), self.main_camera.as_ref())
+        else {
+            return;
+        };
+        pose.apply(&mut camera.borrow_mut());
+    }
+
+    /// Loads the camera bookmarks of a file, where they are saved when changed, or the shared
+    /// ones until the file exists
+    pub fn load_bookmarks(&mut self, path: &Path) -> EngineResult<()> {
+        let source = if path.exists() {
+            path
+        } else {
+            Path::new(bookmarks::DEFAULT_BOOKMARKS_FILE)
+        };
+        self.bookmarks = Bookmarks::load(source)?;
+        self.bookmarks_file = Some(path.to_owned());
+        Ok(())
+    }
+
+    pub fn bookmarks(&self) -> &Bookmarks {
+        &self.bookmarks
+    }
+
+    fn _save_bookmarks(&self) -> EngineResult<()> {
+        match self.bookmarks_file.as_ref() {
+            Some(path) => self.bookmarks.save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Bookmarks the main camera's pose, recalled by a number key if `slot` is given
+    pub fn add_bookmark(&mut self, name: &str, slot: Option<u8>) -> EngineResult<()> {
+        let Some(camera) = self.main_camera.as_ref() else {
+            return Ok(());
+        };
+        let bookmark = Bookmark::from_camera(&camera.borrow(), slot);
+        self.bookmarks.insert(name, bookmark);
+        self._save_bookmarks()
+    }
+
+    /// Removes a bookmark, returns whether it existed
+    pub fn remove_bookmark(&mut self, name: &str) -> EngineResult<bool> {
+        let removed = self.bookmarks.remove(name).is_some();
+        self._save_bookmarks()?;
+        Ok(removed)
+    }
+
+    /// Moves the main camera to a bookmarked pose, returns whether the bookmark exists
+    pub fn go_to_bookmark(&mut self, name: &str) -> bool {
+        let (Some(bookmark), Some(camera)) = (self.bookmarks.get(name), self.main_camera.as_ref())
+        else {
+            return false;
+        };
+        let mut camera = camera.borrow_mut();
+        camera.set_mode(CameraMode::FreeFly);
+        bookmark.pose().apply(&mut camera);
+        self.camera_path_time = None;
+        true
+    }
+
+    /// Recalls the bookmark of a number key, or stores the camera's pose in it
+    fn _use_bookmark_slot(&mut self, slot: u8, store: bool) {
+        let name = self.bookmarks.get_slot(slot).map(|(name, _)| name.clone());
+        if store {
+            let name = name.unwrap_or_else(|| slot.to_string());
+            match self.add_bookmark(&name, Some(slot)) {
+                Ok(()) => log::info!("camera bookmark `{name}` saved on key {slot}"),
+                Err(error) => log::error!("couldn't save the bookmarks: {}", error.chain()),
+            }
+        } else if let Some(name) = name {
+            self.go_to_bookmark(&name);
+            log::info!("camera moved to bookmark `{name}`");
+        }
+    }
+
+    /// Sets the main camera's projection, to be called before [`Engine::init`]
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    /// Sets the size of the instanced grid of cubes, to be called before [`Engine::init`]
+    pub fn set_cube_grid(&mut self, size: u32) {
+        self.cube_grid = size;
+    }
+
+    /// Sets the statistics overlay configuration, to be called before [`Engine::init`]
+    pub fn set_hud_settings(&mut self, settings: HudSettings) {
+        self.hud_settings = settings;
+    }
+
+    fn _init_sdl(&mut self) -> EngineResult<()> {
+        let sdl = sdl2::init().map_err(EngineError::Window)?;
+        let video_subsystem = sdl.video().map_err(EngineError::Window)?;
         let gl_attr = video_subsystem.gl_attr();
         gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
         gl_attr.set_context_version(4, 5);
+        if unsafe { GL_DEBUG_CONTEXT } {
+            gl_attr.set_context_flags().debug().set();
+        }
 
         let window = video_subsystem
-            .window("POGLA project", 1024, 1024)
+            .window("POGLA project", DEFAULT_WINDOW_SIZE.0, DEFAULT_WINDOW_SIZE.1)
             .resizable()
             .opengl()
             .build()
-            .expect("Couldn't create window");
+            .map_err(|e| EngineError::Window(e.to_string()))?;
 
-        let _gl_context = window
-            .gl_create_context()
-            .expect("Couldn't create openGL context");
+        let _gl_context = window.gl_create_context().map_err(EngineError::Context)?;
 
         gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
 
-        let pump = sdl
-            .event_pump()
-            .expect("SDL Event pump creation failed, one may already exist.");
+        let pump = sdl.event_pump().map_err(EngineError::Window)?;
+
+        // controllers are optional, they are opened as their connection events arrive
+        match sdl.game_controller() {
+            Ok(subsystem) => self.game_controller_subsystem = Some(subsystem),
+            Err(e) => log::warn!("game controller support unavailable: {e}"),
+        }
 
         self.sdl = Some(sdl);
         self.video_subsystem = Some(video_subsystem);
         self.window = Some(window);
         self.pump = Some(pump);
         self._gl_context = Some(_gl_context);
+        Ok(())
     }
 
     fn _init_gl(&mut self) {
+        if unsafe { GL_DEBUG_CONTEXT } {
+            gl_debug::enable_debug_output();
+        }
+
         unsafe {
             const PIXEL_BYTE_ALIGNMENT_LEN: i32 = 1; // 1 byte for 8-bit deep color
             gl_checked! {
@@ -109,29 +636,19 @@ impl Engine {
         }
     }
 
-    fn _init_shaders(&mut self) {
+    fn _init_shaders(&mut self) -> EngineResult<()> {
         let program = Program::builder("basic")
             .add_shader(
                 "basic_vertex",
                 Shader::new(ShaderType::Vertex)
-                    .load("resources/shaders/basic.vert")
-                    .expect("Couldn't read basic_shader vertex file"),
+                    .load("resources/shaders/basic.vert")?,
             )
             .add_shader(
                 "basic_frag",
                 Shader::new(ShaderType::Fragment)
-                    .load("resources/shaders/basic.frag")
-                    .expect("Couldn't read basic_shader fragment file"),
+                    .load("resources/shaders/basic.frag")?,
             )
-            .build();
-
-        let program = match program {
-            Ok(prog) => prog,
-            Err(error) => {
-                error.log_error();
-                panic!("program creation failed");
-            }
-        };
+            .build()?;
 
         self.register_program("basic", program);
 
@@ -139,24 +656,14 @@ impl Engine {
             .add_shader(
                 "uniform_vertex",
                 Shader::new(ShaderType::Vertex)
-                    .load("resources/shaders/uniform/uniform.vert")
-                    .unwrap(),
+                    .load("resources/shaders/uniform/uniform.vert")?,
             )
             .add_shader(
                 "uniform_fragment",
                 Shader::new(ShaderType::Fragment)
-                    .load("resources/shaders/uniform/uniform.frag")
-                    .unwrap(),
+                    .load("resources/shaders/uniform/uniform.frag")?,
             )
-            .build();
-
-        let program = match program {
-            Ok(prog) => prog,
-            Err(error) => {
-                error.log_error();
-                panic!("program creation failed");
-            }
-        };
+            .build()?;
 
         program
             .borrow()
@@ -185,38 +692,23 @@ impl Engine {
             .add_shader(
                 "vertex",
                 Shader::new(ShaderType::Vertex)
-                    .load(GLSL_VERSION_SRC)
-                    .unwrap()
-                    .load("resources/shaders/fire_particle/fire.vert.glsl")
-                    .unwrap(),
+                    .load(GLSL_VERSION_SRC)?
+                    .load("resources/shaders/fire_particle/fire.vert.glsl")?,
             )
             .add_shader(
                 "geometry",
                 Shader::new(ShaderType::Geometry)
-                    .load(GLSL_VERSION_SRC)
-                    .unwrap()
-                    .load("resources/shaders/particle_helpers.glsl")
-                    .unwrap()
-                    .load("resources/shaders/fire_particle/fire.geom.glsl")
-                    .unwrap(),
+                    .load(GLSL_VERSION_SRC)?
+                    .load("resources/shaders/particle_helpers.glsl")?
+                    .load("resources/shaders/fire_particle/fire.geom.glsl")?,
             )
             .add_shader(
                 "fragment",
                 Shader::new(ShaderType::Fragment)
-                    .load(GLSL_VERSION_SRC)
-                    .unwrap()
-                    .load("resources/shaders/fire_particle/fire.frag.glsl")
-                    .unwrap(),
+                    .load(GLSL_VERSION_SRC)?
+                    .load("resources/shaders/fire_particle/fire.frag.glsl")?,
             )
-            .build();
-
-        let program = match program {
-            Ok(p) => p,
-            Err(e) => {
-                e.log_error();
-                panic!("fire particle display program compilation failed");
-            }
-        };
+            .build()?;
 
         {
             let p = program.borrow();
@@ -235,11 +727,15 @@ impl Engine {
             p.uniform("max_fire_lifetime")
                 .unwrap()
                 .borrow_mut()
-                .set_float(1.0);
+                .set_float(MAX_FIRE_LIFETIME);
             p.uniform("max_smoke_lifetime")
                 .unwrap()
                 .borrow_mut()
                 .set_float(3.0);
+            p.uniform("smoke_shadow_opacity")
+                .unwrap()
+                .borrow_mut()
+                .set_float(0.6);
             p.uniform(MODEL_TRANSFORM_UNIFORM_NAME)
                 .unwrap()
                 .borrow_mut()
@@ -247,40 +743,349 @@ impl Engine {
         }
 
         self.register_program("fire_display", program);
+
+        let program = Program::builder("mesh")
+            .add_shader(
+                "vertex",
+                Shader::new(ShaderType::Vertex)
+                    .load(GLSL_VERSION_SRC)?
+                    .load("resources/shaders/mesh/mesh.vert.glsl")?,
+            )
+            .add_shader(
+                "fragment",
+                Shader::new(ShaderType::Fragment)
+                    .load(GLSL_VERSION_SRC)?
+                    .source(&format!(
+                        "const int MAX_LIGHTS = {MAX_LIGHTS};\nconst int MAX_SHADOW_MAPS = {MAX_SHADOW_MAPS};\n"
+                    ))
+                    .load("resources/shaders/shadows.glsl")?
+                    .load("resources/shaders/lights.glsl")?
+                    .load("resources/shaders/mesh/mesh.frag.glsl")?,
+            )
+            .build()?;
+        self.register_program("mesh", program);
+
+        let program = Program::builder(definitions::DEPTH_PROGRAM_NAME)
+            .add_shader(
+                "vertex",
+                Shader::new(ShaderType::Vertex)
+                    .load(GLSL_VERSION_SRC)?
+                    .load("resources/shaders/depth/depth.vert.glsl")?,
+            )
+            .add_shader(
+                "fragment",
+                Shader::new(ShaderType::Fragment)
+                    .load(GLSL_VERSION_SRC)?
+                    .load("resources/shaders/depth/depth.frag.glsl")?,
+            )
+            .build()?;
+        self.register_program(definitions::DEPTH_PROGRAM_NAME, program);
+        Ok(())
+    }
+
+    /// Loads an OBJ file as a drawable named after the file, returning that name
+    pub fn load_mesh(&mut self, path: &Path) -> EngineResult<String> {
+        let mesh = ObjMesh::load(path)?;
+        let name = self.unique_drawable_name(path, "mesh");
+        let renderer = mesh
+            .renderer_builder(self.programs["mesh"].clone())
+            .name(name.clone())
+            .build()?;
+        self.register_renderer(renderer);
+        log::info!(
+            "loaded `{}`: {} vertices, {} triangles",
+            path.display(),
+            mesh.vertex_count(),
+            mesh.indices.len() / 3
+        );
+        Ok(name)
+    }
+
+    /// Loads the meshes and cameras of a glTF file, named `file/node`, returning the names of
+    /// the drawables and cameras added
+    pub fn load_scene(&mut self, path: &Path) -> EngineResult<(Vec<String>, Vec<String>)> {
+        let scene = GltfScene::load(path, self.projection)?;
+        let prefix = self.unique_drawable_name(path, "scene");
+        let renderers = scene.renderers(&prefix, self.programs["mesh"].clone())?;
+        let drawables: Vec<String> = renderers
+            .iter()
+            .map(|r| r.borrow().name().to_owned())
+            .collect();
+        for renderer in renderers {
+            self.register_renderer(renderer);
+        }
+        let mut cameras = Vec::new();
+        for (name, camera) in scene.cameras.iter() {
+            let name = format!("{prefix}/{name}");
+            cameras.push(name.clone());
+            self.scene_cameras
+                .push((name, Rc::new(RefCell::new(camera.clone()))));
+        }
+        log::info!(
+            "loaded `{}`: {} drawables, {} triangles, {} cameras",
+            path.display(),
+            drawables.len(),
+            scene.triangle_count(),
+            cameras.len()
+        );
+        Ok((drawables, cameras))
+    }
+
+    /// Adds a generated shape 4 units in front of the main camera, `detail` scaling its
+    /// tessellation, returning the name of its drawable
+    pub fn add_shape(&mut self, shape: PrimitiveShape, detail: u32) -> EngineResult<String> {
+        let mesh = shape.generate(detail);
+        let position = self
+            .main_camera
+            .as_ref()
+            .map(|camera| {
+                let camera = camera.borrow();
+                camera.position() + camera.forward() * 4.
+            })
+            .unwrap_or_else(|| Point3::new(0., 0., 0.));
+        let name = self.unique_drawable_name(Path::new(shape.name()), "shape");
+        let renderer = mesh
+            .renderer_builder(
+                self.programs["mesh"].clone(),
+                Vector4::new(0.8, 0.8, 0.8, 1.),
+            )
+            .name(name.clone())
+            .transform(Matrix4::from_translation(position.to_vec()))
+            .build()?;
+        self.register_renderer(renderer);
+        log::info!(
+            "added {} `{name}`: {} vertices, {} triangles",
+            shape.name(),
+            mesh.vertex_count(),
+            mesh.triangle_count()
+        );
+        Ok(name)
+    }
+
+    /// File stem of `path`, suffixed if a drawable already has it as name or prefix so that
+    /// drawables can be found by name
+    fn unique_drawable_name(&self, path: &Path, default: &str) -> String {
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| default.to_owned());
+        let taken = |name: &str| {
+            self.drawables.iter().any(|d| {
+                let d = d.borrow();
+                d.name() == name || d.name().starts_with(&format!("{name}/"))
+            })
+        };
+        let mut name = stem.clone();
+        let mut suffix = 1;
+        while taken(&name) {
+            suffix += 1;
+            name = format!("{stem}_{suffix}");
+        }
+        name
+    }
+
+    /// Cameras imported from scenes, which views can show
+    pub fn scene_cameras(&self) -> &[(String, CameraPointer)] {
+        &self.scene_cameras
+    }
+
+    /// Shows the camera of a view or an imported camera in a view, which the input keeps
+    /// controlling if it did
+    pub fn set_view_camera(&mut self, view: &str, camera: &str) -> Result<(), String> {
+        let camera = self
+            .find_camera(camera)
+            .ok_or_else(|| format!("no view or scene camera `{camera}`"))?;
+        let main_camera = self.main_camera.clone();
+        let view = self
+            .view_mut(view)
+            .ok_or_else(|| format!("no view `{view}`"))?;
+        let controlled = main_camera.is_some_and(|c| Rc::ptr_eq(&c, view.camera()));
+        view.set_camera(camera.clone());
+        if controlled {
+            self.main_camera = Some(camera);
+        }
+        Ok(())
+    }
+
+    /// Camera of the named view, or imported camera of that name
+    pub fn find_camera(&self, name: &str) -> Option<CameraPointer> {
+        self.views
+            .iter()
+            .find(|v| v.name() == name)
+            .map(|v| v.camera())
+            .or_else(|| {
+                self.scene_cameras
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .map(|(_, c)| c)
+            })
+            .cloned()
+    }
+
+    fn _init_hud(&mut self) -> EngineResult<()> {
+        let program = Program::builder("hud_text")
+            .add_shader(
+                "vertex",
+                Shader::new(ShaderType::Vertex)
+                    .load(GLSL_VERSION_SRC)?
+                    .load("resources/shaders/hud/text.vert.glsl")?,
+            )
+            .add_shader(
+                "fragment",
+                Shader::new(ShaderType::Fragment)
+                    .load(GLSL_VERSION_SRC)?
+                    .load("resources/shaders/hud/text.frag.glsl")?,
+            )
+            .build()?;
+
+        self.register_program("hud_text", program.clone());
+
+        let console = Console::new(program.clone(), self.hud_settings.scale)?;
+        self.console = Some(RefCell::new(console));
+
+        let hud = Hud::new(std::mem::take(&mut self.hud_settings), program)?;
+        self.hud = Some(RefCell::new(hud));
+        Ok(())
+    }
+
+    /// Grid of `size` by `size` cubes sharing their 8 vertices, drawn in one instanced call with
+    /// a position and color per instance
+    fn cube_grid_renderer(&self, size: u32) -> EngineResult<MeshRendererPointer> {
+        let mut positions: Vec<GLfloat> = Vec::new();
+        let mut indices: Vec<u16> = Vec::new();
+        for corner in CUBE_VERTICES_BUFFER.chunks_exact(3) {
+            let index = positions
+                .chunks_exact(3)
+                .position(|p| p == corner)
+                .unwrap_or_else(|| {
+                    positions.extend_from_slice(corner);
+                    positions.len() / 3 - 1
+                });
+            indices.push(index as u16);
+        }
+        // corner normals, giving the cubes a rounded shading
+        let normals: Vec<GLfloat> = positions.iter().map(|v| v / 3f32.sqrt()).collect();
+
+        const SPACING: GLfloat = 3.;
+        let half_extent = (size - 1) as GLfloat * SPACING / 2.;
+        let instances: Vec<CubeInstance> = (0..size)
+            .flat_map(|x| (0..size).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                let (u, v) = (
+                    x as GLfloat / size.max(2) as GLfloat,
+                    y as GLfloat / size.max(2) as GLfloat,
+                );
+                CubeInstance {
+                    offset: [
+                        x as GLfloat * SPACING - half_extent,
+                        y as GLfloat * SPACING - half_extent,
+                        0.,
+                    ],
+                    color: [
+                        (80. + 175. * u) as u8,
+                        (80. + 175. * v) as u8,
+                        (255. - 127. * (u + v)) as u8,
+                        255,
+                    ],
+                }
+            })
+            .collect();
+        let instance_layout = VertexLayout::of::<CubeInstance>()
+            .attribute(VertexAttribute::float("instance_offset", 3))
+            .attribute(
+                VertexAttribute::new("color", 4, ComponentType::U8)
+                    .normalized()
+                    .offset(offset_of!(CubeInstance, color)),
+            );
+
+        MeshRenderer::builder()
+            .name("cube_grid")
+            .shader(self.programs["mesh"].clone())
+            .add_buffer(positions)
+            .add_attribute("position", 3, 0)
+            .add_buffer(normals)
+            .add_attribute("normal", 3, 1)
+            .add_vertex_buffer(&instances, instance_layout)
+            .instance_divisor(2, 1)
+            .add_section(MeshSection {
+                start: 0,
+                count: indices.len(),
+                uniforms: Material::with_color("cube_grid", Vector4::new(1., 1., 1., 1.))
+                    .uniforms(),
+                texture: None,
+            })
+            .indices(indices)
+            .bounds(Aabb::new(
+                Point3::new(-half_extent - 1., -half_extent - 1., -1.),
+                Point3::new(half_extent + 1., half_extent + 1., 1.),
+            ))
+            .transform(
+                Matrix4::from_translation(Vector3::new(0., 0., -4.)) * Matrix4::from_scale(0.25),
+            )
+            .build()
+    }
+
+    fn _init_debug_ui(&mut self) -> EngineResult<()> {
+        let debug_ui = DebugUi::new(self.video_subsystem.as_ref().unwrap())?;
+        self.debug_ui = Some(RefCell::new(debug_ui));
+        Ok(())
     }
 
-    fn _init_objects(&mut self) {
+    fn _init_objects(&mut self) -> EngineResult<()> {
+        self.light_buffer = Some(LightBuffer::new());
+        self.shadow_maps = Some(ShadowMaps::new(self.shadow_settings.map_size)?);
+
+        // ground under the cube and the cube grid, receiving their shadows and the smoke's
+        let ground = PrimitiveMesh::plane(20., 20., 1, 1)
+            .renderer_builder(
+                self.programs["mesh"].clone(),
+                Vector4::new(0.6, 0.6, 0.6, 1.),
+            )
+            .name("ground")
+            .casts_shadows(false)
+            .transform(Matrix4::from_translation(Vector3::new(0., 0., -4.25)))
+            .build()?;
+        self.register_renderer(ground);
+
+        // the triangles share no vertex, giving each face its own normal
+        let cube_indices: Vec<u32> = (0..CUBE_VERTICES_BUFFER.len() as u32 / 3).collect();
         let triangle_renderer = MeshRenderer::builder()
-            .shader(self.programs.get("uniform").unwrap().clone())
+            .name("cube")
+            .shader(self.programs["mesh"].clone())
             .add_buffer(Vec::from(CUBE_VERTICES_BUFFER.as_slice()))
             .add_attribute("position", 3, 0)
+            .add_buffer(obj::smooth_normals(&CUBE_VERTICES_BUFFER, &cube_indices))
+            .add_attribute("normal", 3, 1)
+            .add_section(MeshSection {
+                start: 0,
+                count: cube_indices.len(),
+                uniforms: Material::with_color("cube", Vector4::new(1., 0., 0., 1.)).uniforms(),
+                texture: None,
+            })
             .draw_mode(DrawMode::Triangles)
             .transform(Matrix4::from_translation(Vector3::new(0.0, 0.0, -1.0)))
-            .build();
+            .build()?;
         self.register_renderer(triangle_renderer);
 
+        if self.cube_grid > 0 {
+            let cube_grid = self.cube_grid_renderer(self.cube_grid)?;
+            self.register_renderer(cube_grid);
+        }
+
         // fire particle system
         {
+            let mut rng = StdRng::seed_from_u64(self.seed());
             let particle_count = 16_000;
+            let max_lifetime = 4.0;
             let compute_program = {
                 let program = Program::builder("fire_compute")
                     .add_shader(
                         "compute",
                         Shader::new(ShaderType::Compute)
-                            .load(GLSL_VERSION_SRC)
-                            .unwrap()
-                            .load("resources/shaders/fire_particle/fire.compute.glsl")
-                            .unwrap(),
+                            .load(GLSL_VERSION_SRC)?
+                            .load("resources/shaders/fire_particle/fire.compute.glsl")?,
                     )
-                    .build();
-
-                let program = match program {
-                    Ok(p) => p,
-                    Err(e) => {
-                        e.log_error();
-                        panic!("Failed to build fire particle's compute program")
-                    }
-                };
+                    .build()?;
 
                 {
                     let program = program.borrow();
@@ -293,23 +1098,54 @@ impl Engine {
                         .uniform("max_lifetime")
                         .unwrap()
                         .borrow_mut()
-                        .set_float(4.0);
+                        .set_float(max_lifetime);
                 }
                 program
             };
+            self.register_program("fire_compute", compute_program.clone());
+
+            let light_program = Program::builder("fire_light")
+                .add_shader(
+                    "compute",
+                    Shader::new(ShaderType::Compute)
+                        .load(GLSL_VERSION_SRC)?
+                        .load("resources/shaders/fire_particle/fire_light.compute.glsl")?,
+                )
+                .build()?;
+            {
+                let program = light_program.borrow();
+                program
+                    .uniform("particle_count")
+                    .unwrap()
+                    .borrow_mut()
+                    .set_uint(particle_count);
+                program
+                    .uniform("max_fire_lifetime")
+                    .unwrap()
+                    .borrow_mut()
+                    .set_float(MAX_FIRE_LIFETIME);
+            }
+            self.register_program("fire_light", light_program.clone());
+            let light = ParticleLightSettings {
+                full_activity: FireParticle::steady_light_activity(max_lifetime, MAX_FIRE_LIFETIME),
+                ..ParticleLightSettings::new("fire", ParticleLightSource::Particles(light_program))
+            };
 
             let particle_system = ParticleSystem::builder()
+                .name("fire")
                 .display_program(self.programs.get("fire_display").unwrap().clone())
                 .compute_program(compute_program)
                 .buffer_base(1)
                 .group_size(1024)
-                .initial_particles(FireParticle::spawn(particle_count as usize))
-                .build();
+                .initial_particles(FireParticle::spawn(particle_count as usize, &mut rng))
+                .bounds(FireParticle::bounds(max_lifetime))
+                .light(light)
+                .build()?;
             let particle_system = Rc::new(RefCell::new(particle_system));
 
-            self.register_dynamic_object(particle_system.clone());
-            self.register_renderer(particle_system);
+            self.register_particle_system(particle_system);
         }
+        Ok(())
     }
 
     fn register_dynamic_object(&mut self, obj: Rc<RefCell<dyn Updatable>>) {
@@ -320,6 +1156,12 @@ impl Engine {
         self.drawables.push(obj);
     }
 
+    fn register_particle_system(&mut self, particle_system: ParticleSystemPointer) {
+        self.register_dynamic_object(particle_system.clone());
+        self.register_renderer(particle_system.clone());
+        self.particle_systems.push(particle_system);
+    }
+
     fn register_program(&mut self, name: impl Into<String>, program: Rc<RefCell<Program>>) {
         let name = name.into();
         self.programs.insert(name.clone(), program.clone());
@@ -385,23 +1227,21 @@ impl Engine {
     }
 
     fn _init_point_of_view(&mut self) {
-        let projection = Matrix4::from(PerspectiveFov {
-            aspect: definitions::DEFAULT_ASPECT_RATIO,
-            fovy: Rad(definitions::DEFAULT_FOV),
-            near: definitions::DEFAULT_ZNEAR,
-            far: definitions::DEFAULT_ZFAR,
-        });
+        let mut projection = self.projection;
+        projection.aspect_ratio = self.window_size.0 as f32 / self.window_size.1 as f32;
+        projection.sanitize();
 
         let camera = Rc::new(RefCell::new(Camera::new(
             Point3::new(3.5, 0., 0.),
             0.,
             PI,
-            projection.clone(),
+            projection,
         )));
 
         let view_transform = camera.as_ref().borrow().transform();
-        self.register_dynamic_object(camera.clone());
+        let projection = projection.matrix();
         self.main_camera = Some(camera);
+        self.apply_view_layout(self.view_layout);
 
         for i in 0..self.projection_uniforms.len() {
             self.projection_uniforms[i]
@@ -415,64 +1255,88 @@ impl Engine {
         }
     }
 
-    pub fn init(&mut self) -> &mut Self {
+    pub fn init(&mut self) -> EngineResult<&mut Self> {
+        log::info!("random seed: {}", self.seed());
         log::info!("initializing SDL...");
-        self._init_sdl();
+        self._init_sdl()?;
         log::info!("initializing OpenGL...");
         self._init_gl();
         log::info!("initializing shaders...");
-        self._init_shaders();
+        self._init_shaders()?;
         log::info!("initializing point of view...");
         self._init_point_of_view();
         log::info!("initializing objects...");
-        self._init_objects();
-        self
+        self._init_objects()?;
+        log::info!("initializing overlay...");
+        self._init_hud()?;
+        log::info!("initializing debug interface...");
+        self._init_debug_ui()?;
+        Ok(self)
     }
 
-    fn on_window_resize<'a>(
-        projection_uniforms: impl Iterator<Item = &'a RefCell<Uniform>>,
-        main_camera: &mut Camera,
-        width: i32,
-        height: i32,
-    ) {
-        unsafe {
-            gl::Viewport(0, 0, width, height);
-            gl_check!();
+    fn set_cursor_capture(sdl: &sdl2::Sdl, input: &mut InputState, capture: bool) {
+        sdl.mouse().set_relative_mouse_mode(capture);
+        input.capture_cursor = capture;
+    }
+
+    /// Releases the cursor and receives text input while the console or the debug interface is
+    /// shown, both taking typed text and the interface needing the cursor, and captures the
+    /// cursor back otherwise
+    fn set_typing(&self, input: &mut InputState, typing: bool) {
+        let text_input = self.video_subsystem.as_ref().unwrap().text_input();
+        if typing {
+            text_input.start();
+        } else {
+            text_input.stop();
         }
-        let aspect_ratio = width as f32 / height as f32;
-        Self::update_perspective(projection_uniforms, main_camera, aspect_ratio);
+        Self::set_cursor_capture(self.sdl.as_ref().unwrap(), input, !typing);
     }
 
-    fn update_perspective<'a>(
-        projection_uniforms: impl Iterator<Item = &'a RefCell<Uniform>>,
-        main_camera: &mut Camera,
-        aspect_ratio: f32,
+    fn open_controller(
+        subsystem: Option<&sdl2::GameControllerSubsystem>,
+        controllers: &mut HashMap<u32, GameController>,
+        joystick_index: u32,
     ) {
-        let projection = Matrix4::from(PerspectiveFov {
-            aspect: aspect_ratio,
-            far: definitions::DEFAULT_ZFAR,
-            near: definitions::DEFAULT_ZNEAR,
-            fovy: Rad(definitions::DEFAULT_FOV),
-        });
-
-        for u in projection_uniforms {
-            u.borrow_mut().set_mat4(&projection);
+        let Some(subsystem) = subsystem else { return };
+        match subsystem.open(joystick_index) {
+            Ok(controller) => {
+                log::info!("game controller connected: {}", controller.name());
+                controllers.insert(controller.instance_id(), controller);
+            }
+            Err(e) => log::warn!("couldn't open game controller {joystick_index}: {e}"),
         }
-
-        main_camera.set_projection(projection);
     }
 
-    fn _handle_events(&mut self, should_close: &mut bool) {
+    /// Handles the frame's events, which are the recorded ones instead of the live ones if given
+    fn _handle_events(&mut self, should_close: &mut bool, replayed: Option<&[RecordedEvent]>) {
         static mut PREV_MOUSE_X: i32 = 0;
         static mut PREV_MOUSE_Y: i32 = 0;
 
-        let mut input = unsafe { InputState::get_mut() };
-        input.mouse_x_axis = 0.; // reset mouse, no movement = no event
-        input.mouse_y_axis = 0.;
-
-        let events = self.pump.as_mut().unwrap();
+        let input = unsafe { InputState::get_mut() };
+        input.begin_frame();
+
+        let mut events: Vec<Event> = self.pump.as_mut().unwrap().poll_iter().collect();
+        if let Some(replayed) = replayed {
+            // live input is ignored while replaying, except for closing and resizing the window,
+            // whose size the views follow
+            events.retain(|e| {
+                matches!(
+                    e,
+                    Event::Quit { .. }
+                        | Event::Window {
+                            win_event: WindowEvent::Resized(..),
+                            ..
+                        }
+                )
+            });
+            let window_id = self.window.as_ref().unwrap().id();
+            events.extend(replayed.iter().filter_map(|e| e.to_event(window_id)));
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            events.iter().for_each(|e| recorder.record(e));
+        }
 
-        for event in events.poll_iter() {
+        for event in events {
             match event {
                 // quit event has no window id
                 Event::Quit { .. } => {
@@ -482,119 +1346,78 @@ impl Engine {
                 _ => {}
             }
 
-            let Some(window_id) = event.get_window_id() else {continue};
-            if window_id != self.window.as_ref().unwrap().id() {
-                continue;
-            }
-
+            // controller events have no window id
             match event {
-                Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => {
-                    *should_close = true;
-                    return;
+                Event::ControllerDeviceAdded { which, .. } => {
+                    Self::open_controller(
+                        self.game_controller_subsystem.as_ref(),
+                        &mut self.controllers,
+                        which,
+                    );
+                    continue;
                 }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Tab),
-                    ..
-                } => {
-                    self.sdl
-                        .as_ref()
-                        .unwrap()
-                        .mouse()
-                        .set_relative_mouse_mode(false);
-                    input.capture_cursor = false;
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    if let Some(controller) = self.controllers.remove(&which) {
+                        log::info!("game controller disconnected: {}", controller.name());
+                    }
+                    input.release_pad();
+                    continue;
                 }
-                Event::MouseButtonDown {
-                    mouse_btn: MouseButton::Left,
-                    ..
-                } => {
-                    // may need more checks
-                    self.sdl
-                        .as_ref()
-                        .unwrap()
-                        .mouse()
-                        .set_relative_mouse_mode(true);
-                    input.capture_cursor = true;
+                Event::ControllerAxisMotion { axis, value, .. } => {
+                    input.set_pad_axis(axis, value);
+                    continue;
                 }
+                Event::ControllerButtonDown { .. } | Event::ControllerButtonUp { .. } => {}
+                _ => {
+                    let Some(window_id) = event.get_window_id() else {continue};
+                    if window_id != self.window.as_ref().unwrap().id() {
+                        continue;
+                    }
+                }
+            }
 
-                Event::KeyDown {
-                    keycode: Some(Keycode::Z),
-                    ..
-                } => input.forward = true,
-
-                Event::KeyUp {
-                    keycode: Some(Keycode::Z),
-                    ..
-                } => input.forward = false,
-
-                Event::KeyDown {
-                    keycode: Some(Keycode::S),
-                    ..
-                } => input.backward = true,
-
-                Event::KeyUp {
-                    keycode: Some(Keycode::S),
-                    ..
-                } => input.backward = false,
-
-                Event::KeyDown {
-                    keycode: Some(Keycode::D),
-                    ..
-                } => input.right = true,
-
-                Event::KeyUp {
-                    keycode: Some(Keycode::D),
-                    ..
-                } => input.right = false,
-
-                Event::KeyDown {
-                    keycode: Some(Keycode::Q),
-                    ..
-                } => input.left = true,
-
-                Event::KeyUp {
-                    keycode: Some(Keycode::Q),
-                    ..
-                } => input.left = false,
-
-                Event::KeyDown {
-                    keycode: Some(Keycode::LShift),
-                    ..
-                } => input.down = true,
-
-                Event::KeyUp {
-                    keycode: Some(Keycode::LShift),
-                    ..
-                } => input.down = false,
-
-                Event::KeyDown {
-                    keycode: Some(Keycode::Space),
-                    ..
-                } => input.up = true,
-
-                Event::KeyUp {
-                    keycode: Some(Keycode::Space),
-                    ..
-                } => input.up = false,
-
-                Event::KeyDown {
-                    keycode: Some(Keycode::B),
-                    ..
-                } => unsafe { BROKEN_RELATIVE_MOUSE_MODE = !BROKEN_RELATIVE_MOUSE_MODE },
+            if let Some(console) = self.console.as_ref() {
+                let mut console = console.borrow_mut();
+                let was_visible = console.is_visible();
+                let consumed = if input.bindings().is_press_of(&event, Action::ToggleConsole) {
+                    console.toggle();
+                    true
+                } else {
+                    console.handle_event(&event)
+                };
+                if was_visible != console.is_visible() {
+                    let debug_ui_visible = self
+                        .debug_ui
+                        .as_ref()
+                        .is_some_and(|debug_ui| debug_ui.borrow().is_visible());
+                    if console.is_visible() {
+                        // keys typed into the console don't move the camera
+                        input.release_all();
+                    }
+                    self.set_typing(input, console.is_visible() || debug_ui_visible);
+                }
+                if consumed {
+                    continue;
+                }
+            }
 
-                Event::KeyDown {
-                    keycode: Some(Keycode::L),
-                    ..
-                } => {
-                    let camera = self.main_camera.as_ref().unwrap().borrow();
-                    log::debug!("camera forward: {:#?}", camera.forward());
-                    log::debug!("camera position: {:#?}", camera.position());
-                    log::debug!("camera projection: {:#?}", camera.projection());
-                    log::debug!("camera view_transform: {:#?}", camera.transform());
+            if let Some(debug_ui) = self.debug_ui.as_ref() {
+                let mut debug_ui = debug_ui.borrow_mut();
+                if input.bindings().is_press_of(&event, Action::ToggleDebugUi) {
+                    debug_ui.toggle();
+                    let console_visible = self
+                        .console
+                        .as_ref()
+                        .is_some_and(|console| console.borrow().is_visible());
+                    self.set_typing(input, debug_ui.is_visible() || console_visible);
+                    continue;
                 }
+                if debug_ui.handle_event(&event) {
+                    continue;
+                }
+            }
 
+            match event {
                 Event::Window {
                     win_event: WindowEvent::FocusGained,
                     ..
@@ -608,75 +1431,408 @@ impl Engine {
                 Event::Window {
                     win_event: WindowEvent::Resized(width, height),
                     ..
-                } => Engine::on_window_resize(
-                    self.projection_uniforms
-                        .iter_mut()
-                        .map(|x| /*dereferences ref then rc, then gets reference of RefCell*/ &**x),
-                    &mut *self.main_camera.as_ref().unwrap().borrow_mut(),
-                    width,
-                    height,
-                ),
+                } => {
+                    // the views' viewports and aspect ratios follow when displayed
+                    self.window_size = (width, height)
+                }
 
                 Event::MouseMotion {
                     xrel: x, yrel: y, ..
                 } => unsafe {
                     if BROKEN_RELATIVE_MOUSE_MODE {
-                        input.mouse_x_axis = (x - PREV_MOUSE_X) as f32;
-                        input.mouse_y_axis = (y - PREV_MOUSE_Y) as f32;
+                        input.set_mouse_motion((x - PREV_MOUSE_X) as f32, (y - PREV_MOUSE_Y) as f32);
                         PREV_MOUSE_X = x;
                         PREV_MOUSE_Y = y;
                     } else {
-                        input.mouse_x_axis = x as f32;
-                        input.mouse_y_axis = y as f32;
+                        input.set_mouse_motion(x as f32, y as f32);
                     }
                 },
 
-                _ => {}
+                _ => {
+                    for action in input.handle_event(&event) {
+                        match action {
+                            Action::Quit => {
+                                *should_close = true;
+                                return;
+                            }
+                            Action::ReleaseCursor => {
+                                Self::set_cursor_capture(self.sdl.as_ref().unwrap(), input, false)
+                            }
+                            // may need more checks
+                            Action::CaptureCursor => {
+                                Self::set_cursor_capture(self.sdl.as_ref().unwrap(), input, true)
+                            }
+                            Action::ToggleBrokenMouseFix => unsafe {
+                                BROKEN_RELATIVE_MOUSE_MODE = !BROKEN_RELATIVE_MOUSE_MODE
+                            },
+                            Action::ToggleHud => {
+                                if let Some(hud) = self.hud.as_ref() {
+                                    hud.borrow_mut().toggle();
+                                }
+                            }
+                            Action::ToggleOrbit => {
+                                let mut camera = self.main_camera.as_ref().unwrap().borrow_mut();
+                                camera.toggle_mode();
+                                log::info!("camera mode: {}", camera.mode());
+                            }
+                            Action::FrameObject => {
+                                if let Some(bounds) = self.looked_at_bounds() {
+                                    self.main_camera.as_ref().unwrap().borrow_mut().frame(&bounds);
+                                }
+                            }
+                            Action::RecordKeyframe => self.record_keyframe(),
+                            Action::ToggleCameraPath => {
+                                let play = !self.is_playing_camera_path();
+                                self.play_camera_path(play);
+                            }
+                            Action::DebugLog => {
+                                let camera = self.main_camera.as_ref().unwrap().borrow();
+                                log::debug!("camera forward: {:#?}", camera.forward());
+                                log::debug!("camera position: {:#?}", camera.position());
+                                log::debug!("camera projection: {:#?}", camera.projection());
+                                log::debug!("camera view_transform: {:#?}", camera.transform());
+                            }
+                            action => {
+                                if let Some(slot) = action.bookmark_slot() {
+                                    let store = input.is_pressed(Action::StoreBookmark);
+                                    self._use_bookmark_slot(slot, store);
+                                }
+                            }
+                        }
+                    }
+                }
             }
         }
     }
 
-    pub fn update(&mut self, should_close: &mut bool) -> &mut Self {
-        self._handle_events(should_close);
-        if *should_close {
-            return self;
+    /// Next frame of the replay if one is running, ends the replay once all frames are played
+    fn _next_replayed_frame(&mut self, should_close: &mut bool) -> Option<RecordedFrame> {
+        let replayer = self.replayer.as_mut()?;
+        let frame = replayer.next_frame();
+        if frame.is_none() {
+            log::info!("replay finished");
+            *should_close |= replayer.exit_at_end();
+            self.replayer = None;
         }
+        frame
+    }
+
+    pub fn update(&mut self, should_close: &mut bool) -> &mut Self {
+        let replayed = self._next_replayed_frame(should_close);
+        self._handle_events(should_close, replayed.as_ref().map(|f| f.events.as_slice()));
 
-        let delta = if let Some(instant) = self.last_frame_time {
+        let delta = if let Some(frame) = replayed.as_ref() {
+            frame.delta
+        } else if let Some(instant) = self.last_frame_time {
             instant.elapsed().as_secs_f32()
         } else {
             0f32
         };
-
         self.last_frame_time = Some(Instant::now());
 
-        for item in self.updatables.iter_mut() {
-            item.borrow_mut().update(delta)
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(error) = recorder.end_frame(delta) {
+                log::error!("input recording stopped: {}", error.chain());
+                self.recorder = None;
+            }
+        }
+
+        if *should_close {
+            return self;
+        }
+        self._process_commands();
+
+        self.statistics.borrow_mut().record_frame(delta);
+
+        let stepping = self.paused && self.pending_steps > 0;
+        if stepping {
+            self.pending_steps -= 1;
+        }
+        let frozen = self.paused && !stepping;
+
+        {
+            let _frame_scope = Profiler::scope(&self.profiler, "update", false);
+            // only the controlled camera follows the input
+            if let Some(camera) = self.main_camera.as_ref() {
+                let mut camera = camera.borrow_mut();
+                let _scope =
+                    Profiler::scope(&self.profiler, format!("update/{}", camera.name()), true);
+                camera.update(delta);
+            }
+            for item in self.updatables.iter_mut() {
+                let mut item = item.borrow_mut();
+                if frozen && item.pausable() {
+                    continue;
+                }
+                let _scope =
+                    Profiler::scope(&self.profiler, format!("update/{}", item.name()), true);
+                item.update(delta)
+            }
         }
+        self._update_particle_lights();
+        // overrides the camera's own movement
+        self._update_camera_path(delta);
 
         self
     }
 
+    /// Moves the lights of the particle systems in the light list, where they are added back
+    /// if removed unless disabled
+    fn _update_particle_lights(&mut self) {
+        for system in self.particle_systems.iter() {
+            let system = system.borrow();
+            let Some(particle_light) = system.light() else {
+                continue;
+            };
+            if !particle_light.is_enabled() {
+                self.lights.remove(particle_light.name());
+                continue;
+            }
+            match self.lights.light_mut(particle_light.name()) {
+                Some(light) => particle_light.apply(light),
+                None => self.lights.add(particle_light.light()),
+            }
+        }
+    }
+
+    /// Completes the console input and executes the commands submitted since the last frame
+    fn _process_commands(&mut self) {
+        let Some(console) = self.console.as_ref() else {
+            return;
+        };
+        let completion = console.borrow_mut().take_completion_request();
+        if let Some((words, index)) = completion {
+            let words: Vec<&str> = words.iter().map(String::as_str).collect();
+            let candidates = self.commands.candidates(self, &words, index);
+            console.borrow_mut().complete(&candidates);
+        }
+
+        let pending = console.borrow_mut().take_pending();
+        for (source, line) in pending {
+            let result = commands::execute(self, &line);
+            if let Err(error) = &result {
+                log::debug!("command `{line}` failed: {error}");
+            }
+            if let Some(console) = self.console.as_ref() {
+                console.borrow_mut().report(source, &line, &result);
+            }
+        }
+    }
+
+    /// Executes the requests received by the remote control server, to be called between
+    /// [`Engine::update`] and [`Engine::display`].
+    pub fn process_remote(&mut self) -> &mut Self {
+        // taken out so that requests can access the whole engine
+        let Some(mut server) = self.remote.take() else {
+            return self;
+        };
+        for (connection, line) in server.poll() {
+            if let Some(response) = remote::handle_request(self, &line) {
+                server.respond(connection, &response);
+            }
+        }
+        server.flush();
+        self.remote = Some(server);
+        self
+    }
+
     fn _clear_frame(&self) {
         unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT) };
     }
 
-    pub fn display(&self) -> &Self {
+    /// Clears the whole window, parts of which may not be covered by any view
+    fn _clear_window(&self) {
+        let (width, height) = self.window_size;
+        unsafe {
+            gl_checked! {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::Viewport(0, 0, width, height);
+            };
+        }
+        self._clear_frame();
+    }
+
+    /// Renders the shadow casters into the layers of the shadow maps covering the view of
+    /// `camera`, returning the lights they were rendered for
+    fn _draw_shadows(&self, view: &View, camera: &Camera) -> Vec<ShadowCaster> {
+        let Some(shadow_maps) = self.shadow_maps.as_ref() else {
+            return Vec::new();
+        };
+        let layout = ShadowLayout::new(&self.lights, camera, &self.shadow_settings);
+        if layout.cameras.is_empty() {
+            return Vec::new();
+        }
+        let _group = DebugGroup::push("shadows");
+        let _scope = Profiler::scope(
+            &self.profiler,
+            format!("draw/{}/shadows", view.name()),
+            true,
+        );
+
+        ShadowMaps::begin();
+        for (layer, light_camera) in layout.cameras.iter().enumerate() {
+            shadow_maps.begin_layer(layer);
+            light_camera.projection().apply_depth_convention();
+            let view_transform = light_camera.transform();
+            for uniform in self.view_transform_uniforms.iter() {
+                uniform.borrow_mut().set_mat4(&view_transform);
+            }
+            let projection = light_camera.projection_matrix();
+            for uniform in self.projection_uniforms.iter() {
+                uniform.borrow_mut().set_mat4(&projection);
+            }
+
+            // the casters between the light and the near plane are depth clamped, not clipped
+            let frustum =
+                Frustum::from_matrix(&(projection * view_transform), false).without_near_plane();
+            // the particles face the light, whose camera is the context's
+            let context = DrawContext {
+                engine: self,
+                view,
+                camera: light_camera,
+            };
+            for item in self.drawables.iter() {
+                let item = item.borrow();
+                if !item.casts_shadows() {
+                    continue;
+                }
+                let culled = item.bounds().is_some_and(|b| !frustum.intersects(&b));
+                if self.culling && culled {
+                    continue;
+                }
+                item.draw_depth(&context);
+            }
+        }
+        shadow_maps.end(&layout.cameras, &self.shadow_settings);
+        layout.casters
+    }
+
+    fn _draw_view(&self, view: &View, statistics: &mut RenderStatistics) {
+        let _group = DebugGroup::push(view.name());
+        // the aspect ratio follows the view, which may be resized at any time
+        view.camera()
+            .borrow_mut()
+            .set_aspect_ratio(view.aspect_ratio(self.window_size));
+        let camera = view.camera().borrow();
+
+        // the cascades follow the view's camera
+        let shadow_casters = self._draw_shadows(view, &camera);
+
+        view.bind(self.window_size);
+        // the depth is cleared to the far plane of the camera's convention
+        camera.projection().apply_depth_convention();
         self._clear_frame();
-        let view_transform = self.main_camera.as_ref().unwrap().borrow().transform();
 
+        if let Some(light_buffer) = self.light_buffer.as_ref() {
+            light_buffer.upload(&self.lights, *camera.position(), &shadow_casters);
+        }
+
+        let view_transform = camera.transform();
         for uniform in self.view_transform_uniforms.iter() {
             uniform.borrow_mut().set_mat4(&view_transform);
         }
+        // the projection may change at any time, e.g. the field of view along a camera path
+        let projection = camera.projection_matrix();
+        for uniform in self.projection_uniforms.iter() {
+            uniform.borrow_mut().set_mat4(&projection);
+        }
 
+        let frustum = Frustum::from_matrix(
+            &(projection * view_transform),
+            camera.projection().reversed_z,
+        );
+        let context = DrawContext {
+            engine: self,
+            view,
+            camera: &camera,
+        };
         for item in self.drawables.iter() {
-            item.borrow().draw(self);
+            let item = item.borrow();
+            let culled = item.bounds().is_some_and(|b| !frustum.intersects(&b));
+            if self.culling && culled {
+                statistics.culled += 1;
+                continue;
+            }
+            let _group = DebugGroup::push(item.name());
+            // each view has its own timers, a GPU timer being used once per frame
+            let _scope = Profiler::scope(
+                &self.profiler,
+                format!("draw/{}/{}", view.name(), item.name()),
+                true,
+            );
+            item.draw(&context);
+            item.collect_statistics(statistics);
+        }
+
+        view.present(self.window_size);
+    }
+
+    pub fn display(&self) -> &Self {
+        let _frame_scope = Profiler::scope(&self.profiler, "display", false);
+        self._clear_window();
+
+        let mut render_statistics = RenderStatistics::default();
+        for view in self.views.iter() {
+            self._draw_view(view, &mut render_statistics);
+        }
+        self.profiler.borrow_mut().counters(
+            "render",
+            &[
+                ("drawables", render_statistics.drawables),
+                ("culled", render_statistics.culled),
+                ("draw_calls", render_statistics.draw_calls),
+            ],
+        );
+        self.statistics.borrow_mut().render = render_statistics;
+
+        let (width, height) = self.window_size;
+        unsafe {
+            gl::Viewport(0, 0, width, height);
+            gl_check!();
         }
 
+        if let Some(path) = self.pending_screenshot.borrow_mut().take() {
+            let (width, height) = self.window_size;
+            match save_screenshot(&path, width, height) {
+                Ok(()) => log::info!("screenshot saved to `{}`", path.display()),
+                Err(error) => log::error!("{}", error.chain()),
+            }
+        }
+
+        self._draw_overlay();
+
         self
     }
+
+    fn _draw_overlay(&self) {
+        let Some(hud) = self.hud.as_ref() else { return };
+        let _group = DebugGroup::push("overlay");
+        let _scope = Profiler::scope(&self.profiler, "draw/overlay", true);
+        let (width, height) = self.window_size;
+        hud.borrow_mut()
+            .draw(&self.statistics.borrow(), width, height);
+        if let Some(console) = self.console.as_ref() {
+            console.borrow_mut().draw(width, height);
+        }
+        drop(_scope);
+
+        if let Some(debug_ui) = self.debug_ui.as_ref() {
+            let _scope = Profiler::scope(&self.profiler, "draw/debug_ui", true);
+            debug_ui.borrow_mut().draw(self, width, height);
+        }
+    }
     pub fn swap_buffer(&self) -> &Self {
         self.window.as_ref().unwrap().gl_swap_window();
+        self.profiler.borrow_mut().end_frame();
         self
     }
+
+    /// Releases the engine's end of execution resources, like profiling reports.
+    pub fn finish(&mut self) -> EngineResult<()> {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish()?;
+        }
+        self.profiler.borrow().dump()
+    }
 }
@@ -0,0 +1,122 @@
+use std::{error::Error, fmt::Display, io, path::PathBuf};
+
+pub type EngineResult<T> = Result<T, EngineError>;
+
+/// Crate-wide error type, returned by the engine's initialization steps and object builders.
+#[derive(Debug)]
+pub enum EngineError {
+    /// SDL initialization or window creation failed
+    Window(String),
+    /// OpenGL context creation or setup failed
+    Context(String),
+    /// A shader of a program failed to compile
+    ShaderCompile {
+        program: String,
+        shader: String,
+        log: String,
+    },
+    /// A program failed to link
+    Link { program: String, log: String },
+    /// An attribute requested by a builder is not an active attribute of the program
+    MissingAttribute { program: String, attribute: String },
+    /// A builder has been given an invalid or incomplete configuration
+    InvalidBuilderConfig {
+        builder: &'static str,
+        problems: Vec<String>,
+    },
+    /// A resource file could not be read or written
+    Io { path: PathBuf, source: io::Error },
+    /// A resource file has invalid content
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// A glTF file or one of the buffers or images it references could not be imported
+    Gltf { path: PathBuf, source: gltf::Error },
+}
+
+impl EngineError {
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Self::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn parse(path: impl Into<PathBuf>, line: usize, message: impl Into<String>) -> Self {
+        Self::Parse {
+            path: path.into(),
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Invalid JSON content, `first_line` being the line of the file the JSON value starts at
+    pub fn json(path: impl Into<PathBuf>, first_line: usize, error: &serde_json::Error) -> Self {
+        let message = error.to_string();
+        // the position is reported by the line instead
+        let position = format!(" at line {} column {}", error.line(), error.column());
+        let message = message.strip_suffix(&position).unwrap_or(&message);
+        Self::parse(path, first_line + error.line().max(1) - 1, message)
+    }
+
+    /// Formats the error followed by each of its sources, one per line.
+    pub fn chain(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = self.source();
+        while let Some(error) = source {
+            message.push_str(&format!("\n  caused by: {error}"));
+            source = error.source();
+        }
+        message
+    }
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Window(message) => write!(f, "window creation failed: {message}"),
+            Self::Context(message) => write!(f, "OpenGL context error: {message}"),
+            Self::ShaderCompile {
+                program,
+                shader,
+                log,
+            } => write!(
+                f,
+                "[{program}]<{shader}> shader compilation failed.\n---- LOG ----\n{log}"
+            ),
+            Self::Link { program, log } => {
+                write!(f, "program `{program}` linking failed, link log:\n{log}")
+            }
+            Self::MissingAttribute { program, attribute } => write!(
+                f,
+                "attribute `{attribute}` could not be found in program `{program}`"
+            ),
+            Self::InvalidBuilderConfig { builder, problems } => {
+                write!(f, "invalid {builder} configuration")?;
+                for problem in problems {
+                    write!(f, "\n  - {problem}")?;
+                }
+                Ok(())
+            }
+            Self::Io { path, .. } => write!(f, "couldn't access `{}`", path.display()),
+            Self::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "`{}` line {line}: {message}", path.display()),
+            Self::Gltf { path, .. } => write!(f, "couldn't import glTF `{}`", path.display()),
+        }
+    }
+}
+
+impl Error for EngineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Gltf { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
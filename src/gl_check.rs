@@ -44,10 +44,10 @@ pub(crate) fn check_gl_error(file: &str, line: u32, sub_expression: Option<u32>)
 /// Macro to check a single time if an openGL error occurred.
 ///
 /// The expanded expression is a boolean which is true if an error occurred.
-#[macro_export(local_inner_macro)]
+#[macro_export]
 macro_rules! gl_check {
     () => {
-        gl_check!(file!(), line!())
+        $crate::gl_check!(file!(), line!())
     };
     ($file:expr, $line:expr) => {
         $crate::gl_check::check_gl_error($file, $line, None)
@@ -57,17 +57,20 @@ macro_rules! gl_check {
 /// Macro that adds a call to the custom openGL error checking function after each statement.
 ///
 /// The expanded expression resolves to a boolean, which is true if *any* error occurred.
-#[macro_export(local_inner_macro)]
+#[macro_export]
 macro_rules! gl_checked {
     (@step $_idx:expr, []) => {false};
     (@step $idx:expr, [$head:stmt; $($tail:stmt; )*]) => {{
         $head
         let error = $crate::gl_check::check_gl_error(file!(), line!(), Some($idx));
-        gl_checked!(@step $idx + 1, [$($tail;)*]) || error
+        $crate::gl_checked!(@step $idx + 1, [$($tail;)*]) || error
     }};
     {$( $l:stmt; )+} => {
         {
-            gl_checked!(@step 1, [$($l;)+])
+            // bound to a variable so that using the macro as a statement doesn't discard a
+            // logical operation
+            let error: bool = $crate::gl_checked!(@step 1, [$($l;)+]);
+            error
         }
     };
 }
@@ -0,0 +1,170 @@
+use std::{
+    ffi::c_void,
+    slice,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use ansi_term::Color;
+use gl::types::{GLchar, GLenum, GLint, GLsizei, GLuint};
+use log::Level;
+
+use crate::gl_check;
+
+/// Whether the driver granted a debug context, set by [`enable_debug_output`]
+static DEBUG_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+fn stringify_source(source: GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window_system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader_compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third_party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+fn stringify_type(message_type: GLenum) -> &'static str {
+    match message_type {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined_behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        gl::DEBUG_TYPE_PUSH_GROUP => "push_group",
+        gl::DEBUG_TYPE_POP_GROUP => "pop_group",
+        _ => "other",
+    }
+}
+
+fn severity_level(message_type: GLenum, severity: GLenum) -> Level {
+    match (message_type, severity) {
+        (gl::DEBUG_TYPE_ERROR, _) | (_, gl::DEBUG_SEVERITY_HIGH) => Level::Error,
+        (_, gl::DEBUG_SEVERITY_MEDIUM) => Level::Warn,
+        (gl::DEBUG_TYPE_PUSH_GROUP | gl::DEBUG_TYPE_POP_GROUP, _) => Level::Trace,
+        (_, gl::DEBUG_SEVERITY_LOW) => Level::Debug,
+        _ => Level::Trace,
+    }
+}
+
+extern "system" fn debug_message_callback(
+    source: GLenum,
+    message_type: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut c_void,
+) {
+    let level = severity_level(message_type, severity);
+    if !log::log_enabled!(level) {
+        return;
+    }
+
+    let message = if length < 0 {
+        unsafe { std::ffi::CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        let bytes = unsafe { slice::from_raw_parts(message as *const u8, length as usize) };
+        String::from_utf8_lossy(bytes).into_owned()
+    };
+
+    log::log!(
+        level,
+        "{header}<{source}:{message_type}> (id {id}) {message}",
+        header = Color::Blue.paint("[OpenGL]"),
+        source = stringify_source(source),
+        message_type = Color::Yellow.paint(stringify_type(message_type)),
+    );
+}
+
+/// Installs the debug message callback if the current context is a debug context.
+///
+/// Returns whether debug output has been enabled, which [`is_enabled`] reports afterwards.
+pub fn enable_debug_output() -> bool {
+    let mut flags: GLint = 0;
+    unsafe {
+        gl::GetIntegerv(gl::CONTEXT_FLAGS, &mut flags);
+        gl_check!();
+    }
+    let granted = flags as GLuint & gl::CONTEXT_FLAG_DEBUG_BIT != 0;
+    DEBUG_OUTPUT.store(granted, Ordering::Relaxed);
+    if !granted {
+        log::warn!("OpenGL context is not a debug context, debug output is disabled");
+        return false;
+    }
+
+    unsafe {
+        gl::Enable(gl::DEBUG_OUTPUT);
+        // messages are reported from the thread and call that caused them
+        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl::DebugMessageCallback(Some(debug_message_callback), std::ptr::null());
+        gl::DebugMessageControl(
+            gl::DONT_CARE,
+            gl::DONT_CARE,
+            gl::DONT_CARE,
+            0,
+            std::ptr::null(),
+            gl::TRUE,
+        );
+        gl_check!();
+    }
+    true
+}
+
+/// Whether debug output is enabled, labels and debug groups being ignored otherwise
+pub fn is_enabled() -> bool {
+    DEBUG_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Attaches a human readable label to an OpenGL object, shown in debug messages and GPU debuggers.
+///
+/// `identifier` is the object namespace, e.g. `gl::PROGRAM`, `gl::BUFFER` or `gl::VERTEX_ARRAY`.
+/// Does nothing without a debug context.
+pub fn label_object(identifier: GLenum, id: GLuint, label: &str) {
+    if !is_enabled() {
+        return;
+    }
+    unsafe {
+        gl::ObjectLabel(identifier, id, label.len() as _, label.as_ptr() as _);
+        gl_check!();
+    }
+}
+
+/// RAII guard of a debug group, the group is popped when the guard is dropped.
+///
+/// No group is pushed without a debug context.
+pub struct DebugGroup {
+    pushed: bool,
+}
+
+impl DebugGroup {
+    #[must_use]
+    pub fn push(message: &str) -> Self {
+        if !is_enabled() {
+            return DebugGroup { pushed: false };
+        }
+        unsafe {
+            gl::PushDebugGroup(
+                gl::DEBUG_SOURCE_APPLICATION,
+                0,
+                message.len() as _,
+                message.as_ptr() as _,
+            );
+            gl_check!();
+        }
+        DebugGroup { pushed: true }
+    }
+}
+
+impl Drop for DebugGroup {
+    fn drop(&mut self) {
+        if self.pushed {
+            unsafe {
+                gl::PopDebugGroup();
+            }
+        }
+    }
+}
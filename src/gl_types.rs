@@ -2,6 +2,9 @@ use gl::types::{GLenum, GLuint};
 
 pub type VaoIdType = GLuint;
 pub type BufferIdType = GLuint;
+pub type TextureIdType = GLuint;
+pub type FramebufferIdType = GLuint;
+pub type RenderbufferIdType = GLuint;
 
 #[derive(Default)]
 #[allow(unused)]
@@ -0,0 +1,505 @@
+use std::{collections::HashMap, fs, path::Path, rc::Rc};
+
+use cgmath::{Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, Transform, Vector3, Vector4};
+use gl::types::GLfloat;
+use gltf::{image::Format, mesh::Mode, Document, Gltf};
+
+use crate::{
+    error::{EngineError, EngineResult},
+    obj::smooth_normals,
+    objects::{Camera, Indices, MeshRenderer, MeshRendererPointer, MeshSection},
+    program::{uniform::UniformValue, ProgramSharedPointer},
+    projection::{Projection, ProjectionKind},
+    texture::{Sampling, Texture, TexturePointer},
+};
+
+/// Part of a mesh drawn with one material
+#[derive(Clone, Debug)]
+pub struct ScenePrimitive {
+    /// Index in the scene's materials, the default material being used if `None`
+    pub material: Option<usize>,
+    pub start: usize,
+    pub count: usize,
+}
+
+/// Triangles of all the primitives of a glTF mesh, with one set of attributes per vertex
+#[derive(Clone, Debug, Default)]
+pub struct SceneMesh {
+    pub name: String,
+    pub positions: Vec<GLfloat>,
+    pub normals: Vec<GLfloat>,
+    pub uvs: Vec<GLfloat>,
+    /// xyz tangent and w handedness of the bitangent
+    pub tangents: Vec<GLfloat>,
+    pub colors: Vec<GLfloat>,
+    pub indices: Vec<u32>,
+    pub primitives: Vec<ScenePrimitive>,
+}
+
+/// Base color of a glTF material, the other metallic roughness parameters being ignored
+#[derive(Clone, Debug)]
+pub struct SceneMaterial {
+    pub name: String,
+    pub base_color: [GLfloat; 4],
+    /// Index in the scene's textures
+    pub base_color_texture: Option<usize>,
+    pub metallic: GLfloat,
+    pub roughness: GLfloat,
+}
+
+impl SceneMaterial {
+    /// Blinn-Phong approximation of the metallic-roughness material, metals reflecting their
+    /// base color and rough surfaces getting wide dim highlights
+    pub fn uniforms(&self) -> Vec<(String, UniformValue)> {
+        let base_color = Vector4::from(self.base_color);
+        let dielectric = Vector3::new(0.04, 0.04, 0.04);
+        let specular = dielectric + (base_color.truncate() - dielectric) * self.metallic;
+        let roughness = self.roughness.clamp(0.05, 1.);
+        let shininess = (2. / roughness.powi(4) - 2.).clamp(1., 512.);
+        vec![
+            ("object_color".to_owned(), UniformValue::Vec4(base_color)),
+            ("specular_color".to_owned(), UniformValue::Vec3(specular)),
+            ("shininess".to_owned(), UniformValue::Float(shininess)),
+        ]
+    }
+}
+
+impl Default for SceneMaterial {
+    /// glTF's default material, for primitives without one
+    fn default() -> Self {
+        Self {
+            name: "default".to_owned(),
+            base_color: [1.; 4],
+            base_color_texture: None,
+            metallic: 1.,
+            roughness: 1.,
+        }
+    }
+}
+
+/// Decoded image of a glTF texture, 4 bytes per texel
+#[derive(Clone, Debug)]
+pub struct SceneTexture {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    pub sampling: Sampling,
+}
+
+/// Instance of a mesh placed by a node
+#[derive(Clone, Debug)]
+pub struct SceneNode {
+    pub name: String,
+    /// Index in the scene's meshes
+    pub mesh: usize,
+    pub transform: Matrix4<GLfloat>,
+}
+
+/// Meshes, materials and cameras of the default scene of a glTF file, converted to the engine's
+/// Z up coordinates
+#[derive(Default)]
+pub struct GltfScene {
+    pub meshes: Vec<SceneMesh>,
+    pub materials: Vec<SceneMaterial>,
+    /// `None` for the textures whose image format isn't supported
+    pub textures: Vec<Option<SceneTexture>>,
+    pub nodes: Vec<SceneNode>,
+    pub cameras: Vec<(String, Camera)>,
+}
+
+impl GltfScene {
+    /// Imports a `.gltf` or `.glb` file and the buffers and images it references, the cameras
+    /// getting the parameters of `projection` that glTF doesn't specify
+    pub fn load(path: &Path, projection: Projection) -> EngineResult<Self> {
+        let bytes = fs::read(path).map_err(|e| EngineError::io(path, e))?;
+        let error = |source| EngineError::Gltf {
+            path: path.to_owned(),
+            source,
+        };
+
+        let Gltf { document, blob } = Gltf::from_slice_without_validation(&bytes).map_err(error)?;
+        let mut root = document.into_json();
+        for extension in root.extensions_used.iter() {
+            let required = root.extensions_required.contains(extension);
+            log::warn!(
+                "`{}`: ignoring unsupported extension `{extension}`{}",
+                path.display(),
+                if required { ", which is required" } else { "" }
+            );
+        }
+        // a scene using a required extension is still imported, possibly looking wrong
+        root.extensions_required.clear();
+        let document = Document::from_json(root).map_err(error)?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        let buffers = gltf::import_buffers(&document, Some(base), blob).map_err(error)?;
+        // the textures of images failing to decode, e.g. KTX2 or WebP ones, are left out
+        let images: Vec<Option<gltf::image::Data>> = document
+            .images()
+            .map(|image| {
+                gltf::image::Data::from_source(image.source(), Some(base), &buffers)
+                    .map_err(|e| {
+                        log::warn!(
+                            "`{}`: ignoring image {}: {e}",
+                            path.display(),
+                            image.index()
+                        )
+                    })
+                    .ok()
+            })
+            .collect();
+
+        let mut scene = Self {
+            textures: document
+                .textures()
+                .map(|texture| load_texture(path, &texture, &images))
+                .collect(),
+            materials: document.materials().map(load_material).collect(),
+            ..Default::default()
+        };
+        for mesh in document.meshes() {
+            scene.meshes.push(load_mesh(path, &mesh, &buffers));
+        }
+
+        let Some(gltf_scene) = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+        else {
+            log::warn!("`{}` has no scene", path.display());
+            return Ok(scene);
+        };
+        // glTF is Y up
+        let root_transform = Matrix4::from_angle_x(Deg(90.));
+        for node in gltf_scene.nodes() {
+            scene.add_node(&node, root_transform, projection);
+        }
+        Ok(scene)
+    }
+
+    /// Adds the meshes and cameras of a node and of its children
+    fn add_node(
+        &mut self,
+        node: &gltf::Node,
+        parent_transform: Matrix4<GLfloat>,
+        projection: Projection,
+    ) {
+        let transform = parent_transform * Matrix4::from(node.transform().matrix());
+        let name = node
+            .name()
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("node{}", node.index()));
+
+        if let Some(mesh) = node.mesh() {
+            // the drawables are found by name, which glTF doesn't require to be unique
+            let mut unique_name = name.clone();
+            let mut suffix = 1;
+            while self.nodes.iter().any(|n| n.name == unique_name) {
+                suffix += 1;
+                unique_name = format!("{name}_{suffix}");
+            }
+            self.nodes.push(SceneNode {
+                name: unique_name,
+                mesh: mesh.index(),
+                transform,
+            });
+        }
+        if let Some(camera) = node.camera() {
+            let name = camera.name().map(str::to_owned).unwrap_or(name);
+            self.cameras
+                .push((name, scene_camera(&camera, &transform, projection)));
+        }
+        for child in node.children() {
+            self.add_node(&child, transform, projection);
+        }
+    }
+
+    /// Mesh renderers of the nodes, named `prefix/node`, with a section per primitive
+    pub fn renderers(
+        &self,
+        prefix: &str,
+        program: ProgramSharedPointer,
+    ) -> EngineResult<Vec<MeshRendererPointer>> {
+        let (has_normal, has_uv, has_tangent, has_color) = {
+            let program = program.borrow();
+            (
+                program.has_attribute("normal"),
+                program.has_attribute("uv"),
+                program.has_attribute("tangent"),
+                program.has_attribute("color"),
+            )
+        };
+        // uploaded once, for all the primitives using them
+        let mut textures: HashMap<usize, TexturePointer> = HashMap::new();
+        let mut renderers = Vec::new();
+
+        for node in self.nodes.iter() {
+            let mesh = &self.meshes[node.mesh];
+            if mesh.indices.is_empty() {
+                continue;
+            }
+            let mut builder = MeshRenderer::builder()
+                .name(format!("{prefix}/{}", node.name))
+                .shader(program.clone())
+                .transform(node.transform)
+                .add_buffer(mesh.positions.clone())
+                .add_attribute("position", 3, 0)
+                .indices(Indices::compact(mesh.indices.clone()));
+            let mut buffer_id = 1;
+            for (used, values, name, size) in [
+                (has_normal, &mesh.normals, "normal", 3),
+                (has_uv, &mesh.uvs, "uv", 2),
+                (has_tangent, &mesh.tangents, "tangent", 4),
+                (has_color, &mesh.colors, "color", 4),
+            ] {
+                if used {
+                    builder = builder
+                        .add_buffer(values.clone())
+                        .add_attribute(name, size, buffer_id);
+                    buffer_id += 1;
+                }
+            }
+
+            for primitive in mesh.primitives.iter() {
+                let material = primitive
+                    .material
+                    .and_then(|i| self.materials.get(i))
+                    .cloned()
+                    .unwrap_or_default();
+                let texture = match material.base_color_texture {
+                    Some(index) => self.texture(index, &mut textures)?,
+                    None => None,
+                };
+                builder = builder.add_section(MeshSection {
+                    start: primitive.start,
+                    count: primitive.count,
+                    uniforms: material.uniforms(),
+                    texture,
+                });
+            }
+            renderers.push(builder.build()?);
+        }
+        Ok(renderers)
+    }
+
+    /// Uploads a texture the first time it's used
+    fn texture(
+        &self,
+        index: usize,
+        textures: &mut HashMap<usize, TexturePointer>,
+    ) -> EngineResult<Option<TexturePointer>> {
+        if let Some(texture) = textures.get(&index) {
+            return Ok(Some(texture.clone()));
+        }
+        let Some(Some(data)) = self.textures.get(index) else {
+            return Ok(None);
+        };
+        let texture = Rc::new(Texture::from_rgba8(
+            &data.name,
+            data.width as _,
+            data.height as _,
+            &data.pixels,
+            data.sampling,
+        )?);
+        textures.insert(index, texture.clone());
+        Ok(Some(texture))
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.nodes
+            .iter()
+            .map(|node| self.meshes[node.mesh].indices.len() / 3)
+            .sum()
+    }
+}
+
+fn load_material(material: gltf::Material) -> SceneMaterial {
+    let pbr = material.pbr_metallic_roughness();
+    SceneMaterial {
+        name: material
+            .name()
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("material{}", material.index().unwrap_or_default())),
+        base_color: pbr.base_color_factor(),
+        base_color_texture: pbr.base_color_texture().map(|info| info.texture().index()),
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+    }
+}
+
+/// Converts the image of a texture to RGBA, `None` if its format isn't supported
+fn load_texture(
+    path: &Path,
+    texture: &gltf::Texture,
+    images: &[Option<gltf::image::Data>],
+) -> Option<SceneTexture> {
+    let image = images[texture.source().index()].as_ref()?;
+    // glTF's texture coordinates start from the first row, which is uploaded at v = 0
+    let pixels: Vec<u8> = match image.format {
+        Format::R8G8B8A8 => image.pixels.clone(),
+        Format::R8G8B8 => image
+            .pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        Format::R8G8 => image
+            .pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        Format::R8 => image
+            .pixels
+            .iter()
+            .flat_map(|l| [*l, *l, *l, 255])
+            .collect(),
+        format => {
+            log::warn!(
+                "`{}`: ignoring texture {} of unsupported format {format:?}",
+                path.display(),
+                texture.index()
+            );
+            return None;
+        }
+    };
+
+    let sampler = texture.sampler();
+    let default = Sampling::default();
+    Some(SceneTexture {
+        name: texture
+            .name()
+            .or_else(|| texture.source().name())
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("texture{}", texture.index())),
+        width: image.width,
+        height: image.height,
+        pixels,
+        sampling: Sampling {
+            wrap_s: sampler.wrap_s().as_gl_enum(),
+            wrap_t: sampler.wrap_t().as_gl_enum(),
+            mag_filter: sampler
+                .mag_filter()
+                .map_or(default.mag_filter, |f| f.as_gl_enum()),
+            min_filter: sampler
+                .min_filter()
+                .map_or(default.min_filter, |f| f.as_gl_enum()),
+        },
+    })
+}
+
+/// Merges the triangle primitives of a mesh, the others being skipped
+fn load_mesh(path: &Path, mesh: &gltf::Mesh, buffers: &[gltf::buffer::Data]) -> SceneMesh {
+    let name = mesh
+        .name()
+        .map(str::to_owned)
+        .unwrap_or_else(|| format!("mesh{}", mesh.index()));
+    let mut data = SceneMesh {
+        name,
+        ..Default::default()
+    };
+
+    for primitive in mesh.primitives() {
+        let context = format!(
+            "`{}`: mesh `{}` primitive {}",
+            path.display(),
+            data.name,
+            primitive.index()
+        );
+        if primitive.mode() != Mode::Triangles {
+            log::warn!("{context}: ignoring {:?} primitive", primitive.mode());
+            continue;
+        }
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let Some(positions) = reader.read_positions() else {
+            log::warn!("{context}: ignoring primitive without positions");
+            continue;
+        };
+        let positions: Vec<GLfloat> = positions.flatten().collect();
+        let count = positions.len() / 3;
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..count as u32).collect(),
+        };
+        if let Some(index) = indices.iter().find(|i| **i as usize >= count) {
+            log::warn!("{context}: index {index} out of range, there are {count} vertices");
+            continue;
+        }
+        for (semantic, _) in primitive.attributes() {
+            use gltf::Semantic;
+            match semantic {
+                Semantic::Positions
+                | Semantic::Normals
+                | Semantic::Tangents
+                | Semantic::Colors(0)
+                | Semantic::TexCoords(0) => {}
+                semantic => log::debug!("{context}: ignoring {semantic:?} attribute"),
+            }
+        }
+
+        // glTF leaves the normals to the renderer when there are none
+        let normals = match reader.read_normals() {
+            Some(normals) => normals.flatten().collect(),
+            None => smooth_normals(&positions, &indices),
+        };
+        let uvs = match reader.read_tex_coords(0) {
+            Some(uvs) => uvs.into_f32().flatten().collect(),
+            None => vec![0.; count * 2],
+        };
+        let tangents = match reader.read_tangents() {
+            Some(tangents) => tangents.flatten().collect(),
+            None => vec![0.; count * 4],
+        };
+        let colors = match reader.read_colors(0) {
+            Some(colors) => colors.into_rgba_f32().flatten().collect(),
+            None => vec![1.; count * 4],
+        };
+
+        let base = (data.positions.len() / 3) as u32;
+        let start = data.indices.len();
+        data.positions.extend(positions);
+        data.normals.extend(normals);
+        data.uvs.extend(uvs);
+        data.tangents.extend(tangents);
+        data.colors.extend(colors);
+        data.indices.extend(indices.iter().map(|i| base + i));
+        data.primitives.push(ScenePrimitive {
+            material: primitive.material().index(),
+            start,
+            count: indices.len(),
+        });
+    }
+    data
+}
+
+/// Engine camera looking along the node's -Z axis, its roll being lost
+fn scene_camera(
+    camera: &gltf::Camera,
+    transform: &Matrix4<GLfloat>,
+    projection: Projection,
+) -> Camera {
+    let position = transform.transform_point(Point3::origin());
+    let forward = transform.transform_vector(-Vector3::unit_z()).normalize();
+    let mut projection = match camera.projection() {
+        gltf::camera::Projection::Perspective(perspective) => Projection {
+            kind: ProjectionKind::Perspective,
+            fov: perspective.yfov(),
+            near: perspective.znear(),
+            far: perspective.zfar().unwrap_or(projection.far),
+            ..projection
+        },
+        gltf::camera::Projection::Orthographic(orthographic) => Projection {
+            kind: ProjectionKind::Orthographic,
+            // ymag is half the height of the view box
+            ortho_height: orthographic.ymag() * 2.,
+            near: orthographic.znear(),
+            far: orthographic.zfar(),
+            ..projection
+        },
+    };
+    projection.sanitize();
+    Camera::new(
+        position,
+        forward.z.clamp(-1., 1.).asin(),
+        forward.y.atan2(forward.x),
+        projection,
+    )
+}
@@ -0,0 +1,132 @@
+use cgmath::{Vector2, Vector4};
+use clap::ValueEnum;
+
+use crate::{
+    error::EngineResult, objects::TextRenderer, program::ProgramSharedPointer,
+    statistics::FrameStatistics,
+};
+
+const TEXT_COLOR: Vector4<f32> = Vector4::new(1., 1., 1., 1.);
+const BACKGROUND_COLOR: Vector4<f32> = Vector4::new(0., 0., 0., 0.5);
+/// Space between the panel and the window border, and between the panel and its text, in glyphs
+const MARGIN: f32 = 0.5;
+
+/// Window corner the statistics panel is attached to
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum HudAnchor {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Statistic displayed as a line of the panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum HudItem {
+    Fps,
+    FrameTime,
+    DrawCalls,
+    Drawables,
+    Culled,
+    Particles,
+}
+
+impl HudItem {
+    fn format(self, statistics: &FrameStatistics) -> String {
+        let render = &statistics.render;
+        match self {
+            Self::Fps => format!("FPS:        {:.1}", statistics.fps()),
+            Self::FrameTime => format!("Frame time: {:.2} ms", statistics.frame_time_ms()),
+            Self::DrawCalls => format!("Draw calls: {}", render.draw_calls),
+            Self::Drawables => format!("Drawables:  {}", render.drawables),
+            Self::Culled => format!(
+                "Culled:     {} / {}",
+                render.culled,
+                render.culled + render.drawables
+            ),
+            Self::Particles => format!(
+                "Particles:  {} ({} systems)",
+                render.particles, render.particle_systems
+            ),
+        }
+    }
+}
+
+pub struct HudSettings {
+    pub visible: bool,
+    pub anchor: HudAnchor,
+    pub items: Vec<HudItem>,
+    /// Size multiplier of the 8 pixels font
+    pub scale: f32,
+}
+
+impl Default for HudSettings {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            anchor: Default::default(),
+            items: vec![
+                HudItem::Fps,
+                HudItem::FrameTime,
+                HudItem::DrawCalls,
+                HudItem::Particles,
+            ],
+            scale: 2.,
+        }
+    }
+}
+
+/// Overlay displaying the engine's runtime statistics
+pub struct Hud {
+    settings: HudSettings,
+    text: TextRenderer,
+}
+
+impl Hud {
+    pub fn new(settings: HudSettings, program: ProgramSharedPointer) -> EngineResult<Self> {
+        let text = TextRenderer::new(program, settings.scale)?;
+        Ok(Self { settings, text })
+    }
+
+    pub fn toggle(&mut self) {
+        self.settings.visible = !self.settings.visible;
+    }
+
+    pub fn settings_mut(&mut self) -> &mut HudSettings {
+        &mut self.settings
+    }
+
+    pub fn draw(&mut self, statistics: &FrameStatistics, width: i32, height: i32) {
+        if !self.settings.visible || self.settings.items.is_empty() {
+            return;
+        }
+
+        let content = self
+            .settings
+            .items
+            .iter()
+            .map(|item| item.format(statistics))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let margin = self.text.glyph_size() * MARGIN;
+        let panel_size = self.text.text_size(&content) + margin * 2.;
+        let (width, height) = (width as f32, height as f32);
+        let panel_position = match self.settings.anchor {
+            HudAnchor::TopLeft => margin,
+            HudAnchor::TopRight => Vector2::new(width - panel_size.x - margin.x, margin.y),
+            HudAnchor::BottomLeft => Vector2::new(margin.x, height - panel_size.y - margin.y),
+            HudAnchor::BottomRight => Vector2::new(
+                width - panel_size.x - margin.x,
+                height - panel_size.y - margin.y,
+            ),
+        };
+
+        self.text
+            .queue_rect(panel_position, panel_size, BACKGROUND_COLOR);
+        self.text
+            .queue_text(panel_position + margin, &content, TEXT_COLOR);
+        self.text.flush(width as _, height as _);
+    }
+}
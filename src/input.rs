@@ -1,13 +1,21 @@
+use std::collections::{HashMap, HashSet};
+
+use sdl2::{controller::Axis, event::Event, mouse::MouseWheelDirection};
+
+use self::bindings::{Action, AxisRange, Binding, Bindings, MouseAxis};
+
+pub mod bindings;
+
+/// Current state of the inputs, queried by action through the active [`Bindings`]
 #[derive(Default, Debug)]
 pub struct InputState {
-    pub forward: bool,
-    pub backward: bool,
-    pub left: bool,
-    pub right: bool,
-    pub up: bool,
-    pub down: bool,
-    pub mouse_x_axis: f32,
-    pub mouse_y_axis: f32,
+    bindings: Bindings,
+    held: HashSet<Binding>,
+    mouse_motion: (f32, f32),
+    /// Wheel notches scrolled during the frame
+    mouse_wheel: f32,
+    /// Raw game controller axes values, in [-1,1]
+    pad_axes: HashMap<Axis, f32>,
     pub capture_cursor: bool,
     pub focused: bool,
 }
@@ -30,4 +38,114 @@ impl InputState {
     pub unsafe fn get_mut() -> &'static mut Self {
         INSTANCE.get_or_insert_with(InputState::new)
     }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.bindings = bindings;
+        self.held.clear();
+    }
+
+    /// Resets the per frame inputs, to be called before handling a frame's events
+    pub fn begin_frame(&mut self) {
+        // no movement = no event
+        self.mouse_motion = (0., 0.);
+        self.mouse_wheel = 0.;
+    }
+
+    /// Updates the held inputs, returns the actions the event triggered
+    pub fn handle_event(&mut self, event: &Event) -> Vec<Action> {
+        if let Event::MouseWheel { y, direction, .. } = event {
+            let y = match direction {
+                MouseWheelDirection::Flipped => -y,
+                _ => *y,
+            };
+            self.mouse_wheel += y as f32;
+            return vec![];
+        }
+        let Some((binding, pressed)) = Binding::from_event(event) else {
+            return vec![];
+        };
+        if !pressed {
+            self.held.remove(&binding);
+            return vec![];
+        }
+        self.held.insert(binding);
+        self.bindings.actions(binding).collect()
+    }
+
+    pub fn set_mouse_motion(&mut self, x: f32, y: f32) {
+        self.mouse_motion = (x, y);
+    }
+
+    /// Sets a game controller axis from its SDL value
+    pub fn set_pad_axis(&mut self, axis: Axis, value: i16) {
+        self.pad_axes
+            .insert(axis, (value as f32 / i16::MAX as f32).clamp(-1., 1.));
+    }
+
+    /// Releases every held input, for when inputs stop reaching the engine
+    pub fn release_all(&mut self) {
+        self.held.clear();
+        self.mouse_motion = (0., 0.);
+        self.mouse_wheel = 0.;
+        self.pad_axes.clear();
+    }
+
+    /// Releases the game controllers' inputs, for when one is disconnected
+    pub fn release_pad(&mut self) {
+        self.held.retain(|b| !matches!(b, Binding::PadButton(_)));
+        self.pad_axes.clear();
+    }
+
+    fn pad_axis(&self, axis: Axis, range: AxisRange) -> f32 {
+        let raw = self.pad_axes.get(&axis).copied().unwrap_or_default();
+        let value = self.bindings.analog().shape(axis, raw);
+        match range {
+            AxisRange::Full => value,
+            AxisRange::Positive => value.max(0.),
+            AxisRange::Negative => (-value).max(0.),
+        }
+    }
+
+    /// Whether any input bound to the action is held, analog axes counting past half their range
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.value(action).abs() > 0.5
+    }
+
+    /// Analog value of the action in [-1,1]: 1 for held buttons, the shaped value of controller
+    /// axes. Mouse motion is not included, see [`InputState::motion`].
+    pub fn value(&self, action: Action) -> f32 {
+        let mut value = 0f32;
+        for binding in self.bindings.bindings(action) {
+            value += match binding {
+                Binding::MouseAxis(_) => 0.,
+                Binding::PadAxis(axis, range) => self.pad_axis(*axis, *range),
+                binding if self.held.contains(binding) => 1.,
+                _ => 0.,
+            };
+        }
+        value.clamp(-1., 1.)
+    }
+
+    /// Mouse motion bound to the action during this frame, in pixels or wheel notches
+    pub fn motion(&self, action: Action) -> f32 {
+        self.bindings
+            .bindings(action)
+            .iter()
+            .map(|binding| match binding {
+                Binding::MouseAxis(MouseAxis::X) => self.mouse_motion.0,
+                Binding::MouseAxis(MouseAxis::Y) => self.mouse_motion.1,
+                Binding::MouseAxis(MouseAxis::Wheel) => self.mouse_wheel,
+                _ => 0.,
+            })
+            .sum()
+    }
+
+    /// Difference between the values of two opposite actions, e.g. forward and backward
+    pub fn axis(&self, positive: Action, negative: Action) -> f32 {
+        self.value(positive) - self.value(negative)
+    }
 }
@@ -0,0 +1,508 @@
+use std::{collections::HashMap, fmt::Display, fs, path::Path, str::FromStr};
+
+use clap::ValueEnum;
+use sdl2::{
+    controller::{Axis, Button},
+    event::Event,
+    keyboard::Keycode,
+    mouse::MouseButton,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{EngineError, EngineResult};
+
+/// Named input the engine and its objects react to, independently of the physical input
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    /// Horizontal look axis, positive to the right
+    LookX,
+    /// Vertical look axis, positive downwards
+    LookY,
+    CaptureCursor,
+    ReleaseCursor,
+    Quit,
+    ToggleBrokenMouseFix,
+    DebugLog,
+    ToggleConsole,
+    ToggleDebugUi,
+    ToggleHud,
+    /// Switches the camera between free-fly and orbiting
+    ToggleOrbit,
+    /// Moves the orbited target with the look inputs while held
+    OrbitPan,
+    /// Zoom axis of the orbiting camera, positive towards the target
+    Zoom,
+    /// Orbits around the object the camera looks at
+    FrameObject,
+    /// Free-fly speed multiplier axis, positive to go faster
+    Speed,
+    /// Moves faster while held
+    Sprint,
+    /// Moves slower while held
+    Slow,
+    /// Adds a keyframe at the camera's pose to the camera path
+    RecordKeyframe,
+    /// Plays the camera path in a loop, or stops it
+    ToggleCameraPath,
+    /// Held while pressing a bookmark key to save the camera's pose in it instead of recalling it
+    StoreBookmark,
+    Bookmark1,
+    Bookmark2,
+    Bookmark3,
+    Bookmark4,
+    Bookmark5,
+    Bookmark6,
+    Bookmark7,
+    Bookmark8,
+    Bookmark9,
+}
+
+impl Action {
+    pub const ALL: [Action; 35] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::LookX,
+        Action::LookY,
+        Action::CaptureCursor,
+        Action::ReleaseCursor,
+        Action::Quit,
+        Action::ToggleBrokenMouseFix,
+        Action::DebugLog,
+        Action::ToggleConsole,
+        Action::ToggleDebugUi,
+        Action::ToggleHud,
+        Action::ToggleOrbit,
+        Action::OrbitPan,
+        Action::Zoom,
+        Action::FrameObject,
+        Action::Speed,
+        Action::Sprint,
+        Action::Slow,
+        Action::RecordKeyframe,
+        Action::ToggleCameraPath,
+        Action::StoreBookmark,
+        Action::Bookmark1,
+        Action::Bookmark2,
+        Action::Bookmark3,
+        Action::Bookmark4,
+        Action::Bookmark5,
+        Action::Bookmark6,
+        Action::Bookmark7,
+        Action::Bookmark8,
+        Action::Bookmark9,
+    ];
+
+    const BOOKMARKS: [Action; 9] = [
+        Action::Bookmark1,
+        Action::Bookmark2,
+        Action::Bookmark3,
+        Action::Bookmark4,
+        Action::Bookmark5,
+        Action::Bookmark6,
+        Action::Bookmark7,
+        Action::Bookmark8,
+        Action::Bookmark9,
+    ];
+
+    /// Number of the bookmark recalled by the action, from 1 to 9
+    pub fn bookmark_slot(self) -> Option<u8> {
+        Self::BOOKMARKS
+            .iter()
+            .position(|a| *a == self)
+            .map(|i| i as u8 + 1)
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // same name as in bindings files
+        let name = serde_json::to_value(self).ok();
+        write!(
+            f,
+            "{}",
+            name.as_ref().and_then(|n| n.as_str()).unwrap_or_default()
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseAxis {
+    X,
+    Y,
+    /// Vertical wheel, positive away from the user
+    Wheel,
+}
+
+/// Part of a game controller axis range a binding reacts to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AxisRange {
+    /// The signed value of the axis
+    Full,
+    /// The magnitude of the positive half of the axis
+    Positive,
+    /// The magnitude of the negative half of the axis
+    Negative,
+}
+
+/// Physical input an action can be bound to.
+///
+/// Written `key:<SDL key name>`, `mouse:<left|middle|right|x1|x2>`, `mouse_axis:<x|y|wheel>`,
+/// `pad:<SDL button name>` or `pad_axis:<SDL axis name>[+|-]` in bindings files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Key(Keycode),
+    MouseButton(MouseButton),
+    MouseAxis(MouseAxis),
+    PadButton(Button),
+    PadAxis(Axis, AxisRange),
+}
+
+impl Binding {
+    /// The binding changed by a button event, and whether it is now pressed
+    pub fn from_event(event: &Event) -> Option<(Binding, bool)> {
+        match event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                repeat: false,
+                ..
+            } => Some((Binding::Key(*keycode), true)),
+            Event::KeyUp {
+                keycode: Some(keycode),
+                ..
+            } => Some((Binding::Key(*keycode), false)),
+            Event::MouseButtonDown { mouse_btn, .. } => {
+                Some((Binding::MouseButton(*mouse_btn), true))
+            }
+            Event::MouseButtonUp { mouse_btn, .. } => {
+                Some((Binding::MouseButton(*mouse_btn), false))
+            }
+            Event::ControllerButtonDown { button, .. } => Some((Binding::PadButton(*button), true)),
+            Event::ControllerButtonUp { button, .. } => Some((Binding::PadButton(*button), false)),
+            _ => None,
+        }
+    }
+}
+
+fn mouse_button_name(button: MouseButton) -> &'static str {
+    match button {
+        MouseButton::Left => "left",
+        MouseButton::Middle => "middle",
+        MouseButton::Right => "right",
+        MouseButton::X1 => "x1",
+        MouseButton::X2 => "x2",
+        MouseButton::Unknown => "unknown",
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Key(keycode) => write!(f, "key:{}", keycode.name()),
+            Self::MouseButton(button) => write!(f, "mouse:{}", mouse_button_name(*button)),
+            Self::MouseAxis(MouseAxis::X) => write!(f, "mouse_axis:x"),
+            Self::MouseAxis(MouseAxis::Y) => write!(f, "mouse_axis:y"),
+            Self::MouseAxis(MouseAxis::Wheel) => write!(f, "mouse_axis:wheel"),
+            Self::PadButton(button) => write!(f, "pad:{}", button.string()),
+            Self::PadAxis(axis, range) => {
+                let suffix = match range {
+                    AxisRange::Full => "",
+                    AxisRange::Positive => "+",
+                    AxisRange::Negative => "-",
+                };
+                write!(f, "pad_axis:{}{suffix}", axis.string())
+            }
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (device, name) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected `device:name`, got `{s}`"))?;
+        match device {
+            "key" => Keycode::from_name(name)
+                .map(Binding::Key)
+                .ok_or_else(|| format!("unknown key `{name}`")),
+            "mouse" => {
+                let button = match name.to_lowercase().as_str() {
+                    "left" => MouseButton::Left,
+                    "middle" => MouseButton::Middle,
+                    "right" => MouseButton::Right,
+                    "x1" => MouseButton::X1,
+                    "x2" => MouseButton::X2,
+                    _ => return Err(format!("unknown mouse button `{name}`")),
+                };
+                Ok(Binding::MouseButton(button))
+            }
+            "mouse_axis" => match name.to_lowercase().as_str() {
+                "x" => Ok(Binding::MouseAxis(MouseAxis::X)),
+                "y" => Ok(Binding::MouseAxis(MouseAxis::Y)),
+                "wheel" => Ok(Binding::MouseAxis(MouseAxis::Wheel)),
+                _ => Err(format!("unknown mouse axis `{name}`")),
+            },
+            "pad" => Button::from_string(name)
+                .map(Binding::PadButton)
+                .ok_or_else(|| format!("unknown controller button `{name}`")),
+            "pad_axis" => {
+                let (name, range) = if let Some(name) = name.strip_suffix('+') {
+                    (name, AxisRange::Positive)
+                } else if let Some(name) = name.strip_suffix('-') {
+                    (name, AxisRange::Negative)
+                } else {
+                    (name, AxisRange::Full)
+                };
+                Axis::from_string(name)
+                    .map(|axis| Binding::PadAxis(axis, range))
+                    .ok_or_else(|| format!("unknown controller axis `{name}`"))
+            }
+            _ => Err(format!("unknown input device `{device}`")),
+        }
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Binding> for String {
+    fn from(value: Binding) -> Self {
+        value.to_string()
+    }
+}
+
+/// Built-in bindings, differing by the position of the movement keys
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyboardLayout {
+    /// Moves with `Z` `Q` `S` `D`
+    #[default]
+    Azerty,
+    /// Moves with `W` `A` `S` `D`
+    Qwerty,
+}
+
+/// Shaping of the game controllers' analog axes
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalogSettings {
+    /// Fraction of the stick range around the center that is ignored
+    pub dead_zone: f32,
+    /// Fraction of the trigger range from the rest position that is ignored
+    pub trigger_dead_zone: f32,
+    /// Exponent of the response curve applied after the dead zone, 1 being linear.
+    /// Higher values give finer control near the center.
+    pub response_exponent: f32,
+}
+
+impl Default for AnalogSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.15,
+            trigger_dead_zone: 0.05,
+            response_exponent: 2.,
+        }
+    }
+}
+
+impl AnalogSettings {
+    /// Applies the dead zone and response curve to a raw axis value in [-1,1]
+    pub fn shape(&self, axis: Axis, value: f32) -> f32 {
+        let dead_zone = match axis {
+            Axis::TriggerLeft | Axis::TriggerRight => self.trigger_dead_zone,
+            _ => self.dead_zone,
+        };
+        let magnitude = value.abs();
+        if magnitude <= dead_zone {
+            return 0.;
+        }
+        // rescaled so that the output starts from 0 at the edge of the dead zone
+        let magnitude = ((magnitude - dead_zone) / (1. - dead_zone)).min(1.);
+        magnitude.powf(self.response_exponent) * value.signum()
+    }
+}
+
+/// Content of a bindings file, whose bindings replace those of the preset for the listed actions
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BindingsFile {
+    preset: Option<KeyboardLayout>,
+    #[serde(default)]
+    bindings: HashMap<Action, Vec<Binding>>,
+    #[serde(default)]
+    analog: AnalogSettings,
+}
+
+/// Maps each action to the inputs triggering it, an action may have several bindings
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    actions: HashMap<Action, Vec<Binding>>,
+    analog: AnalogSettings,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self::preset(KeyboardLayout::default())
+    }
+}
+
+impl Bindings {
+    pub fn preset(layout: KeyboardLayout) -> Self {
+        use Action::*;
+        use Binding::Key;
+
+        let (forward, left) = match layout {
+            KeyboardLayout::Azerty => (Keycode::Z, Keycode::Q),
+            KeyboardLayout::Qwerty => (Keycode::W, Keycode::A),
+        };
+        let pad_axis = Binding::PadAxis;
+        let actions = HashMap::from([
+            (
+                MoveForward,
+                vec![Key(forward), pad_axis(Axis::LeftY, AxisRange::Negative)],
+            ),
+            (
+                MoveBackward,
+                vec![Key(Keycode::S), pad_axis(Axis::LeftY, AxisRange::Positive)],
+            ),
+            (
+                MoveLeft,
+                vec![Key(left), pad_axis(Axis::LeftX, AxisRange::Negative)],
+            ),
+            (
+                MoveRight,
+                vec![Key(Keycode::D), pad_axis(Axis::LeftX, AxisRange::Positive)],
+            ),
+            (
+                MoveUp,
+                vec![
+                    Key(Keycode::Space),
+                    pad_axis(Axis::TriggerRight, AxisRange::Full),
+                ],
+            ),
+            (
+                MoveDown,
+                vec![
+                    Key(Keycode::LShift),
+                    pad_axis(Axis::TriggerLeft, AxisRange::Full),
+                ],
+            ),
+            (
+                LookX,
+                vec![
+                    Binding::MouseAxis(MouseAxis::X),
+                    pad_axis(Axis::RightX, AxisRange::Full),
+                ],
+            ),
+            (
+                LookY,
+                vec![
+                    Binding::MouseAxis(MouseAxis::Y),
+                    pad_axis(Axis::RightY, AxisRange::Full),
+                ],
+            ),
+            (CaptureCursor, vec![Binding::MouseButton(MouseButton::Left)]),
+            (ReleaseCursor, vec![Key(Keycode::Tab)]),
+            (Quit, vec![Key(Keycode::Escape)]),
+            (ToggleBrokenMouseFix, vec![Key(Keycode::B)]),
+            (DebugLog, vec![Key(Keycode::L)]),
+            (ToggleConsole, vec![Key(Keycode::F1)]),
+            (ToggleDebugUi, vec![Key(Keycode::F2)]),
+            (
+                ToggleHud,
+                vec![Key(Keycode::F3), Binding::PadButton(Button::Back)],
+            ),
+            (
+                ToggleOrbit,
+                vec![Key(Keycode::O), Binding::PadButton(Button::RightStick)],
+            ),
+            (OrbitPan, vec![Binding::MouseButton(MouseButton::Middle)]),
+            (Zoom, vec![Binding::MouseAxis(MouseAxis::Wheel)]),
+            (
+                FrameObject,
+                vec![Key(Keycode::F), Binding::PadButton(Button::LeftStick)],
+            ),
+            (Speed, vec![Binding::MouseAxis(MouseAxis::Wheel)]),
+            (
+                Sprint,
+                vec![
+                    Key(Keycode::LCtrl),
+                    Binding::PadButton(Button::RightShoulder),
+                ],
+            ),
+            (RecordKeyframe, vec![Key(Keycode::K)]),
+            (ToggleCameraPath, vec![Key(Keycode::P)]),
+            (StoreBookmark, vec![Key(Keycode::RCtrl)]),
+            (Bookmark1, vec![Key(Keycode::Num1), Key(Keycode::Kp1)]),
+            (Bookmark2, vec![Key(Keycode::Num2), Key(Keycode::Kp2)]),
+            (Bookmark3, vec![Key(Keycode::Num3), Key(Keycode::Kp3)]),
+            (Bookmark4, vec![Key(Keycode::Num4), Key(Keycode::Kp4)]),
+            (Bookmark5, vec![Key(Keycode::Num5), Key(Keycode::Kp5)]),
+            (Bookmark6, vec![Key(Keycode::Num6), Key(Keycode::Kp6)]),
+            (Bookmark7, vec![Key(Keycode::Num7), Key(Keycode::Kp7)]),
+            (Bookmark8, vec![Key(Keycode::Num8), Key(Keycode::Kp8)]),
+            (Bookmark9, vec![Key(Keycode::Num9), Key(Keycode::Kp9)]),
+            (
+                Slow,
+                vec![Key(Keycode::LAlt), Binding::PadButton(Button::LeftShoulder)],
+            ),
+        ]);
+        Self {
+            actions,
+            analog: Default::default(),
+        }
+    }
+
+    /// Loads a bindings file, on top of the preset it names or `default_layout`'s preset
+    pub fn load(path: &Path, default_layout: KeyboardLayout) -> EngineResult<Self> {
+        let content = fs::read_to_string(path).map_err(|e| EngineError::io(path, e))?;
+        let file: BindingsFile =
+            serde_json::from_str(&content).map_err(|e| EngineError::json(path, 1, &e))?;
+        let mut bindings = Self::preset(file.preset.unwrap_or(default_layout));
+        bindings.actions.extend(file.bindings);
+        bindings.analog = file.analog;
+        Ok(bindings)
+    }
+
+    pub fn analog(&self) -> &AnalogSettings {
+        &self.analog
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.actions.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Actions triggered by the binding
+    pub fn actions(&self, binding: Binding) -> impl Iterator<Item = Action> + '_ {
+        self.actions
+            .iter()
+            .filter(move |(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    /// Whether the event is the press of an input bound to `action`
+    pub fn is_press_of(&self, event: &Event, action: Action) -> bool {
+        matches!(
+            Binding::from_event(event),
+            Some((binding, true)) if self.bindings(action).contains(&binding)
+        )
+    }
+}
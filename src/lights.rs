@@ -0,0 +1,335 @@
+use std::{fmt::Display, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Deg, EuclideanSpace, InnerSpace, Point3, Vector3};
+use clap::ValueEnum;
+use gl::types::{GLfloat, GLint, GLuint};
+
+use crate::{
+    extensions::SafeNormalize, gl_check, gl_checked, gl_debug, gl_types::BufferIdType,
+    shadows::ShadowCaster,
+};
+
+/// Lights the shaders can read, the others being ignored
+pub const MAX_LIGHTS: usize = 16;
+/// Uniform block binding point of the light list, `layout(std140, binding = 0) uniform Lights`
+pub const LIGHTS_BINDING: GLuint = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LightKind {
+    /// Parallel rays, like the sun's, the position being ignored
+    Directional,
+    /// Rays in all directions from the position, fading out at the range
+    Point,
+    /// Point light restricted to a cone around the direction
+    Spot,
+}
+
+impl Display for LightKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Directional => write!(f, "directional"),
+            Self::Point => write!(f, "point"),
+            Self::Spot => write!(f, "spot"),
+        }
+    }
+}
+
+/// Light source of the scene, lighting the `mesh` program's drawables
+#[derive(Clone, Debug, PartialEq)]
+pub struct Light {
+    pub name: String,
+    pub kind: LightKind,
+    pub position: Point3<GLfloat>,
+    /// Direction the light travels in, for directional and spot lights
+    pub direction: Vector3<GLfloat>,
+    /// Linear RGB color, multiplied by the intensity
+    pub color: Vector3<GLfloat>,
+    pub intensity: GLfloat,
+    /// Distance at which point and spot lights no longer light anything
+    pub range: GLfloat,
+    /// Half angle of the cone lit fully by a spot light
+    pub inner_angle: Deg<GLfloat>,
+    /// Half angle of the cone lit by a spot light, fading out from the inner one
+    pub outer_angle: Deg<GLfloat>,
+    pub enabled: bool,
+    /// Whether the shadow casters hide the light, for directional and spot lights
+    pub cast_shadows: bool,
+}
+
+impl Light {
+    pub const NAMES: [&'static str; 10] = [
+        "kind",
+        "position",
+        "direction",
+        "color",
+        "intensity",
+        "range",
+        "inner_angle",
+        "outer_angle",
+        "enabled",
+        "cast_shadows",
+    ];
+
+    /// White light of intensity 1, pointing down from 4 units above the origin
+    pub fn new(name: impl Into<String>, kind: LightKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            position: Point3::new(0., 0., 4.),
+            direction: -Vector3::unit_z(),
+            color: Vector3::new(1., 1., 1.),
+            intensity: 1.,
+            range: 10.,
+            inner_angle: Deg(20.),
+            outer_angle: Deg(30.),
+            enabled: true,
+            cast_shadows: false,
+        }
+    }
+
+    /// Value of the named member as text, vectors component by component
+    pub fn get(&self, name: &str) -> Option<String> {
+        let vector = |v: [GLfloat; 3]| format!("{} {} {}", v[0], v[1], v[2]);
+        Some(match name {
+            "kind" => self.kind.to_string(),
+            "position" => vector(self.position.into()),
+            "direction" => vector(self.direction.into()),
+            "color" => vector(self.color.into()),
+            "intensity" => self.intensity.to_string(),
+            "range" => self.range.to_string(),
+            "inner_angle" => self.inner_angle.0.to_string(),
+            "outer_angle" => self.outer_angle.0.to_string(),
+            "enabled" => self.enabled.to_string(),
+            "cast_shadows" => self.cast_shadows.to_string(),
+            _ => return None,
+        })
+    }
+
+    /// Parses and sets the named member, vectors being given component by component
+    pub fn set(&mut self, name: &str, values: &[&str]) -> Result<(), String> {
+        let floats = |count: usize| -> Result<Vec<GLfloat>, String> {
+            if values.len() != count {
+                return Err(format!("expected {count} value(s), got {}", values.len()));
+            }
+            values
+                .iter()
+                .map(|v| v.parse().map_err(|_| format!("invalid number `{v}`")))
+                .collect()
+        };
+        let vector = || floats(3).map(|v| Vector3::new(v[0], v[1], v[2]));
+        match name {
+            "kind" => match values {
+                [value] => self.kind = LightKind::from_str(value, true)?,
+                _ => return Err("expected `directional`, `point` or `spot`".to_owned()),
+            },
+            "position" => self.position = Point3::from_vec(vector()?),
+            "direction" => self.direction = vector()?,
+            "color" => self.color = vector()?,
+            "intensity" => self.intensity = floats(1)?[0],
+            "range" => self.range = floats(1)?[0],
+            "inner_angle" => self.inner_angle = Deg(floats(1)?[0]),
+            "outer_angle" => self.outer_angle = Deg(floats(1)?[0]),
+            "enabled" | "cast_shadows" => {
+                let [value] = values else {
+                    return Err("expected `true` or `false`".to_owned());
+                };
+                let value = value.parse().map_err(|_| "expected `true` or `false`")?;
+                match name {
+                    "enabled" => self.enabled = value,
+                    _ => self.cast_shadows = value,
+                }
+            }
+            _ => return Err(format!("unknown light member `{name}`")),
+        }
+        self.sanitize();
+        Ok(())
+    }
+
+    /// Keeps the members in ranges the shaders handle
+    pub fn sanitize(&mut self) {
+        if self.direction.magnitude2() == 0. {
+            self.direction = -Vector3::unit_z();
+        }
+        self.direction = self.direction.normalize();
+        self.color = self.color.map(|c| c.max(0.));
+        self.intensity = self.intensity.max(0.);
+        self.range = self.range.max(1e-3);
+        self.outer_angle = Deg(self.outer_angle.0.clamp(0.1, 89.9));
+        self.inner_angle = Deg(self.inner_angle.0.clamp(0., self.outer_angle.0 - 0.1));
+    }
+
+    /// `shadow_layers` being the first layer of the light's shadow maps and their count
+    fn gpu(&self, shadow_layers: Option<(usize, usize)>) -> GpuLight {
+        let (shadow_layer, shadow_layer_count) = shadow_layers.unwrap_or_default();
+        GpuLight {
+            position: self.position.into(),
+            kind: self.kind as GLint,
+            direction: self.direction.safe_normalize().into(),
+            range: self.range,
+            color: (self.color * self.intensity).into(),
+            cos_inner: self.inner_angle.0.to_radians().cos(),
+            cos_outer: self.outer_angle.0.to_radians().cos(),
+            shadow_layer: shadow_layer as GLint,
+            shadow_layer_count: shadow_layer_count as GLint,
+            _padding: 0.,
+        }
+    }
+}
+
+/// Lights of the scene and the ambient light added to them
+#[derive(Clone, Debug)]
+pub struct LightSet {
+    lights: Vec<Light>,
+    /// Linear RGB light reaching every surface, however it faces
+    pub ambient: Vector3<GLfloat>,
+}
+
+impl Default for LightSet {
+    /// A sun shining down at an angle, and a dim ambient light
+    fn default() -> Self {
+        let mut sun = Light::new("sun", LightKind::Directional);
+        sun.direction = -Vector3::new(0.4, 0.3, 1.).normalize();
+        sun.intensity = 0.8;
+        sun.cast_shadows = true;
+        Self {
+            lights: vec![sun],
+            ambient: Vector3::new(0.2, 0.2, 0.2),
+        }
+    }
+}
+
+impl LightSet {
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn light(&self, name: &str) -> Option<&Light> {
+        self.lights.iter().find(|l| l.name == name)
+    }
+
+    pub fn light_mut(&mut self, name: &str) -> Option<&mut Light> {
+        self.lights.iter_mut().find(|l| l.name == name)
+    }
+
+    /// Adds a light, replacing any light of the same name
+    pub fn add(&mut self, mut light: Light) {
+        light.sanitize();
+        match self.light_mut(&light.name) {
+            Some(existing) => *existing = light,
+            None => {
+                if self.lights.len() >= MAX_LIGHTS {
+                    log::warn!(
+                        "more than {MAX_LIGHTS} lights, `{}` won't light anything",
+                        light.name
+                    );
+                }
+                self.lights.push(light)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Light> {
+        let index = self.lights.iter().position(|l| l.name == name)?;
+        Some(self.lights.remove(index))
+    }
+}
+
+/// std140 layout of a light in the `Lights` uniform block
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuLight {
+    position: [GLfloat; 3],
+    kind: GLint,
+    direction: [GLfloat; 3],
+    range: GLfloat,
+    /// Premultiplied by the intensity
+    color: [GLfloat; 3],
+    cos_inner: GLfloat,
+    cos_outer: GLfloat,
+    /// Layer of the first shadow map, of the cascades of directional lights
+    shadow_layer: GLint,
+    /// 0 without shadows
+    shadow_layer_count: GLint,
+    _padding: GLfloat,
+}
+
+/// std140 layout of the `Lights` uniform block before its array of lights
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuLightsHeader {
+    ambient: [GLfloat; 3],
+    light_count: GLint,
+    camera_position: [GLfloat; 3],
+    _padding: GLfloat,
+}
+
+/// Uniform buffer of the `Lights` block, bound to [`LIGHTS_BINDING`]
+pub struct LightBuffer {
+    id: BufferIdType,
+}
+
+impl LightBuffer {
+    const SIZE: usize = size_of::<GpuLightsHeader>() + MAX_LIGHTS * size_of::<GpuLight>();
+
+    pub fn new() -> Self {
+        let mut id: BufferIdType = 0;
+        unsafe {
+            gl_checked! {
+                gl::CreateBuffers(1, &mut id);
+                gl::NamedBufferStorage(
+                    id,
+                    Self::SIZE as _,
+                    std::ptr::null(),
+                    gl::DYNAMIC_STORAGE_BIT,
+                );
+                gl::BindBufferBase(gl::UNIFORM_BUFFER, LIGHTS_BINDING, id);
+            };
+        }
+        gl_debug::label_object(gl::BUFFER, id, "lights");
+        Self { id }
+    }
+
+    /// Uploads the enabled lights, seen from a camera at `camera_position`, with the layers of
+    /// the shadow maps rendered for them
+    pub fn upload(
+        &self,
+        lights: &LightSet,
+        camera_position: Point3<GLfloat>,
+        shadow_casters: &[ShadowCaster],
+    ) {
+        let gpu_lights: Vec<GpuLight> = lights
+            .lights
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| l.enabled)
+            .take(MAX_LIGHTS)
+            .map(|(index, light)| {
+                let caster = shadow_casters.iter().find(|c| c.light == index);
+                light.gpu(caster.map(|c| (c.first_layer, c.layer_count)))
+            })
+            .collect();
+        let header = GpuLightsHeader {
+            ambient: lights.ambient.into(),
+            light_count: gpu_lights.len() as GLint,
+            camera_position: camera_position.into(),
+            _padding: 0.,
+        };
+        let mut data = Vec::with_capacity(Self::SIZE);
+        data.extend_from_slice(bytemuck::bytes_of(&header));
+        data.extend_from_slice(bytemuck::cast_slice(&gpu_lights));
+        unsafe {
+            gl::NamedBufferSubData(self.id, 0, data.len() as _, data.as_ptr() as _);
+            gl_check!();
+        }
+    }
+}
+
+impl Drop for LightBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.id);
+            gl_check!();
+        }
+    }
+}
@@ -8,20 +8,55 @@ extern crate log;
 extern crate sdl2;
 extern crate rand;
 
+use std::{path::PathBuf, process::ExitCode};
+
 use clap::Parser;
 use engine::Engine;
+use hud::{HudAnchor, HudItem, HudSettings};
+use input::{
+    bindings::{Bindings, KeyboardLayout},
+    InputState,
+};
+use profiler::ProfilerSettings;
+use projection::{Projection, ProjectionKind};
+use remote::RemoteEndpoint;
+use shadows::ShadowSettings;
+use view::ViewLayout;
+mod bookmarks;
+mod bounds;
+mod camera_path;
+mod commands;
+mod console;
+mod debug_ui;
 mod definitions;
 mod engine;
+mod error;
 mod gl_check;
+mod gl_debug;
 mod gl_utils;
+mod gltf_scene;
 mod input;
+mod lights;
 mod logger;
+mod obj;
+mod primitives;
 mod objects;
 mod program;
+mod projection;
 mod traits;
+mod texture;
+mod vertex_layout;
+mod view;
 mod gl_types;
+mod hud;
 mod extensions;
 mod particles;
+mod profiler;
+mod replay;
+mod remote;
+mod screenshot;
+mod shadows;
+mod statistics;
 
 #[derive(Parser)]
 #[command(name = "POGLA project")]
@@ -31,25 +66,286 @@ struct Args {
     #[arg(short = 'b', long)]
     /// Whether or not the sdl2 relative mouse mode implementation is broken (it is when you can move you mouse freely after clicking on the window)
     relative_mouse_broken: bool,
+
+    #[arg(short = 'k', long, value_enum, default_value_t = KeyboardLayout::Azerty)]
+    /// Built-in key bindings preset, used unless the bindings file names another one
+    keyboard_layout: KeyboardLayout,
+
+    #[arg(long, value_name = "FILE")]
+    /// JSON file mapping actions to keys, mouse buttons and axes, on top of the preset
+    bindings: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Do not request an OpenGL debug context, disabling driver debug messages, object labels and debug groups
+    no_gl_debug: bool,
+
+    #[arg(short = 'p', long)]
+    /// Measure CPU and GPU time of each frame pass, and log a summary periodically
+    profile: bool,
+
+    #[arg(long, value_name = "FILE")]
+    /// Write the profiling summary to FILE on exit, as CSV if FILE ends with `.csv`, as JSON otherwise (implies --profile)
+    profile_output: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    /// Write every profiled scope to FILE in the Chrome trace format, viewable in chrome://tracing (implies --profile)
+    chrome_trace: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Seed of the random number generators, random by default
+    seed: Option<u64>,
+
+    #[arg(long, value_name = "FILE")]
+    /// Record every frame's input and time step to FILE
+    record: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "seed")]
+    /// Replay the input and time steps recorded in FILE, then return to live input
+    replay: Option<PathBuf>,
+
+    #[arg(long, requires = "replay")]
+    /// Close the program once the replay is over
+    exit_after_replay: bool,
+
+    #[arg(long, value_name = "FILE", default_value = "camera_bookmarks.json")]
+    /// Load the camera bookmarks from FILE, where new bookmarks are saved, or the shared ones of `resources/bookmarks/default.json` until FILE exists
+    bookmarks: PathBuf,
+
+    #[arg(long, value_name = "NAME")]
+    /// Start at the pose of a camera bookmark
+    camera: Option<String>,
+
+    #[arg(long = "mesh", value_name = "FILE")]
+    /// Load a Wavefront OBJ mesh and the materials of its MTL files, can be repeated
+    meshes: Vec<PathBuf>,
+
+    #[arg(long = "scene", value_name = "FILE")]
+    /// Load the meshes, materials and cameras of a glTF file (.gltf or .glb), can be repeated
+    scenes: Vec<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    /// Load the camera path keyframes from FILE, where they are also saved by default
+    camera_path: Option<PathBuf>,
+
+    #[arg(long, requires = "camera_path")]
+    /// Start playing the camera path in a loop
+    play_camera_path: bool,
+
+    #[arg(long, value_enum, default_value_t = ProjectionKind::Perspective)]
+    /// Projection of the camera, can be changed at runtime with `set camera.projection`
+    projection: ProjectionKind,
+
+    #[arg(long, value_name = "DEGREES", default_value_t = definitions::DEFAULT_FOV.to_degrees())]
+    /// Vertical field of view of the perspective projection
+    fov: f32,
+
+    #[arg(long, default_value_t = definitions::DEFAULT_ORTHO_HEIGHT)]
+    /// Height of the orthographic projection's view box, in world units
+    ortho_height: f32,
+
+    #[arg(long, default_value_t = definitions::DEFAULT_ZNEAR)]
+    /// Distance of the near clip plane
+    near: f32,
+
+    #[arg(long, default_value_t = definitions::DEFAULT_ZFAR)]
+    /// Distance of the far clip plane, ignored by reversed-Z perspective projections
+    far: f32,
+
+    #[arg(long)]
+    /// Map the near plane to depth 1 and the far one to 0, with an infinite far plane for perspective projections
+    reversed_z: bool,
+
+    #[arg(long, value_enum, default_value_t = ViewLayout::Single)]
+    /// Cameras rendered into the window, more can be added with the `view` command
+    layout: ViewLayout,
+
+    #[arg(long)]
+    /// Draw every object, even those out of the cameras' view
+    no_culling: bool,
+
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    /// Draw an N by N grid of cubes, with a single instanced draw call
+    cube_grid: u32,
+
+    #[arg(long)]
+    /// Render no shadows at startup, they can still be enabled with the `shadow` command
+    no_shadows: bool,
+
+    #[arg(long, value_name = "TEXELS", default_value_t = ShadowSettings::default().map_size)]
+    /// Width and height of each shadow map
+    shadow_map_size: i32,
+
+    #[arg(long)]
+    /// Hide the statistics overlay at startup, it can still be toggled with F3
+    hide_hud: bool,
+
+    #[arg(long, value_enum, default_value_t = HudAnchor::TopLeft)]
+    /// Window corner of the statistics overlay
+    hud_position: HudAnchor,
+
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = HudSettings::default().items)]
+    /// Comma separated statistics displayed by the overlay, in order
+    hud_items: Vec<HudItem>,
+
+    #[arg(long, default_value_t = 2.)]
+    /// Size multiplier of the overlay's 8 pixels font
+    hud_scale: f32,
+
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH")]
+    /// Listen for JSON-RPC remote control requests on the Unix domain socket PATH
+    rpc_socket: Option<PathBuf>,
+
+    #[arg(long, value_name = "PORT")]
+    /// Listen for JSON-RPC remote control requests on the localhost TCP port PORT
+    rpc_port: Option<u16>,
 }
 
-fn main() {
+fn main() -> ExitCode {
     logger::init_default();
 
     let args = Args::parse();
 
-    unsafe { engine::BROKEN_RELATIVE_MOUSE_MODE = args.relative_mouse_broken };
+    unsafe {
+        engine::BROKEN_RELATIVE_MOUSE_MODE = args.relative_mouse_broken;
+        engine::GL_DEBUG_CONTEXT = !args.no_gl_debug;
+    };
+
+    let bindings = match args.bindings.as_ref() {
+        Some(path) => match Bindings::load(path, args.keyboard_layout) {
+            Ok(bindings) => bindings,
+            Err(error) => {
+                log::error!("invalid bindings file: {}", error.chain());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Bindings::preset(args.keyboard_layout),
+    };
+    unsafe { InputState::get_mut() }.set_bindings(bindings);
 
     let engine = unsafe { Engine::instance_mut() };
 
-    engine.init();
+    engine.set_hud_settings(HudSettings {
+        visible: !args.hide_hud,
+        anchor: args.hud_position,
+        items: args.hud_items,
+        scale: args.hud_scale,
+    });
+
+    let mut projection = Projection {
+        kind: args.projection,
+        fov: args.fov.to_radians(),
+        ortho_height: args.ortho_height,
+        near: args.near,
+        far: args.far,
+        reversed_z: args.reversed_z,
+        ..Default::default()
+    };
+    projection.sanitize();
+    engine.set_projection(projection);
+    engine.set_view_layout(args.layout);
+    engine.set_culling(!args.no_culling);
+    engine.set_cube_grid(args.cube_grid);
+    let shadow_settings = ShadowSettings {
+        enabled: !args.no_shadows,
+        map_size: args.shadow_map_size,
+        ..Default::default()
+    };
+    // the shadow maps are allocated at initialization
+    if let Err(error) = engine.set_shadow_settings(shadow_settings) {
+        log::error!("invalid shadow settings: {}", error.chain());
+        return ExitCode::FAILURE;
+    }
+
+    if args.profile || args.profile_output.is_some() || args.chrome_trace.is_some() {
+        engine.profiler().borrow_mut().enable(ProfilerSettings {
+            output: args.profile_output,
+            chrome_trace: args.chrome_trace,
+        });
+    }
+
+    if let Some(seed) = args.seed {
+        engine.set_seed(seed);
+    }
+    if let Some(path) = args.replay.as_ref() {
+        if let Err(error) = engine.start_replay(path, args.exit_after_replay) {
+            log::error!("couldn't load the replay: {}", error.chain());
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Some(path) = args.record.as_ref() {
+        if let Err(error) = engine.start_recording(path) {
+            log::error!("couldn't start recording: {}", error.chain());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Err(error) = engine.init() {
+        log::error!("engine initialization failed: {}", error.chain());
+        return ExitCode::FAILURE;
+    }
+
+    for path in args.meshes.iter() {
+        if let Err(error) = engine.load_mesh(path) {
+            log::error!("couldn't load the mesh: {}", error.chain());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    for path in args.scenes.iter() {
+        if let Err(error) = engine.load_scene(path) {
+            log::error!("couldn't load the scene: {}", error.chain());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Err(error) = engine.load_bookmarks(&args.bookmarks) {
+        log::error!("couldn't load the camera bookmarks: {}", error.chain());
+        return ExitCode::FAILURE;
+    }
+    if let Some(name) = args.camera.as_ref() {
+        if !engine.go_to_bookmark(name) {
+            log::error!(
+                "no camera bookmark `{name}` in `{}`",
+                args.bookmarks.display()
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(path) = args.camera_path.as_ref() {
+        if let Err(error) = engine.load_camera_path(path) {
+            log::error!("couldn't load the camera path: {}", error.chain());
+            return ExitCode::FAILURE;
+        }
+        engine.play_camera_path(args.play_camera_path);
+    }
+
+    let mut endpoints: Vec<RemoteEndpoint> =
+        args.rpc_port.map(RemoteEndpoint::Tcp).into_iter().collect();
+    #[cfg(unix)]
+    endpoints.extend(args.rpc_socket.map(RemoteEndpoint::Unix));
+    for endpoint in endpoints.iter() {
+        if let Err(error) = engine.listen(endpoint) {
+            log::error!("remote control unavailable: {}", error.chain());
+            return ExitCode::FAILURE;
+        }
+    }
 
     let mut should_close = false;
 
     while !should_close {
         engine
             .update(&mut should_close) // update objects and handle events
+            .process_remote() // execute remote control requests
             .display() // Draw objects to window
             .swap_buffer();
     }
+
+    if let Err(error) = engine.finish() {
+        log::error!("{}", error.chain());
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,601 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use cgmath::{InnerSpace, Vector3, Vector4};
+use gl::types::GLfloat;
+
+use crate::{
+    error::{EngineError, EngineResult},
+    extensions::SafeNormalize,
+    objects::{Indices, MeshRenderer, MeshRendererBuilder, MeshSection},
+    program::{uniform::UniformValue, ProgramSharedPointer},
+    texture::{Sampling, Texture, TexturePointer},
+};
+
+/// Surface properties of an MTL material
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub name: String,
+    pub diffuse: [GLfloat; 3],
+    pub specular: [GLfloat; 3],
+    pub shininess: GLfloat,
+    pub opacity: GLfloat,
+    /// Diffuse color image, relative to the working directory
+    pub diffuse_texture: Option<PathBuf>,
+}
+
+impl Material {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            diffuse: [0.8; 3],
+            specular: [0.; 3],
+            shininess: 0.,
+            opacity: 1.,
+            diffuse_texture: None,
+        }
+    }
+
+    /// Material of a diffuse color and opacity, with a faint white highlight
+    pub fn with_color(name: impl Into<String>, color: Vector4<GLfloat>) -> Self {
+        Self {
+            diffuse: color.truncate().into(),
+            specular: [0.2; 3],
+            shininess: 32.,
+            opacity: color.w,
+            ..Self::new(name)
+        }
+    }
+
+    /// Uniform values applied while drawing the faces using the material, all of them being
+    /// set so that none is left to the previously drawn material
+    pub fn uniforms(&self) -> Vec<(String, UniformValue)> {
+        let [r, g, b] = self.diffuse;
+        vec![
+            (
+                "object_color".to_owned(),
+                UniformValue::Vec4(Vector4::new(r, g, b, self.opacity)),
+            ),
+            (
+                "specular_color".to_owned(),
+                UniformValue::Vec3(Vector3::from(self.specular)),
+            ),
+            (
+                "shininess".to_owned(),
+                UniformValue::Float(self.shininess.max(1.)),
+            ),
+        ]
+    }
+
+    /// Parses the materials of an MTL file
+    pub fn load_library(path: &Path) -> EngineResult<Vec<Self>> {
+        let content = fs::read_to_string(path).map_err(|e| EngineError::io(path, e))?;
+        let mut materials: Vec<Self> = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            let mut line = Line::new(path, index + 1, line);
+            let Some(keyword) = line.keyword() else {
+                continue;
+            };
+            if keyword == "newmtl" {
+                materials.push(Self::new(line.rest()?));
+                continue;
+            }
+            let Some(material) = materials.last_mut() else {
+                return Err(line.error(format!("`{keyword}` before any `newmtl`")));
+            };
+            match keyword {
+                "Kd" => material.diffuse = line.floats()?,
+                "Ks" => material.specular = line.floats()?,
+                "Ns" => material.shininess = line.floats::<1>()?[0],
+                "d" => material.opacity = line.floats::<1>()?[0],
+                "Tr" => material.opacity = 1. - line.floats::<1>()?[0],
+                "map_Kd" => {
+                    let file = line.rest()?;
+                    material.diffuse_texture = Some(relative_to(path, file));
+                }
+                _ => log::debug!("{}:{}: ignoring `{keyword}`", path.display(), index + 1),
+            }
+        }
+        Ok(materials)
+    }
+}
+
+/// Range of a mesh's indices drawn with the same material
+#[derive(Clone, Debug)]
+pub struct ObjSection {
+    pub material: Option<String>,
+    pub start: usize,
+    pub count: usize,
+}
+
+/// Indexed triangles of a Wavefront OBJ file, with one position, normal and texture coordinate
+/// per vertex
+#[derive(Clone, Debug, Default)]
+pub struct ObjMesh {
+    pub positions: Vec<GLfloat>,
+    pub normals: Vec<GLfloat>,
+    pub uvs: Vec<GLfloat>,
+    pub indices: Vec<u32>,
+    pub sections: Vec<ObjSection>,
+    pub materials: HashMap<String, Material>,
+}
+
+/// Position, texture coordinate and normal indices of a face corner
+type Corner = (usize, Option<usize>, Option<usize>);
+
+impl ObjMesh {
+    /// Parses an OBJ file and the MTL files it references, polygons being split in triangle fans,
+    /// and normals being computed for the vertices without one
+    pub fn load(path: &Path) -> EngineResult<Self> {
+        let content = fs::read_to_string(path).map_err(|e| EngineError::io(path, e))?;
+
+        let mut positions: Vec<[GLfloat; 3]> = Vec::new();
+        let mut uvs: Vec<[GLfloat; 2]> = Vec::new();
+        let mut normals: Vec<[GLfloat; 3]> = Vec::new();
+        let mut vertices: HashMap<Corner, u32> = HashMap::new();
+        let mut mesh = Self::default();
+        let mut section = ObjSection {
+            material: None,
+            start: 0,
+            count: 0,
+        };
+
+        for (index, line) in content.lines().enumerate() {
+            let mut line = Line::new(path, index + 1, line);
+            let Some(keyword) = line.keyword() else {
+                continue;
+            };
+            match keyword {
+                "v" => positions.push(line.floats()?),
+                "vt" => {
+                    let [u, v] = line.floats_or(0.)?;
+                    uvs.push([u, v]);
+                }
+                "vn" => normals.push(line.floats()?),
+                "f" => {
+                    let corners = line
+                        .words()
+                        .map(|word| parse_corner(&line, word, &positions, &uvs, &normals))
+                        .collect::<EngineResult<Vec<_>>>()?;
+                    if corners.len() < 3 {
+                        return Err(line.error("a face needs at least 3 vertices"));
+                    }
+                    let indices: Vec<u32> = corners
+                        .iter()
+                        .map(|corner| {
+                            mesh.vertex(&mut vertices, *corner, &positions, &uvs, &normals)
+                        })
+                        .collect();
+                    for i in 1..indices.len() - 1 {
+                        mesh.indices
+                            .extend([indices[0], indices[i], indices[i + 1]]);
+                    }
+                    section.count += (indices.len() - 2) * 3;
+                }
+                "usemtl" => {
+                    let name = line.rest()?.to_owned();
+                    if !mesh.materials.contains_key(&name) {
+                        log::warn!(
+                            "{}:{}: unknown material `{name}`",
+                            path.display(),
+                            index + 1
+                        );
+                    }
+                    let start = mesh.indices.len();
+                    let previous = std::mem::replace(
+                        &mut section,
+                        ObjSection {
+                            material: Some(name),
+                            start,
+                            count: 0,
+                        },
+                    );
+                    if previous.count > 0 {
+                        mesh.sections.push(previous);
+                    }
+                }
+                "mtllib" => {
+                    let library = relative_to(path, line.rest()?);
+                    match Material::load_library(&library) {
+                        Ok(materials) => mesh
+                            .materials
+                            .extend(materials.into_iter().map(|m| (m.name.clone(), m))),
+                        // the geometry is still usable with the default material
+                        Err(error) => {
+                            log::warn!("{}:{}: {}", path.display(), index + 1, error.chain())
+                        }
+                    }
+                }
+                "o" | "g" | "s" => {}
+                _ => log::debug!("{}:{}: ignoring `{keyword}`", path.display(), index + 1),
+            }
+        }
+        if section.count > 0 {
+            mesh.sections.push(section);
+        }
+        if mesh.indices.is_empty() {
+            return Err(EngineError::parse(
+                path,
+                content.lines().count(),
+                "no faces",
+            ));
+        }
+
+        mesh.compute_missing_normals(&vertices);
+        Ok(mesh)
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len() / 3
+    }
+
+    /// Index of the vertex of a face corner, added if it's the first corner using it
+    fn vertex(
+        &mut self,
+        vertices: &mut HashMap<Corner, u32>,
+        corner: Corner,
+        positions: &[[GLfloat; 3]],
+        uvs: &[[GLfloat; 2]],
+        normals: &[[GLfloat; 3]],
+    ) -> u32 {
+        *vertices.entry(corner).or_insert_with(|| {
+            let (position, uv, normal) = corner;
+            self.positions.extend(positions[position]);
+            self.uvs.extend(uv.map_or([0.; 2], |i| uvs[i]));
+            self.normals.extend(normal.map_or([0.; 3], |i| normals[i]));
+            (self.positions.len() / 3 - 1) as u32
+        })
+    }
+
+    /// Gives the vertices without a normal the area weighted average of their faces' normals
+    fn compute_missing_normals(&mut self, vertices: &HashMap<Corner, u32>) {
+        let missing: Vec<usize> = vertices
+            .iter()
+            .filter(|((_, _, normal), _)| normal.is_none())
+            .map(|(_, index)| *index as usize)
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+
+        let normals = smooth_normals(&self.positions, &self.indices);
+        for i in missing {
+            self.normals[i * 3..i * 3 + 3].copy_from_slice(&normals[i * 3..i * 3 + 3]);
+        }
+    }
+
+    /// Mesh renderer builder with the position attribute, the normal and uv ones if the program
+    /// uses them, and a section per material, textured with its PNG diffuse image if any
+    pub fn renderer_builder(&self, program: ProgramSharedPointer) -> MeshRendererBuilder {
+        let (has_normal, has_uv, has_color) = {
+            let program = program.borrow();
+            (
+                program.has_attribute("normal"),
+                program.has_attribute("uv"),
+                program.has_attribute("color"),
+            )
+        };
+        let mut builder = MeshRenderer::builder()
+            .shader(program)
+            .add_buffer(self.positions.clone())
+            .add_attribute("position", 3, 0)
+            .indices(Indices::compact(self.indices.clone()));
+        let mut buffer_id = 1;
+        if has_normal {
+            builder = builder
+                .add_buffer(self.normals.clone())
+                .add_attribute("normal", 3, buffer_id);
+            buffer_id += 1;
+        }
+        if has_uv {
+            builder = builder
+                .add_buffer(self.uvs.clone())
+                .add_attribute("uv", 2, buffer_id);
+            buffer_id += 1;
+        }
+        if has_color {
+            // OBJ files have no vertex colors, white leaves the material's color unchanged
+            builder = builder
+                .add_buffer(vec![1.; self.vertex_count() * 4])
+                .add_attribute("color", 4, buffer_id);
+        }
+        // the materials sharing an image share its texture
+        let mut textures: HashMap<&Path, Option<TexturePointer>> = HashMap::new();
+        for section in self.sections.iter() {
+            let material = section
+                .material
+                .as_ref()
+                .and_then(|name| self.materials.get(name));
+            let uniforms = material
+                .map(Material::uniforms)
+                // the program's color would otherwise be left to the previously drawn mesh's
+                .unwrap_or_else(|| Material::new("default").uniforms());
+            let texture = material
+                .and_then(|m| m.diffuse_texture.as_deref())
+                .and_then(|path| {
+                    textures
+                        .entry(path)
+                        .or_insert_with(|| {
+                            // the mesh is still drawn with the material's color
+                            Texture::from_png(path, Sampling::default())
+                                .map_err(|e| log::warn!("ignoring texture: {}", e.chain()))
+                                .ok()
+                                .map(Rc::new)
+                        })
+                        .clone()
+                });
+            builder = builder.add_section(MeshSection {
+                start: section.start,
+                count: section.count,
+                uniforms,
+                texture,
+            });
+        }
+        builder
+    }
+}
+
+/// Area weighted average of the normals of the triangles around each vertex, 3 values per
+/// vertex, vertices outside of any triangle pointing up
+pub fn smooth_normals(positions: &[GLfloat], indices: &[u32]) -> Vec<GLfloat> {
+    let position = |i: u32| {
+        let i = i as usize * 3;
+        Vector3::new(positions[i], positions[i + 1], positions[i + 2])
+    };
+    let mut sums = vec![Vector3::new(0., 0., 0.); positions.len() / 3];
+    for triangle in indices.chunks_exact(3) {
+        let (a, b, c) = (
+            position(triangle[0]),
+            position(triangle[1]),
+            position(triangle[2]),
+        );
+        // the cross product's length is twice the triangle's area
+        let normal = (b - a).cross(c - a);
+        for i in triangle {
+            sums[*i as usize] += normal;
+        }
+    }
+    sums.into_iter()
+        .flat_map(|sum| {
+            let normal = sum.safe_normalize();
+            let normal: [GLfloat; 3] = if normal.magnitude2() > 0. {
+                normal.into()
+            } else {
+                Vector3::unit_z().into()
+            };
+            normal
+        })
+        .collect()
+}
+
+/// Resolves the `position/uv/normal` indices of a face corner, which are 1 based, or relative
+/// to the end of the lists if negative
+fn parse_corner(
+    line: &Line,
+    word: &str,
+    positions: &[[GLfloat; 3]],
+    uvs: &[[GLfloat; 2]],
+    normals: &[[GLfloat; 3]],
+) -> EngineResult<Corner> {
+    let resolve = |index: &str, count: usize, kind: &str| -> EngineResult<usize> {
+        let parsed: i64 = index
+            .parse()
+            .map_err(|_| line.error(format!("invalid {kind} index `{index}`")))?;
+        let resolved = match parsed {
+            1.. => parsed - 1,
+            ..=-1 => count as i64 + parsed,
+            0 => -1,
+        };
+        if resolved < 0 || resolved >= count as i64 {
+            return Err(line.error(format!(
+                "{kind} index {parsed} out of range, there are {count} {kind}s"
+            )));
+        }
+        Ok(resolved as usize)
+    };
+
+    let mut parts = word.split('/');
+    let position = resolve(
+        parts.next().unwrap_or_default(),
+        positions.len(),
+        "position",
+    )?;
+    let uv = match parts.next() {
+        Some("") | None => None,
+        Some(index) => Some(resolve(index, uvs.len(), "texture coordinate")?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(index) => Some(resolve(index, normals.len(), "normal")?),
+    };
+    if parts.next().is_some() {
+        return Err(line.error(format!("invalid face vertex `{word}`")));
+    }
+    Ok((position, uv, normal))
+}
+
+/// Path of a file referenced by another, relative to the latter's directory
+fn relative_to(path: &Path, file: &str) -> PathBuf {
+    path.parent().unwrap_or(Path::new("")).join(file)
+}
+
+/// Line of an OBJ or MTL file, split in whitespace separated words after its keyword
+struct Line<'a> {
+    path: &'a Path,
+    number: usize,
+    content: &'a str,
+    words: std::str::SplitWhitespace<'a>,
+}
+
+impl<'a> Line<'a> {
+    fn new(path: &'a Path, number: usize, line: &'a str) -> Self {
+        // comments run to the end of the line
+        let content = line.split('#').next().unwrap_or_default().trim();
+        Self {
+            path,
+            number,
+            content,
+            words: content.split_whitespace(),
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> EngineError {
+        EngineError::parse(self.path, self.number, message)
+    }
+
+    fn keyword(&mut self) -> Option<&'a str> {
+        self.words.next()
+    }
+
+    fn words(&self) -> std::str::SplitWhitespace<'a> {
+        self.words.clone()
+    }
+
+    /// Text after the keyword, e.g. a name which may contain spaces
+    fn rest(&self) -> EngineResult<&'a str> {
+        let rest = self
+            .content
+            .split_once(char::is_whitespace)
+            .map(|(_, rest)| rest.trim())
+            .unwrap_or_default();
+        if rest.is_empty() {
+            return Err(self.error("missing value"));
+        }
+        Ok(rest)
+    }
+
+    /// `N` numbers, any following ones being ignored
+    fn floats<const N: usize>(&mut self) -> EngineResult<[GLfloat; N]> {
+        let mut values = [0.; N];
+        for (i, value) in values.iter_mut().enumerate() {
+            let word = self
+                .words
+                .next()
+                .ok_or_else(|| self.error(format!("expected {N} numbers, got {i}")))?;
+            *value = word
+                .parse()
+                .map_err(|_| self.error(format!("invalid number `{word}`")))?;
+        }
+        Ok(values)
+    }
+
+    /// Up to `N` numbers, the missing ones taking the `default` value
+    fn floats_or<const N: usize>(&mut self, default: GLfloat) -> EngineResult<[GLfloat; N]> {
+        let mut values = [default; N];
+        for (i, value) in values.iter_mut().enumerate() {
+            let Some(word) = self.words.next() else {
+                if i == 0 {
+                    return Err(self.error("missing value"));
+                }
+                break;
+            };
+            *value = word
+                .parse()
+                .map_err(|_| self.error(format!("invalid number `{word}`")))?;
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `content` written to a file of the temporary directory
+    fn load(name: &str, content: &str) -> EngineResult<ObjMesh> {
+        let path = std::env::temp_dir().join(format!("obj_test_{}_{name}.obj", std::process::id()));
+        fs::write(&path, content).unwrap();
+        let mesh = ObjMesh::load(&path);
+        fs::remove_file(&path).unwrap();
+        mesh
+    }
+
+    fn error_line(result: EngineResult<ObjMesh>) -> usize {
+        match result {
+            Err(EngineError::Parse { line, .. }) => line,
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    #[test]
+    fn negative_indices_count_from_the_end() {
+        let mesh = load("negative", &format!("{SQUARE}f -4 -3 -2\n")).unwrap();
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert_eq!(mesh.positions, [0., 0., 0., 1., 0., 0., 1., 1., 0.]);
+    }
+
+    #[test]
+    fn index_zero_is_rejected() {
+        let result = load("zero", &format!("{SQUARE}f 0 1 2\n"));
+        assert_eq!(error_line(result), 5);
+    }
+
+    #[test]
+    fn out_of_range_index_is_rejected() {
+        let result = load("range", &format!("{SQUARE}f 1 2 5\n"));
+        assert_eq!(error_line(result), 5);
+    }
+
+    #[test]
+    fn corners_without_texture_coordinates_keep_their_normals() {
+        let mesh = load("normals", &format!("{SQUARE}vn 0 0 -1\nf 1//1 2//1 3//1\n")).unwrap();
+        assert_eq!(mesh.normals, [0., 0., -1., 0., 0., -1., 0., 0., -1.]);
+        assert_eq!(mesh.uvs, [0.; 6]);
+    }
+
+    #[test]
+    fn missing_normals_are_computed() {
+        let mesh = load("smooth", &format!("{SQUARE}f 1 2 3\n")).unwrap();
+        assert_eq!(mesh.normals, [0., 0., 1., 0., 0., 1., 0., 0., 1.]);
+    }
+
+    #[test]
+    fn polygons_are_split_in_fans() {
+        let pentagon = format!("{SQUARE}v -1 0.5 0\nf 1 2 3 4 5\n");
+        let mesh = load("fan", &pentagon).unwrap();
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3, 0, 3, 4]);
+        assert_eq!(mesh.vertex_count(), 5);
+    }
+
+    #[test]
+    fn shared_corners_are_merged() {
+        let mesh = load("shared", &format!("{SQUARE}f 1 2 3\nf 1 3 4\n")).unwrap();
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertex_count(), 4);
+    }
+
+    #[test]
+    fn errors_report_their_line() {
+        let content = "# comment\n\nv 0 0 0\nv 1 x 0\n";
+        assert_eq!(error_line(load("number", content)), 4);
+        assert_eq!(error_line(load("face", &format!("{SQUARE}f 1 2\n"))), 5);
+        assert_eq!(
+            error_line(load("corner", &format!("{SQUARE}f 1/1/1/1 2 3\n"))),
+            5
+        );
+    }
+
+    #[test]
+    fn files_without_faces_are_rejected() {
+        assert_eq!(error_line(load("empty", SQUARE)), 4);
+    }
+
+    #[test]
+    fn sections_follow_the_materials() {
+        let content = format!("{SQUARE}f 1 2 3\nusemtl red\nf 1 3 4\nf 1 2 4\n");
+        let mesh = load("sections", &content).unwrap();
+        let sections: Vec<_> = mesh
+            .sections
+            .iter()
+            .map(|s| (s.material.as_deref(), s.start, s.count))
+            .collect();
+        assert_eq!(sections, [(None, 0, 3), (Some("red"), 3, 6)]);
+    }
+}
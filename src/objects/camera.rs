@@ -1,19 +1,196 @@
-use std::f32::consts::{FRAC_PI_2, PI};
+use std::{
+    cell::RefCell,
+    f32::consts::{FRAC_PI_2, PI},
+    fmt::Display,
+    rc::Rc,
+    str::FromStr,
+};
 
 use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
 use gl::types::GLfloat;
+use serde::{Deserialize, Serialize};
 
-use crate::{extensions::SafeNormalize, input::InputState, traits::Updatable};
+use crate::{
+    bounds::Aabb,
+    extensions::SafeNormalize,
+    input::{bindings::Action, InputState},
+    projection::{Projection, ProjectionKind},
+    traits::Updatable,
+};
 
 const UP: Vector3<GLfloat> = Vector3::new(0., 0., 1.);
-const LOOK_SENSITIVITY: f32 = 0.005;
-const SPEED: f32 = 3.0;
+/// Speed multiplier factor of one mouse wheel notch
+const SPEED_STEP: f32 = 1.25;
+const MIN_SPEED_MULTIPLIER: f32 = 0.05;
+const MAX_SPEED_MULTIPLIER: f32 = 20.0;
+/// Distance to the target when starting to orbit without having framed anything
+const DEFAULT_ORBIT_DISTANCE: f32 = 5.0;
+const MIN_ORBIT_DISTANCE: f32 = 0.1;
+/// Distance factor of one mouse wheel notch
+const ZOOM_STEP: f32 = 1.1;
+/// Relative distance change per second of the zoom keys
+const ZOOM_SPEED: f32 = 1.5;
+/// Panning per pixel of mouse motion, relative to the distance to the target
+const PAN_SENSITIVITY: f32 = 0.002;
+/// Panning per second of the pan keys, relative to the distance to the target
+const PAN_SPEED: f32 = 1.0;
 
+/// How the camera reacts to the movement and look inputs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Looks around from its position and moves relatively to where it looks
+    #[default]
+    FreeFly,
+    /// Rotates around a target point, zooms towards it and pans it
+    Orbit,
+}
+
+impl Display for CameraMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FreeFly => write!(f, "free"),
+            Self::Orbit => write!(f, "orbit"),
+        }
+    }
+}
+
+impl FromStr for CameraMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "free" => Ok(Self::FreeFly),
+            "orbit" => Ok(Self::Orbit),
+            _ => Err(format!(
+                "unknown camera mode `{s}`, expected `free` or `orbit`"
+            )),
+        }
+    }
+}
+
+/// Tunable camera behaviour, editable at runtime
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraSettings {
+    /// Free-fly speed in units per second, before the multipliers
+    pub speed: f32,
+    /// Multiplier of the speed, changed with the mouse wheel in free-fly mode
+    pub speed_multiplier: f32,
+    /// Speed multiplier while the sprint input is held
+    pub sprint_multiplier: f32,
+    /// Speed multiplier while the slow input is held
+    pub slow_multiplier: f32,
+    /// Rate in 1/s at which the velocity reaches the one asked by the inputs, 0 for instantly
+    pub acceleration: f32,
+    /// Rate in 1/s at which the velocity decays without input, 0 for instantly
+    pub damping: f32,
+    /// Rotation in radians per pixel of mouse motion
+    pub mouse_sensitivity: f32,
+    /// Rotation speed in radians per second of a fully tilted look stick
+    pub stick_look_speed: f32,
+    /// Time in seconds over which the mouse motion is smoothed, 0 to disable
+    pub mouse_smoothing: f32,
+    /// Looks up when moving the mouse or the look stick down
+    pub invert_y: bool,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            speed: 3.0,
+            speed_multiplier: 1.0,
+            sprint_multiplier: 3.0,
+            slow_multiplier: 0.25,
+            acceleration: 10.0,
+            damping: 8.0,
+            mouse_sensitivity: 0.005 * PI * 2.,
+            stick_look_speed: PI,
+            mouse_smoothing: 0.0,
+            invert_y: false,
+        }
+    }
+}
+
+impl CameraSettings {
+    pub const NAMES: [&'static str; 10] = [
+        "speed",
+        "speed_multiplier",
+        "sprint_multiplier",
+        "slow_multiplier",
+        "acceleration",
+        "damping",
+        "mouse_sensitivity",
+        "stick_look_speed",
+        "mouse_smoothing",
+        "invert_y",
+    ];
+
+    fn float_mut(&mut self, name: &str) -> Option<&mut f32> {
+        Some(match name {
+            "speed" => &mut self.speed,
+            "speed_multiplier" => &mut self.speed_multiplier,
+            "sprint_multiplier" => &mut self.sprint_multiplier,
+            "slow_multiplier" => &mut self.slow_multiplier,
+            "acceleration" => &mut self.acceleration,
+            "damping" => &mut self.damping,
+            "mouse_sensitivity" => &mut self.mouse_sensitivity,
+            "stick_look_speed" => &mut self.stick_look_speed,
+            "mouse_smoothing" => &mut self.mouse_smoothing,
+            _ => return None,
+        })
+    }
+
+    /// Value of the named setting as text
+    pub fn get(&self, name: &str) -> Option<String> {
+        if name == "invert_y" {
+            return Some(self.invert_y.to_string());
+        }
+        let mut settings = *self;
+        settings.float_mut(name).map(|v| v.to_string())
+    }
+
+    /// Parses and sets the named setting, numbers can't be negative
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if name == "invert_y" {
+            self.invert_y = value.parse().map_err(|_| "expected `true` or `false`")?;
+            return Ok(());
+        }
+        let setting = self
+            .float_mut(name)
+            .ok_or_else(|| format!("unknown camera setting `{name}`"))?;
+        let value: f32 = value
+            .parse()
+            .map_err(|_| format!("invalid number `{value}`"))?;
+        *setting = value.max(0.);
+        Ok(())
+    }
+}
+
+/// Fraction of the remaining way covered in `delta_time` when converging at `rate` per second
+fn convergence(rate: f32, delta_time: f32) -> f32 {
+    if rate <= 0. {
+        1.
+    } else {
+        1. - (-rate * delta_time).exp()
+    }
+}
+
+pub type CameraPointer = Rc<RefCell<Camera>>;
+
+#[derive(Clone)]
 pub struct Camera {
     position: Point3<GLfloat>,
     pitch: GLfloat,
     yaw: GLfloat,
-    projection: Matrix4<GLfloat>,
+    projection: Projection,
+    mode: CameraMode,
+    /// Point orbited around, in front of the camera at `distance`
+    target: Point3<GLfloat>,
+    distance: GLfloat,
+    settings: CameraSettings,
+    velocity: Vector3<GLfloat>,
+    /// Smoothed mouse motion, in pixels per second
+    mouse_velocity: (f32, f32),
 }
 
 impl Camera {
@@ -21,13 +198,19 @@ impl Camera {
         position: Point3<GLfloat>,
         pitch: GLfloat,
         yaw: GLfloat,
-        projection: Matrix4<GLfloat>,
+        projection: Projection,
     ) -> Self {
         Camera {
             position,
             pitch,
             yaw,
             projection,
+            mode: CameraMode::default(),
+            target: position,
+            distance: DEFAULT_ORBIT_DISTANCE,
+            settings: Default::default(),
+            velocity: Vector3::new(0., 0., 0.),
+            mouse_velocity: (0., 0.),
         }
     }
 
@@ -48,6 +231,100 @@ impl Camera {
         &self.position
     }
 
+    /// Moves the camera, and the orbited target along with it
+    pub fn set_position(&mut self, position: Point3<GLfloat>) {
+        self.target += position - self.position;
+        self.position = position;
+    }
+
+    pub fn pitch(&self) -> GLfloat {
+        self.pitch
+    }
+
+    /// Sets the pitch, clamped to look at most straight up or down
+    pub fn set_pitch(&mut self, pitch: GLfloat) {
+        self.pitch = pitch.clamp(-FRAC_PI_2, FRAC_PI_2);
+        self.update_orbit_position();
+    }
+
+    pub fn yaw(&self) -> GLfloat {
+        self.yaw
+    }
+
+    pub fn set_yaw(&mut self, yaw: GLfloat) {
+        self.yaw = yaw % (PI * 2.);
+        self.update_orbit_position();
+    }
+
+    pub fn settings(&self) -> &CameraSettings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut CameraSettings {
+        &mut self.settings
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Switches mode without moving the view, the orbited target being the point in front of
+    /// the camera at the current orbit distance
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode == CameraMode::Orbit && self.mode != CameraMode::Orbit {
+            self.target = self.position + self.forward() * self.distance;
+            self.velocity = Vector3::new(0., 0., 0.);
+        }
+        self.mode = mode;
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.set_mode(match self.mode {
+            CameraMode::FreeFly => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::FreeFly,
+        });
+    }
+
+    pub fn target(&self) -> &Point3<GLfloat> {
+        &self.target
+    }
+
+    /// Sets the orbited target, the camera moving to keep looking at it from the same direction
+    pub fn set_target(&mut self, target: Point3<GLfloat>) {
+        self.target = target;
+        self.update_orbit_position();
+    }
+
+    /// Distance between the camera and the orbited target
+    pub fn distance(&self) -> GLfloat {
+        self.distance
+    }
+
+    pub fn set_distance(&mut self, distance: GLfloat) {
+        self.distance = distance.max(MIN_ORBIT_DISTANCE);
+        self.update_orbit_position();
+    }
+
+    /// Orbits around the center of the bounds, close enough for them to fill the view
+    pub fn frame(&mut self, bounds: &Aabb) {
+        self.mode = CameraMode::Orbit;
+        self.velocity = Vector3::new(0., 0., 0.);
+        self.target = bounds.center();
+        self.distance =
+            (bounds.radius() / (self.projection.fov / 2.).sin()).max(MIN_ORBIT_DISTANCE);
+        if self.projection.kind == ProjectionKind::Orthographic {
+            self.projection.ortho_height = bounds.radius() * 2.;
+        }
+        self.update_orbit_position();
+    }
+
+    /// Places the camera at `distance` behind the target, when orbiting
+    fn update_orbit_position(&mut self) {
+        if self.mode == CameraMode::Orbit {
+            self.position = self.target - self.forward() * self.distance;
+        }
+    }
+
     pub fn up_forward(&self) -> (Vector3<GLfloat>, Vector3<GLfloat>) {
         let forward = self.forward();
         let dot = forward.dot(UP);
@@ -66,52 +343,185 @@ impl Camera {
         Matrix4::look_to_rh(self.position, forward, up)
     }
 
-    pub fn projection(&self) -> &Matrix4<GLfloat> {
+    pub fn projection(&self) -> &Projection {
         &self.projection
     }
 
-    pub fn set_projection(&mut self, projection: Matrix4<GLfloat>) {
+    /// Replaces the projection parameters, keeping the aspect ratio of the view
+    pub fn set_projection(&mut self, projection: Projection) {
+        let aspect_ratio = self.projection.aspect_ratio;
         self.projection = projection;
+        self.projection.aspect_ratio = aspect_ratio;
+        self.projection.sanitize();
     }
 
-    pub fn move_relative(&mut self, movement: &Vector3<GLfloat>) -> &mut Self {
+    pub fn projection_matrix(&self) -> Matrix4<GLfloat> {
+        self.projection.matrix()
+    }
+
+    pub fn fov(&self) -> GLfloat {
+        self.projection.fov
+    }
+
+    /// Sets the vertical field of view in radians, used by perspective projections
+    pub fn set_fov(&mut self, fov: GLfloat) {
+        self.projection.fov = fov;
+        self.projection.sanitize();
+    }
+
+    /// Sets the width to height ratio of the view
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: GLfloat) {
+        self.projection.aspect_ratio = aspect_ratio;
+    }
+
+    /// Converts a (forward, right, up) vector relative to the camera's yaw to world space
+    fn relative_to_global(&self, relative: &Vector3<GLfloat>) -> Vector3<GLfloat> {
         let forward = self.planar_forward();
         let right = Vector3::new(
             (self.yaw - FRAC_PI_2).cos(),
             (self.yaw - FRAC_PI_2).sin(),
             0.,
         );
-        let global_movement = movement.x * forward + movement.y * right + movement.z * UP;
-        self.position += global_movement;
+        relative.x * forward + relative.y * right + relative.z * UP
+    }
+
+    pub fn move_relative(&mut self, movement: &Vector3<GLfloat>) -> &mut Self {
+        self.position += self.relative_to_global(movement);
         self
     }
+
+    /// Current free-fly velocity, in units per second
+    pub fn velocity(&self) -> &Vector3<GLfloat> {
+        &self.velocity
+    }
+
+    /// Mouse look motion of the frame in pixels, smoothed over time if enabled
+    fn mouse_look(&mut self, input: &InputState, delta_time: f32) -> (f32, f32) {
+        let motion = (input.motion(Action::LookX), input.motion(Action::LookY));
+        let smoothing = self.settings.mouse_smoothing;
+        if smoothing <= 0. || delta_time <= 0. {
+            self.mouse_velocity = (0., 0.);
+            return motion;
+        }
+        let factor = convergence(1. / smoothing, delta_time);
+        self.mouse_velocity.0 += (motion.0 / delta_time - self.mouse_velocity.0) * factor;
+        self.mouse_velocity.1 += (motion.1 / delta_time - self.mouse_velocity.1) * factor;
+        (
+            self.mouse_velocity.0 * delta_time,
+            self.mouse_velocity.1 * delta_time,
+        )
+    }
+
+    fn update_free_fly(&mut self, input: &InputState, delta_time: f32) {
+        let notches = input.motion(Action::Speed);
+        if notches != 0. {
+            self.settings.speed_multiplier = (self.settings.speed_multiplier
+                * SPEED_STEP.powf(notches))
+            .clamp(MIN_SPEED_MULTIPLIER, MAX_SPEED_MULTIPLIER);
+        }
+        let mut speed = self.settings.speed * self.settings.speed_multiplier;
+        if input.is_pressed(Action::Sprint) {
+            speed *= self.settings.sprint_multiplier;
+        }
+        if input.is_pressed(Action::Slow) {
+            speed *= self.settings.slow_multiplier;
+        }
+
+        let x_input = input.axis(Action::MoveForward, Action::MoveBackward);
+        let y_input = input.axis(Action::MoveRight, Action::MoveLeft);
+        let z_input = input.axis(Action::MoveUp, Action::MoveDown);
+        let mut move_direction = Vector3::new(x_input, y_input, z_input);
+        // analog inputs move slower when partially tilted, but diagonals are not faster
+        if move_direction.magnitude2() > 1. {
+            move_direction = move_direction.safe_normalize();
+        }
+
+        // accelerates towards the asked velocity, and slows down to a stop without input
+        let target_velocity = self.relative_to_global(&move_direction) * speed;
+        let rate = if move_direction.magnitude2() > 0. {
+            self.settings.acceleration
+        } else {
+            self.settings.damping
+        };
+        self.velocity += (target_velocity - self.velocity) * convergence(rate, delta_time);
+        self.position += self.velocity * delta_time;
+    }
+
+    /// Moves the orbited target in the view plane, `right` and `up` being relative to the
+    /// distance to the target
+    fn pan(&mut self, right: GLfloat, up: GLfloat) {
+        let (view_up, forward) = self.up_forward();
+        let view_right = forward.cross(view_up).safe_normalize();
+        let view_up = view_right.cross(forward);
+        self.target += (view_right * right + view_up * up) * self.distance;
+        self.update_orbit_position();
+    }
+
+    fn update_orbit(&mut self, input: &InputState, delta_time: f32) {
+        let mut pan = (
+            input.axis(Action::MoveRight, Action::MoveLeft) * PAN_SPEED * delta_time,
+            input.axis(Action::MoveUp, Action::MoveDown) * PAN_SPEED * delta_time,
+        );
+        // dragging pans the target instead of rotating around it
+        if input.is_pressed(Action::OrbitPan) {
+            pan.0 -= input.motion(Action::LookX) * PAN_SENSITIVITY;
+            pan.1 += input.motion(Action::LookY) * PAN_SENSITIVITY;
+        }
+        self.pan(pan.0, pan.1);
+
+        let zoom = input.motion(Action::Zoom)
+            + input.axis(Action::MoveForward, Action::MoveBackward) * ZOOM_SPEED * delta_time
+                / ZOOM_STEP.ln();
+        if zoom != 0. {
+            self.set_distance(self.distance / ZOOM_STEP.powf(zoom));
+            // the distance doesn't change the size of what is seen without perspective
+            if self.projection.kind == ProjectionKind::Orthographic {
+                self.projection.ortho_height /= ZOOM_STEP.powf(zoom);
+                self.projection.sanitize();
+            }
+        }
+    }
 }
 
 impl Updatable for Camera {
+    fn name(&self) -> &str {
+        "camera"
+    }
+
+    fn pausable(&self) -> bool {
+        false
+    }
+
     fn update(&mut self, delta_time: f32) {
         let input = unsafe { InputState::get() };
         if !input.focused {
             return;
         }
 
-        if input.capture_cursor {
-            // update pitch
-            let pitch_movement = -input.mouse_y_axis * LOOK_SENSITIVITY * PI * 2.;
-            self.pitch += pitch_movement;
-            self.pitch = self.pitch.clamp(-FRAC_PI_2, FRAC_PI_2);
-            // update yaw
-            let yaw_movement = -input.mouse_x_axis * LOOK_SENSITIVITY * PI * 2.;
-            self.yaw += yaw_movement;
-            self.yaw %= PI * 2.;
-        }
-
-        // update position
-        let x_input = input.forward as i32 - input.backward as i32;
-        let y_input = input.right as i32 - input.left as i32;
-        let z_input = input.up as i32 - input.down as i32;
-        let move_direction =
-            Vector3::new(x_input as f32, y_input as f32, z_input as f32).safe_normalize();
-        let movement = move_direction * SPEED * delta_time;
-        self.move_relative(&movement);
+        // analog sticks look at a constant rate, the mouse only while the cursor is captured
+        let y_sign = if self.settings.invert_y { -1. } else { 1. };
+        let stick_speed = self.settings.stick_look_speed * delta_time;
+        let mut pitch_movement = -input.value(Action::LookY) * y_sign * stick_speed;
+        let mut yaw_movement = -input.value(Action::LookX) * stick_speed;
+        let (mouse_x, mouse_y) = self.mouse_look(input, delta_time);
+        let panning = self.mode == CameraMode::Orbit && input.is_pressed(Action::OrbitPan);
+        if input.capture_cursor && !panning {
+            pitch_movement -= mouse_y * y_sign * self.settings.mouse_sensitivity;
+            yaw_movement -= mouse_x * self.settings.mouse_sensitivity;
+        }
+        // update pitch
+        self.pitch += pitch_movement;
+        self.pitch = self.pitch.clamp(-FRAC_PI_2, FRAC_PI_2);
+        // update yaw
+        self.yaw += yaw_movement;
+        self.yaw %= PI * 2.;
+
+        match self.mode {
+            CameraMode::FreeFly => self.update_free_fly(input, delta_time),
+            CameraMode::Orbit => {
+                self.update_orbit_position();
+                self.update_orbit(input, delta_time);
+            }
+        }
     }
 }
@@ -1,16 +1,22 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, mem::size_of, rc::Rc};
 
-use cgmath::Matrix4;
-use gl::types::{GLenum, GLfloat, GLint};
+use bytemuck::Pod;
+use cgmath::{Matrix4, Point3};
+use gl::types::{GLenum, GLfloat, GLint, GLuint};
 
 use crate::{
-    gl_checked,
+    bounds::Aabb,
+    definitions, gl_check, gl_checked,
     gl_types::{BufferIdType, VaoIdType},
-    program::uniform::Uniform,
-    traits::Drawable, engine::Engine,
+    program::uniform::{Uniform, UniformValue},
+    statistics::RenderStatistics,
+    texture::TexturePointer,
+    traits::Drawable,
+    vertex_layout::{ComponentType, Conversion, VertexAttribute, VertexLayout},
+    view::DrawContext,
 };
 
-use self::builder::MeshRendererBuilder;
+pub use self::builder::MeshRendererBuilder;
 use crate::program::ProgramSharedPointer;
 
 type BufferType = Vec<GLfloat>;
@@ -18,14 +24,114 @@ type DrawModeType = GLenum;
 type UniformType = Option<Rc<RefCell<Uniform>>>;
 pub type MeshRendererPointer = Rc<RefCell<MeshRenderer>>;
 
+/// Order in which a mesh's vertices are drawn, 16 bit indices halving the buffer's size
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    /// 16 bit indices if they can address all the vertices, 32 bit ones otherwise
+    pub fn compact(indices: Vec<u32>) -> Self {
+        if indices.iter().all(|i| *i <= u16::MAX as u32) {
+            Self::U16(indices.into_iter().map(|i| i as u16).collect())
+        } else {
+            Self::U32(indices)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::U16(indices) => indices.len(),
+            Self::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn max(&self) -> Option<usize> {
+        match self {
+            Self::U16(indices) => indices.iter().max().map(|i| *i as usize),
+            Self::U32(indices) => indices.iter().max().map(|i| *i as usize),
+        }
+    }
+
+    /// Size of an index in bytes
+    pub fn index_size(&self) -> usize {
+        match self {
+            Self::U16(_) => size_of::<u16>(),
+            Self::U32(_) => size_of::<u32>(),
+        }
+    }
+
+    pub fn gl_type(&self) -> GLenum {
+        match self {
+            Self::U16(_) => gl::UNSIGNED_SHORT,
+            Self::U32(_) => gl::UNSIGNED_INT,
+        }
+    }
+
+    fn as_ptr(&self) -> *const std::ffi::c_void {
+        match self {
+            Self::U16(indices) => indices.as_ptr() as _,
+            Self::U32(indices) => indices.as_ptr() as _,
+        }
+    }
+}
+
+impl From<Vec<u16>> for Indices {
+    fn from(indices: Vec<u16>) -> Self {
+        Self::U16(indices)
+    }
+}
+
+impl From<Vec<u32>> for Indices {
+    fn from(indices: Vec<u32>) -> Self {
+        Self::U32(indices)
+    }
+}
+
+/// Range of a mesh's indices, or vertices if it has none, drawn with its own uniform values
+#[derive(Clone, Debug)]
+pub struct MeshSection {
+    pub start: usize,
+    pub count: usize,
+    /// Values set on the program's uniforms of the same name before drawing the section
+    pub uniforms: Vec<(String, UniformValue)>,
+    /// Texture bound to unit 0 while drawing the section
+    pub texture: Option<TexturePointer>,
+}
+
 pub struct MeshRenderer {
+    name: String,
     shader: ProgramSharedPointer,
     vao_id: VaoIdType,
     draw_mode: DrawModeType,
     vertex_count: usize,
     buffer_ids: Vec<BufferIdType>,
+    index_buffer_id: Option<BufferIdType>,
+    index_count: usize,
+    /// `gl::UNSIGNED_SHORT` or `gl::UNSIGNED_INT`
+    index_type: GLenum,
+    /// Number of copies drawn with instanced draw calls, `None` for a single regular draw
+    instance_count: Option<usize>,
+    /// Parts drawn separately, the whole mesh being drawn at once if there are none
+    sections: Vec<MeshSection>,
     transform: Matrix4<GLfloat>,
     transform_uniform: UniformType,
+    use_texture_uniform: UniformType,
+    receive_shadows_uniform: UniformType,
+    /// Bounds of the vertices' `position` attribute, or those given to the builder, before the
+    /// transform
+    local_bounds: Option<Aabb>,
+    casts_shadows: bool,
+    receives_shadows: bool,
+    /// Whether the depth program can read the vertex attributes, the position being at its
+    /// location and any instance offset too
+    depth_compatible: bool,
 }
 
 impl MeshRenderer {
@@ -35,33 +141,71 @@ impl MeshRenderer {
 }
 
 mod builder {
-    use std::mem::size_of;
+    use std::{collections::HashSet, ffi::CString};
 
     use cgmath::{Matrix4, SquareMatrix};
 
-    use crate::{definitions, gl_check, gl_checked, gl_types::DrawMode, program::uniform};
+    use crate::{
+        definitions,
+        error::{EngineError, EngineResult},
+        gl_check, gl_checked, gl_debug,
+        gl_types::DrawMode,
+        program::uniform,
+    };
+
+    const DEFAULT_NAME: &str = "mesh_renderer";
 
     use super::*;
 
-    type BufferCollectionType = Vec<BufferType>;
-    type AttributeConfigType = (String, GLint);
-    type AttributeConfigCollection = HashMap<usize, Vec<AttributeConfigType>>;
+    /// Raw bytes of the vertex buffers
+    type BufferCollectionType = Vec<Vec<u8>>;
+    type LayoutCollection = HashMap<usize, VertexLayout>;
     #[derive(Default)]
     pub struct MeshRendererBuilder {
+        name: Option<String>,
         buffers: BufferCollectionType,
         shader: Option<ProgramSharedPointer>,
         draw_mode: Option<DrawMode>,
-        attribute_config: AttributeConfigCollection,
+        layouts: LayoutCollection,
         transform: Option<Matrix4<GLfloat>>,
+        indices: Option<Indices>,
+        sections: Vec<MeshSection>,
+        /// Attribute divisors of the per instance buffers, by buffer id
+        divisors: HashMap<usize, GLuint>,
+        instance_count: Option<usize>,
+        bounds: Option<Aabb>,
+        casts_shadows: Option<bool>,
+        receives_shadows: Option<bool>,
+        /// Ids of the buffers added with [`Self::add_vertex_buffer`], whose layout is complete
+        vertex_buffers: HashSet<usize>,
+        /// Attributes added to the buffers of `vertex_buffers`, reported when building
+        rejected_attributes: Vec<(String, usize)>,
     }
 
     impl MeshRendererBuilder {
-        /// Adds a buffer
+        /// Name of the renderer, used to label its OpenGL objects
+        pub fn name(mut self, name: impl Into<String>) -> Self {
+            self.name = Some(name.into());
+            self
+        }
+
+        /// Adds a buffer of floats, whose attributes are added with [`Self::add_attribute`]
         ///
         /// The buffer will be given an internal ID starting at 0 and counting up with each buffer added,
         /// so first buffer has ID 0, second had ID 1, etc.
         pub fn add_buffer(mut self, buffer: BufferType) -> Self {
-            self.buffers.push(buffer);
+            self.buffers.push(bytemuck::cast_slice(&buffer).to_vec());
+            self
+        }
+
+        /// Adds a buffer of vertices of any plain data type, described by `layout`
+        ///
+        /// The buffer gets the next ID like those of [`Self::add_buffer`], more attributes can't
+        /// be added to it with [`Self::add_attribute`], building then fails
+        pub fn add_vertex_buffer<T: Pod>(mut self, vertices: &[T], layout: VertexLayout) -> Self {
+            self.layouts.insert(self.buffers.len(), layout);
+            self.vertex_buffers.insert(self.buffers.len());
+            self.buffers.push(bytemuck::cast_slice(vertices).to_vec());
             self
         }
 
@@ -77,7 +221,7 @@ mod builder {
             self
         }
 
-        /// Add an attribute to builder
+        /// Add a float attribute to builder, following the previous attributes of its buffer
         ///
         /// `name` refers to the attribute name in the shader code\
         /// `size` is the size in amount of values per attribute (vec3 has 3 fields, so size is 3)\
@@ -88,14 +232,12 @@ mod builder {
             size: GLint,
             buffer_id: usize,
         ) -> Self {
-            if !self.attribute_config.contains_key(&buffer_id) {
-                self.attribute_config.insert(buffer_id, Vec::new());
+            if self.vertex_buffers.contains(&buffer_id) {
+                self.rejected_attributes.push((name.into(), buffer_id));
+                return self;
             }
-
-            self.attribute_config
-                .get_mut(&buffer_id)
-                .unwrap()
-                .push((name.into(), size));
+            let layout = self.layouts.entry(buffer_id).or_default();
+            *layout = std::mem::take(layout).attribute(VertexAttribute::float(name, size));
             self
         }
 
@@ -104,9 +246,110 @@ mod builder {
             self
         }
 
-        pub fn build(self) -> MeshRendererPointer {
-            self.assert_integrity();
+        /// Whether the mesh is rendered into the shadow maps, `true` by default
+        pub fn casts_shadows(mut self, casts: bool) -> Self {
+            self.casts_shadows = Some(casts);
+            self
+        }
+
+        /// Whether shadows darken the mesh, if its program supports it, `true` by default
+        pub fn receives_shadows(mut self, receives: bool) -> Self {
+            self.receives_shadows = Some(receives);
+            self
+        }
+
+        /// Draws the vertices in the order of `indices` instead of the buffers' order
+        pub fn indices(mut self, indices: impl Into<Indices>) -> Self {
+            self.indices = Some(indices.into());
+            self
+        }
+
+        /// Makes the attributes of a buffer advance once every `divisor` instances instead of
+        /// once per vertex, the mesh then being drawn instanced
+        pub fn instance_divisor(mut self, buffer_id: usize, divisor: GLuint) -> Self {
+            self.divisors.insert(buffer_id, divisor);
+            self
+        }
+
+        /// Number of instances drawn, by default as many as the per instance buffers hold
+        pub fn instance_count(mut self, count: usize) -> Self {
+            self.instance_count = Some(count);
+            self
+        }
 
+        /// Bounds of the mesh before its transform, computed from the `position` attribute by
+        /// default, instanced meshes having none unless given
+        pub fn bounds(mut self, bounds: Aabb) -> Self {
+            self.bounds = Some(bounds);
+            self
+        }
+
+        /// Adds a part of the mesh drawn with its own uniform values
+        pub fn add_section(mut self, section: MeshSection) -> Self {
+            self.sections.push(section);
+            self
+        }
+
+        /// Number of bytes of each vertex or instance of a buffer
+        fn stride(&self, buffer_id: usize) -> usize {
+            self.layouts
+                .get(&buffer_id)
+                .map_or(0, VertexLayout::vertex_size)
+        }
+
+        /// Number of vertices of the first per vertex buffer
+        fn vertex_count(&self) -> Option<usize> {
+            let buffer_id = (0..self.buffers.len()).find(|i| !self.divisors.contains_key(i))?;
+            Some(self.buffers[buffer_id].len() / self.stride(buffer_id).max(1))
+        }
+
+        /// Number of instances given, or that the per instance buffers have enough values for
+        fn instance_count_or_default(&self) -> Option<usize> {
+            self.instance_count.or_else(|| {
+                self.divisors
+                    .iter()
+                    .map(|(buffer_id, divisor)| self.instances_available(*buffer_id, *divisor))
+                    .min()
+            })
+        }
+
+        /// Number of instances a per instance buffer has values for
+        fn instances_available(&self, buffer_id: usize, divisor: GLuint) -> usize {
+            let values = self
+                .buffers
+                .get(buffer_id)
+                .map_or(0, |buffer| buffer.len() / self.stride(buffer_id).max(1));
+            values * divisor.max(1) as usize
+        }
+
+        /// Bounds of the `position` attribute's values, if there is a 3 component per vertex one
+        fn position_bounds(&self) -> Option<Aabb> {
+            if self.instance_count_or_default().is_some() {
+                return None;
+            }
+            self.layouts.iter().find_map(|(buffer_id, layout)| {
+                let (attribute, offset) = layout.attribute_named("position")?;
+                let is_float_vec3 = attribute.components == 3
+                    && attribute.component_type == ComponentType::F32
+                    && attribute.conversion == Conversion::Float;
+                if !is_float_vec3 {
+                    return None;
+                }
+                let positions = self.buffers[*buffer_id]
+                    .chunks_exact(layout.vertex_size())
+                    .map(|v| bytemuck::pod_read_unaligned::<[GLfloat; 3]>(&v[offset..offset + 12]))
+                    .map(Point3::from);
+                Aabb::from_points(positions)
+            })
+        }
+
+        pub fn build(self) -> EngineResult<MeshRendererPointer> {
+            self.check_integrity()?;
+
+            let name = self.name.clone().unwrap_or_else(|| DEFAULT_NAME.to_owned());
+            let local_bounds = self.bounds.or_else(|| self.position_bounds());
+            let vertex_count = self.vertex_count().unwrap_or_default();
+            let instance_count = self.instance_count_or_default();
             let mut vao_id: VaoIdType = 0;
             let program = self.shader.as_ref().unwrap();
             let program = program.as_ref().borrow();
@@ -118,6 +361,7 @@ mod builder {
                     gl::BindVertexArray(vao_id);
                 };
             }
+            gl_debug::label_object(gl::VERTEX_ARRAY, vao_id, &format!("{name} vao"));
 
             let mut buffer_ids: Vec<BufferIdType> = vec![0; self.buffers.len()];
 
@@ -126,7 +370,8 @@ mod builder {
                 gl_check!();
             }
 
-            let mut strides: Vec<usize> = vec![0; buffer_ids.len()];
+            let mut has_depth_position = false;
+            let mut depth_compatible = true;
             // for each buffer
             for i in 0..buffer_ids.len() {
                 unsafe {
@@ -134,50 +379,107 @@ mod builder {
                         gl::BindBuffer(gl::ARRAY_BUFFER, buffer_ids[i]);
                         gl::NamedBufferData( // Load buffer data into OpenGL buffer
                             buffer_ids[i],
-                            (self.buffers[i].len() * size_of::<GLfloat>()) as _,
+                            self.buffers[i].len() as _,
                             self.buffers[i].as_ptr() as _,
                             gl::STATIC_DRAW,
                         );
                     };
                 }
+                gl_debug::label_object(gl::BUFFER, buffer_ids[i], &format!("{name} buffer {i}"));
 
-                // compute actual stride of each vertex by summing the individual attributes' sizes
-                let mut stride = 0usize;
-                for (_, size) in self.attribute_config.get(&i).unwrap().iter() {
-                    stride += (*size) as usize;
-                }
-
-                strides[i] = stride;
+                let layout = self.layouts.get(&i).unwrap();
+                let stride = layout.vertex_size();
+                let divisor = self.divisors.get(&i).copied().unwrap_or_default();
 
                 // configure attributes
-                let offset = 0usize;
-                for (name, size) in self.attribute_config.get(&i).unwrap().iter() {
-                    let location = unsafe { gl::GetAttribLocation(prog_id, name.as_ptr() as _) };
-                    gl_check!();
+                for (attribute, offset) in layout.attributes() {
+                    let location = match attribute.location {
+                        Some(location) => location as GLint,
+                        None => {
+                            let c_name =
+                                CString::new(attribute.name.as_bytes()).unwrap_or_default();
+                            let location =
+                                unsafe { gl::GetAttribLocation(prog_id, c_name.as_ptr()) };
+                            gl_check!();
+                            location
+                        }
+                    };
                     if location == -1 {
-                        log::error!(
-                            "attribute `{name}` could not be found in program `{program_name}`",
-                            program_name = program.name()
-                        );
-                        continue;
+                        unsafe {
+                            gl_checked! {
+                                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+                                gl::BindVertexArray(0);
+                                gl::DeleteBuffers(buffer_ids.len() as _, buffer_ids.as_ptr());
+                                gl::DeleteVertexArrays(1, &vao_id);
+                            };
+                        }
+                        return Err(EngineError::MissingAttribute {
+                            program: program.name().clone(),
+                            attribute: attribute.name.clone(),
+                        });
+                    }
+
+                    match location {
+                        definitions::DEPTH_POSITION_LOCATION => {
+                            has_depth_position = attribute.name == "position"
+                        }
+                        definitions::DEPTH_INSTANCE_OFFSET_LOCATION => {
+                            depth_compatible &= attribute.name == "instance_offset"
+                        }
+                        _ => {}
                     }
 
+                    let component_type = attribute.component_type.gl_type();
                     unsafe {
-                        gl_checked! {
-                            gl::VertexAttribPointer(
+                        match attribute.conversion {
+                            // integer inputs would read garbage from float conversions
+                            Conversion::Integer => gl::VertexAttribIPointer(
                                 location as _,
-                                *size,
-                                gl::FLOAT,
-                                gl::FALSE,
-                                (stride * size_of::<GLfloat>()) as _,
+                                attribute.components,
+                                component_type,
+                                stride as _,
                                 offset as _,
-                            );
+                            ),
+                            conversion => gl::VertexAttribPointer(
+                                location as _,
+                                attribute.components,
+                                component_type,
+                                if conversion == Conversion::Normalized {
+                                    gl::TRUE
+                                } else {
+                                    gl::FALSE
+                                },
+                                stride as _,
+                                offset as _,
+                            ),
+                        }
+                        gl_checked! {
+                            gl::VertexAttribDivisor(location as _, divisor);
                             gl::EnableVertexArrayAttrib(vao_id, location as _);
                         };
                     }
                 }
             }
 
+            // the element buffer binding is part of the vao's state
+            let index_buffer_id = self.indices.as_ref().map(|indices| {
+                let mut index_buffer_id: BufferIdType = 0;
+                unsafe {
+                    gl_checked! {
+                        gl::GenBuffers(1, &mut index_buffer_id);
+                        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer_id);
+                        gl::NamedBufferData(
+                            index_buffer_id,
+                            (indices.len() * indices.index_size()) as _,
+                            indices.as_ptr(),
+                            gl::STATIC_DRAW,
+                        );
+                    };
+                }
+                gl_debug::label_object(gl::BUFFER, index_buffer_id, &format!("{name} indices"));
+                index_buffer_id
+            });
+
             unsafe {
                 gl_checked! {
                     gl::BindBuffer(gl::ARRAY_BUFFER, 0);
@@ -185,28 +487,45 @@ mod builder {
                 };
             }
 
-            Rc::new(RefCell::new(MeshRenderer {
+            Ok(Rc::new(RefCell::new(MeshRenderer {
+                name,
                 shader: self.shader.as_ref().unwrap().clone(),
                 vao_id,
                 draw_mode: self.draw_mode.unwrap_or_default().gl_constant(),
-                vertex_count: self.buffers[0].len() / strides[0],
+                vertex_count,
                 buffer_ids,
+                index_buffer_id,
+                index_count: self.indices.as_ref().map_or(0, Indices::len),
+                index_type: self
+                    .indices
+                    .as_ref()
+                    .map_or(gl::UNSIGNED_INT, Indices::gl_type),
+                instance_count,
+                sections: self.sections,
                 transform: self.transform.unwrap_or_else(Matrix4::identity),
                 transform_uniform: program
                     .uniform(definitions::MODEL_TRANSFORM_UNIFORM_NAME)
                     .map(|p| p.clone()),
-            }))
+                use_texture_uniform: program
+                    .uniform(definitions::USE_TEXTURE_UNIFORM_NAME)
+                    .cloned(),
+                receive_shadows_uniform: program
+                    .uniform(definitions::RECEIVE_SHADOWS_UNIFORM_NAME)
+                    .cloned(),
+                local_bounds,
+                casts_shadows: self.casts_shadows.unwrap_or(true),
+                receives_shadows: self.receives_shadows.unwrap_or(true),
+                depth_compatible: depth_compatible && has_depth_position,
+            })))
         }
 
-        fn assert_integrity(&self) {
-            let mut error = false;
+        fn check_integrity(&self) -> EngineResult<()> {
+            let mut problems = Vec::new();
             if self.buffers.is_empty() {
-                log::error!("mesh renderer builder has no vertex buffers");
-                error = true;
+                problems.push("mesh renderer builder has no vertex buffers".to_owned());
             }
             if self.shader.is_none() {
-                log::error!("mesh renderer builder has no program");
-                error = true;
+                problems.push("mesh renderer builder has no program".to_owned());
             } else {
                 let program = self.shader.as_ref().unwrap().as_ref().borrow();
                 let transform_uniform = program.uniform(definitions::MODEL_TRANSFORM_UNIFORM_NAME);
@@ -224,24 +543,78 @@ mod builder {
                     }
                 }
             }
-            if self.attribute_config.is_empty() {
-                log::error!("No attribute has been specified in mesh renderer builder.");
-                error = true;
+            if self.layouts.values().all(VertexLayout::is_empty) {
+                problems.push("no attribute has been specified".to_owned());
             }
 
-            let mut missing_attribute_configs = false;
             for i in 0..self.buffers.len() {
-                if !self.attribute_config.contains_key(&i) {
-                    missing_attribute_configs = true;
-                    log::debug!("missing attribute config for buffer {i} in mesh renderer builder");
+                match self.layouts.get(&i) {
+                    Some(layout) if !layout.is_empty() => problems.extend(
+                        layout
+                            .problems()
+                            .into_iter()
+                            .map(|problem| format!("buffer {i}: {problem}")),
+                    ),
+                    _ => problems.push(format!("buffer {i} is declared but has no attribute")),
+                }
+            }
+            for (name, buffer_id) in self.rejected_attributes.iter() {
+                problems.push(format!(
+                    "attribute `{name}` added to buffer {buffer_id}, whose vertex layout is given"
+                ));
+            }
+
+            for buffer_id in self.divisors.keys() {
+                if *buffer_id >= self.buffers.len() {
+                    problems.push(format!(
+                        "instance divisor set on missing buffer {buffer_id}"
+                    ));
                 }
             }
-            if missing_attribute_configs {
-                log::error!("Some buffers are declared in mesh renderer builder, but no attribute have been defined for them (see debug logs)");
-                error = true;
+            let vertex_count = self.vertex_count();
+            if vertex_count.is_none() && !self.buffers.is_empty() {
+                problems.push("all the buffers are per instance".to_owned());
+            }
+            if let (Some(indices), Some(vertex_count)) = (self.indices.as_ref(), vertex_count) {
+                if let Some(index) = indices.max().filter(|i| *i >= vertex_count) {
+                    problems.push(format!(
+                        "index {index} out of range, there are {vertex_count} vertices"
+                    ));
+                }
             }
-            if error {
-                panic!("Errors have occured while building mesh renderer, check logs");
+            if let Some(count) = self.instance_count {
+                for (buffer_id, divisor) in self.divisors.iter() {
+                    let available = self.instances_available(*buffer_id, *divisor);
+                    if available < count {
+                        problems.push(format!(
+                            "buffer {buffer_id} has values for {available} instances, {count} are drawn"
+                        ));
+                    }
+                }
+            }
+            let element_count = self
+                .indices
+                .as_ref()
+                .map(Indices::len)
+                .or(vertex_count)
+                .unwrap_or_default();
+            for section in self.sections.iter() {
+                if section.start + section.count > element_count {
+                    problems.push(format!(
+                        "section {}..{} out of range, there are {element_count} elements",
+                        section.start,
+                        section.start + section.count
+                    ));
+                }
+            }
+
+            if problems.is_empty() {
+                Ok(())
+            } else {
+                Err(EngineError::InvalidBuilderConfig {
+                    builder: "mesh renderer builder",
+                    problems,
+                })
             }
         }
     }
@@ -254,12 +627,141 @@ impl Drop for MeshRenderer {
                 gl::DeleteBuffers(self.buffer_ids.len() as _, self.buffer_ids.as_ptr());
                 gl::DeleteVertexArrays(1, &self.vao_id);
             };
+            if let Some(index_buffer_id) = self.index_buffer_id.as_ref() {
+                gl::DeleteBuffers(1, index_buffer_id);
+                gl_check!();
+            }
+        }
+    }
+}
+
+impl MeshRenderer {
+    /// Draws `count` elements from `start`, indexed if the mesh has indices and instanced if it
+    /// has instances
+    fn draw_range(&self, start: usize, count: usize) {
+        let index_size = match self.index_type {
+            gl::UNSIGNED_SHORT => size_of::<u16>(),
+            _ => size_of::<u32>(),
+        };
+        unsafe {
+            match (self.index_buffer_id.is_some(), self.instance_count) {
+                (true, None) => gl::DrawElements(
+                    self.draw_mode,
+                    count as _,
+                    self.index_type,
+                    (start * index_size) as _,
+                ),
+                (true, Some(instances)) => gl::DrawElementsInstanced(
+                    self.draw_mode,
+                    count as _,
+                    self.index_type,
+                    (start * index_size) as _,
+                    instances as _,
+                ),
+                (false, None) => gl::DrawArrays(self.draw_mode, start as _, count as _),
+                (false, Some(instances)) => {
+                    gl::DrawArraysInstanced(self.draw_mode, start as _, count as _, instances as _)
+                }
+            }
+            gl_check!();
+        }
+    }
+
+    /// Number of copies drawn, 1 if the mesh isn't instanced
+    pub fn instance_count(&self) -> usize {
+        self.instance_count.unwrap_or(1)
+    }
+
+    /// Binds the texture of a section, telling the program whether there is one
+    fn bind_texture(&self, section: Option<&MeshSection>) {
+        let texture = section.and_then(|s| s.texture.as_ref());
+        if let Some(uniform) = self.use_texture_uniform.as_ref() {
+            uniform.borrow_mut().set_int(texture.is_some() as _);
+        }
+        if let Some(texture) = texture {
+            texture.bind(0);
         }
     }
 }
 
 impl Drawable for MeshRenderer {
-    fn draw(&self, _engine: &Engine) {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.local_bounds.map(|b| b.transformed(&self.transform))
+    }
+
+    fn collect_statistics(&self, statistics: &mut RenderStatistics) {
+        statistics.drawables += 1;
+        statistics.draw_calls += self.sections.len().max(1);
+    }
+
+    fn casts_shadows(&self) -> bool {
+        self.casts_shadows
+    }
+
+    fn set_casts_shadows(&mut self, casts: bool) -> Result<(), String> {
+        self.casts_shadows = casts;
+        Ok(())
+    }
+
+    fn receives_shadows(&self) -> bool {
+        self.receives_shadows && self.receive_shadows_uniform.is_some()
+    }
+
+    fn set_receives_shadows(&mut self, receives: bool) -> Result<(), String> {
+        if self.receive_shadows_uniform.is_none() {
+            return Err(format!(
+                "the program of `{}` doesn't receive shadows",
+                self.name
+            ));
+        }
+        self.receives_shadows = receives;
+        Ok(())
+    }
+
+    /// Draws the positions with the depth program when the attributes allow it, without the
+    /// sections' uniforms and textures
+    fn draw_depth(&self, context: &DrawContext) {
+        let depth_program = context
+            .engine
+            .programs()
+            .get(definitions::DEPTH_PROGRAM_NAME)
+            .filter(|_| self.depth_compatible);
+        let Some(program) = depth_program else {
+            return self.draw(context);
+        };
+        let program = program.borrow();
+        program.bind();
+        if let Some(uniform) = program.uniform(definitions::MODEL_TRANSFORM_UNIFORM_NAME) {
+            uniform.borrow_mut().set_mat4(&self.transform);
+        }
+
+        unsafe {
+            gl::BindVertexArray(self.vao_id);
+            gl_check!();
+        }
+        if self.sections.is_empty() {
+            let count = if self.index_buffer_id.is_some() {
+                self.index_count
+            } else {
+                self.vertex_count
+            };
+            self.draw_range(0, count);
+        } else {
+            for section in self.sections.iter() {
+                self.draw_range(section.start, section.count);
+            }
+        }
+        unsafe {
+            gl::BindVertexArray(0);
+            gl_check!();
+        }
+    }
+
+    fn draw(&self, _context: &DrawContext) {
         self.shader.as_ref().borrow().bind();
         if let Some(transform_uniform) = self.transform_uniform.as_ref() {
             transform_uniform
@@ -267,13 +769,40 @@ impl Drawable for MeshRenderer {
                 .borrow_mut()
                 .set_mat4(&self.transform);
         }
+        if let Some(uniform) = self.receive_shadows_uniform.as_ref() {
+            uniform.borrow_mut().set_int(self.receives_shadows as _);
+        }
 
         unsafe {
-            gl_checked! {
-                gl::BindVertexArray(self.vao_id);
-                gl::DrawArrays(self.draw_mode, 0, self.vertex_count as _);
-                gl::BindVertexArray(0);
+            gl::BindVertexArray(self.vao_id);
+            gl_check!();
+        }
+        if self.sections.is_empty() {
+            let count = if self.index_buffer_id.is_some() {
+                self.index_count
+            } else {
+                self.vertex_count
             };
+            self.bind_texture(None);
+            self.draw_range(0, count);
+        } else {
+            let program = self.shader.borrow();
+            for section in self.sections.iter() {
+                for (name, value) in section.uniforms.iter() {
+                    if let Some(uniform) = program.uniform(name.as_str()) {
+                        let mut uniform = uniform.borrow_mut();
+                        if uniform.accepts(value) {
+                            uniform.set(value);
+                        }
+                    }
+                }
+                self.bind_texture(Some(section));
+                self.draw_range(section.start, section.count);
+            }
+        }
+        unsafe {
+            gl::BindVertexArray(0);
+            gl_check!();
         }
     }
 }
@@ -1,6 +1,10 @@
 pub mod camera;
 pub mod mesh_renderer;
+pub mod particle_light;
 pub mod particle_system;
+pub mod text_renderer;
 pub use camera::*;
 pub use mesh_renderer::*;
+pub use particle_light::*;
 pub use particle_system::*;
+pub use text_renderer::*;
@@ -0,0 +1,258 @@
+use std::mem::size_of;
+
+use cgmath::{Point3, Vector3, Vector4};
+use gl::types::{GLfloat, GLint, GLuint};
+
+use crate::{
+    extensions::CeilDiv,
+    gl_check, gl_checked, gl_debug,
+    gl_types::BufferIdType,
+    lights::{Light, LightKind},
+    program::{uniform::UniformValue, ProgramSharedPointer},
+};
+
+/// Binding of the buffer the light reduction programs write their sums to, the particles
+/// being at the particle system's binding
+pub const PARTICLE_LIGHT_BINDING: GLuint = 2;
+
+/// Where the light of a particle system is
+#[derive(Clone)]
+pub enum ParticleLightSource {
+    /// A fixed point, e.g. the center of the emitter
+    Emitter(Point3<GLfloat>),
+    /// The weighted center of the particles, summed by a compute program writing, for each
+    /// work group, the sum of the weighted positions and of the weights as a `vec4`
+    Particles(ProgramSharedPointer),
+}
+
+/// Point light driven by a particle system
+#[derive(Clone)]
+pub struct ParticleLightSettings {
+    /// Name of the light in the engine's light list
+    pub name: String,
+    pub source: ParticleLightSource,
+    /// `vec4` uniform of the display program giving the light's color
+    pub color_uniform: String,
+    /// Uniforms of the display program copied to the source program before each reduction, so
+    /// that the weights follow their edits
+    pub shared_uniforms: Vec<String>,
+    /// Intensity when the particles are fully active
+    pub intensity: GLfloat,
+    pub range: GLfloat,
+    /// Mean weight of the particles at which the intensity is reached, unused with an emitter
+    pub full_activity: GLfloat,
+    /// Relative amplitude of the intensity's flicker, from 0 to 1
+    pub flicker: GLfloat,
+}
+
+impl ParticleLightSettings {
+    pub fn new(name: impl Into<String>, source: ParticleLightSource) -> Self {
+        Self {
+            name: name.into(),
+            source,
+            color_uniform: "fire_color".to_owned(),
+            shared_uniforms: vec!["max_fire_lifetime".to_owned()],
+            intensity: 4.,
+            range: 8.,
+            full_activity: 1.,
+            flicker: 0.3,
+        }
+    }
+}
+
+/// State of the light of a particle system, updated along its simulation
+pub struct ParticleLight {
+    settings: ParticleLightSettings,
+    enabled: bool,
+    /// Sum buffers written on alternate frames, each read back a frame after being written
+    buffers: [BufferIdType; 2],
+    group_size: usize,
+    frame: usize,
+    /// Simulated time, flickering stops while the particle system is paused
+    time: GLfloat,
+    position: Point3<GLfloat>,
+    /// Mean weight of the particles relative to the full activity
+    activity: GLfloat,
+    color: Vector3<GLfloat>,
+}
+
+impl ParticleLight {
+    pub fn new(settings: ParticleLightSettings, particle_count: usize) -> Self {
+        let mut light = Self {
+            enabled: true,
+            buffers: [0; 2],
+            group_size: 1,
+            frame: 0,
+            time: 0.,
+            position: Point3::new(0., 0., 0.),
+            activity: 1.,
+            color: Vector3::new(1., 1., 1.),
+            settings,
+        };
+        match &light.settings.source {
+            ParticleLightSource::Emitter(position) => light.position = *position,
+            ParticleLightSource::Particles(program) => {
+                let mut group_size: [GLint; 3] = [1; 3];
+                unsafe {
+                    gl::GetProgramiv(
+                        program.borrow().id(),
+                        gl::COMPUTE_WORK_GROUP_SIZE,
+                        group_size.as_mut_ptr(),
+                    );
+                    gl_check!();
+                }
+                light.group_size = group_size[0].max(1) as usize;
+                let size = particle_count.ceil_div(light.group_size) * size_of::<[GLfloat; 4]>();
+                let name = light.settings.name.clone();
+                for (i, buffer) in light.buffers.iter_mut().enumerate() {
+                    unsafe {
+                        gl_checked! {
+                            gl::CreateBuffers(1, buffer);
+                            gl::NamedBufferStorage(
+                                *buffer,
+                                size as _,
+                                std::ptr::null(),
+                                gl::DYNAMIC_STORAGE_BIT,
+                            );
+                        };
+                    }
+                    gl_debug::label_object(gl::BUFFER, *buffer, &format!("{name} sums {i}"));
+                }
+                // dark until the first sums are read back
+                light.activity = 0.;
+            }
+        }
+        light
+    }
+
+    pub fn settings(&self) -> &ParticleLightSettings {
+        &self.settings
+    }
+
+    /// Settings, of which the name and source are kept from the creation
+    pub fn settings_mut(&mut self) -> &mut ParticleLightSettings {
+        &mut self.settings
+    }
+
+    pub fn name(&self) -> &str {
+        &self.settings.name
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Sums the particles written by the last simulation step, reads the sums of the previous
+    /// frame back, and the color and shared uniforms from the display program
+    pub fn update(
+        &mut self,
+        delta_time: GLfloat,
+        particle_count: usize,
+        display_program: &ProgramSharedPointer,
+    ) {
+        self.time += delta_time;
+        if let Some(uniform) = display_program
+            .borrow()
+            .uniform(self.settings.color_uniform.as_str())
+        {
+            if let Some(UniformValue::Vec4(color)) = uniform.borrow().get() {
+                self.color = color.truncate();
+            }
+        }
+
+        let ParticleLightSource::Particles(program) = &self.settings.source else {
+            return;
+        };
+        {
+            let program = program.borrow();
+            if let Some(uniform) = program.uniform("particle_count") {
+                uniform.borrow_mut().set_uint(particle_count as _);
+            }
+            let display_program = display_program.borrow();
+            for name in self.settings.shared_uniforms.iter() {
+                let value = display_program
+                    .uniform(name.as_str())
+                    .and_then(|u| u.borrow().get());
+                if let (Some(uniform), Some(value)) = (program.uniform(name.as_str()), value) {
+                    if uniform.borrow().accepts(&value) {
+                        uniform.borrow_mut().set(&value);
+                    }
+                }
+            }
+        }
+        let group_count = particle_count.ceil_div(self.group_size);
+        let written = self.buffers[self.frame % 2];
+        let previous = self.buffers[(self.frame + 1) % 2];
+        {
+            let _ctx = program.borrow().bound_context();
+            unsafe {
+                gl_checked! {
+                    gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, PARTICLE_LIGHT_BINDING, written);
+                    gl::DispatchCompute(group_count as _, 1, 1);
+                    gl::MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+                };
+            }
+        }
+        if self.frame > 0 {
+            let mut sums = vec![Vector4::new(0., 0., 0., 0.); group_count];
+            unsafe {
+                gl::GetNamedBufferSubData(
+                    previous,
+                    0,
+                    (sums.len() * size_of::<[GLfloat; 4]>()) as _,
+                    sums.as_mut_ptr() as _,
+                );
+                gl_check!();
+            }
+            let sum: Vector4<GLfloat> = sums.into_iter().sum();
+            // without any weight the light stays where it was
+            if sum.w > 0. {
+                self.position = Point3::from_homogeneous(sum);
+            }
+            self.activity = sum.w / particle_count.max(1) as GLfloat / self.settings.full_activity;
+        }
+        self.frame += 1;
+    }
+
+    /// Intensity of the light, following the particles' activity with some flicker
+    pub fn intensity(&self) -> GLfloat {
+        // incommensurate frequencies, never repeating exactly
+        let t = self.time;
+        let noise =
+            0.5 * (7.3 * t).sin() + 0.3 * (13.1 * t + 1.7).sin() + 0.2 * (23.7 * t + 0.4).sin();
+        let flicker = 1. + self.settings.flicker.clamp(0., 1.) * noise;
+        self.settings.intensity * self.activity * flicker
+    }
+
+    /// Moves `light` to the particles and sets its color and intensity, the other members
+    /// being left as edited
+    pub fn apply(&self, light: &mut Light) {
+        light.kind = LightKind::Point;
+        light.position = self.position;
+        light.color = self.color;
+        light.intensity = self.intensity();
+    }
+
+    /// New point light of the settings' range
+    pub fn light(&self) -> Light {
+        let mut light = Light::new(self.settings.name.clone(), LightKind::Point);
+        light.range = self.settings.range;
+        self.apply(&mut light);
+        light
+    }
+}
+
+impl Drop for ParticleLight {
+    fn drop(&mut self) {
+        if matches!(self.settings.source, ParticleLightSource::Particles(_)) {
+            unsafe {
+                gl::DeleteBuffers(2, self.buffers.as_ptr());
+                gl_check!();
+            }
+        }
+    }
+}
@@ -1,53 +1,92 @@
+use std::{cell::RefCell, rc::Rc};
+
 use gl::types::GLuint;
 
 use crate::{
-    engine::Engine,
+    bounds::Aabb,
+    definitions,
     extensions::CeilDiv,
     gl_check, gl_checked,
+    gl_debug::{self, DebugGroup},
     gl_types::{BufferIdType, DrawMode, VaoIdType},
-    program::ProgramSharedPointer,
+    program::{uniform::Uniform, ProgramSharedPointer},
+    statistics::RenderStatistics,
     traits::{Drawable, ParticleLike, Updatable},
+    view::DrawContext,
 };
 
+use super::{ParticleLight, ParticleLightSettings};
+
 pub struct ParticleSystem {
+    name: String,
     compute_program: ProgramSharedPointer,
     display_program: ProgramSharedPointer,
     vao_id: VaoIdType,
     buffer_id: BufferIdType,
     particle_count: usize,
     group_size: usize,
+    time_scale: f32,
+    paused: bool,
+    bounds: Option<Aabb>,
+    light: Option<ParticleLight>,
+    casts_shadows: bool,
+    /// Uniform of the display program switching it to the shadow maps' depth only rendering,
+    /// without which the particles can't cast shadows
+    depth_only_uniform: Option<Rc<RefCell<Uniform>>>,
 }
 
+pub type ParticleSystemPointer = Rc<RefCell<ParticleSystem>>;
+
 pub mod builder {
     use std::mem::size_of;
 
-    use crate::{gl_check, program::ProgramSharedPointer, traits::ParticleLike};
+    use crate::{
+        error::{EngineError, EngineResult},
+        gl_check,
+        program::ProgramSharedPointer,
+        traits::ParticleLike,
+    };
 
     use super::*;
 
     const DEFAULT_GROUP_SIZE: usize = 1024;
+    const DEFAULT_NAME: &str = "particle_system";
 
     pub struct ParticleSystemBuilder<ParticleType: ParticleLike> {
+        name: Option<String>,
         compute_program: Option<ProgramSharedPointer>,
         display_program: Option<ProgramSharedPointer>,
         initial_particles: Option<Vec<ParticleType>>,
         group_size: Option<usize>,
         buffer_base: Option<GLuint>,
+        bounds: Option<Aabb>,
+        light: Option<ParticleLightSettings>,
+        casts_shadows: bool,
     }
 
     impl<T: ParticleLike> Default for ParticleSystemBuilder<T> {
         fn default() -> Self {
             Self {
+                name: Default::default(),
                 compute_program: Default::default(),
                 display_program: Default::default(),
                 initial_particles: Default::default(),
                 buffer_base: Default::default(),
                 group_size: Default::default(),
+                bounds: Default::default(),
+                light: Default::default(),
+                casts_shadows: Default::default(),
             }
         }
     }
 
     impl<ParticleType: ParticleLike> ParticleSystemBuilder<ParticleType> {
+        /// Name of the particle system, used to label its OpenGL objects
+        pub fn name(mut self, name: impl Into<String>) -> Self {
+            self.name = Some(name.into());
+            self
+        }
+
         pub fn compute_program(mut self, program: ProgramSharedPointer) -> Self {
             self.compute_program = Some(program);
             self
@@ -73,51 +112,73 @@ pub mod builder {
             self
         }
 
-        fn assert_integrity(&self) {
-            let mut error = false;
+        /// World space box the particles stay in, the simulation running on the GPU
+        pub fn bounds(mut self, bounds: Aabb) -> Self {
+            self.bounds = Some(bounds);
+            self
+        }
+
+        /// Point light following the particles, added to the engine's light list
+        pub fn light(mut self, settings: ParticleLightSettings) -> Self {
+            self.light = Some(settings);
+            self
+        }
+
+        /// Whether the particles are rendered into the shadow maps, `false` by default, which
+        /// needs a `depth_only` uniform in the display program
+        pub fn casts_shadows(mut self, casts: bool) -> Self {
+            self.casts_shadows = casts;
+            self
+        }
+
+        fn check_integrity(&self) -> EngineResult<()> {
+            let mut problems = Vec::new();
 
-            if let None = self.initial_particles {
-                log::error!("Particle system's initial particles are missing");
-                error = true;
+            if self.initial_particles.is_none() {
+                problems.push("initial particles are missing".to_owned());
             };
 
             if let Some(ref program) = self.compute_program {
                 if !program.borrow().is_compute() {
-                    log::error!(
-                        "Particle system compute/simulation program is not a compute shader"
-                    );
-                    error = true;
+                    problems.push("compute/simulation program is not a compute shader".to_owned());
                 }
             } else {
-                log::error!("Particle system's compute program missing");
-                error = true;
+                problems.push("compute program is missing".to_owned());
             }
 
             if let Some(ref program) = self.display_program {
                 if !program.borrow().has_geometry() {
                     log::warn!("Particle system's display program contains no geometry shader, this may be a mistake");
                 }
+                let uniform = definitions::DEPTH_ONLY_UNIFORM_NAME;
+                if self.casts_shadows && program.borrow().uniform(uniform).is_none() {
+                    problems.push(format!(
+                        "casting shadows needs a `{uniform}` uniform in the display program"
+                    ));
+                }
             } else {
-                log::error!("Particle system's display program missing");
-                error = true;
+                problems.push("display program is missing".to_owned());
             }
 
             if let Some(ref size) = self.group_size {
                 if !(0..=1024).contains(size) {
-                    log::error!("Particle system's group size must be in the range [0,1024]");
-                    error = true;
+                    problems.push("group size must be in the range [0,1024]".to_owned());
                 }
             }
 
-            if error {
-                panic!(
-                    "particle system builder integrity assertion failed due to the previous errors"
-                );
+            if problems.is_empty() {
+                Ok(())
+            } else {
+                Err(EngineError::InvalidBuilderConfig {
+                    builder: "particle system builder",
+                    problems,
+                })
             }
         }
 
-        pub fn build(self) -> ParticleSystem {
-            self.assert_integrity();
+        pub fn build(self) -> EngineResult<ParticleSystem> {
+            self.check_integrity()?;
+            let name = self.name.unwrap_or_else(|| DEFAULT_NAME.to_owned());
             let particles = self.initial_particles.unwrap();
             let particle_byte_size = size_of::<ParticleType>();
 
@@ -132,6 +193,10 @@ pub mod builder {
 
             let mut buffer_id: BufferIdType = 0;
             let display_program = self.display_program.unwrap();
+            let depth_only_uniform = display_program
+                .borrow()
+                .uniform(definitions::DEPTH_ONLY_UNIFORM_NAME)
+                .cloned();
 
             unsafe {
                 gl_check!();
@@ -153,6 +218,9 @@ pub mod builder {
                 };
             }
 
+            gl_debug::label_object(gl::VERTEX_ARRAY, vao_id, &format!("{name} vao"));
+            gl_debug::label_object(gl::BUFFER, buffer_id, &format!("{name} particles"));
+
             ParticleType::setup_attributes();
 
             unsafe {
@@ -160,14 +228,23 @@ pub mod builder {
                 gl_check!();
             }
 
-            ParticleSystem {
+            Ok(ParticleSystem {
+                name,
                 compute_program: self.compute_program.unwrap(),
                 display_program,
                 vao_id,
                 buffer_id,
                 particle_count: particles.len(),
                 group_size: self.group_size.unwrap_or(DEFAULT_GROUP_SIZE),
-            }
+                time_scale: 1.0,
+                paused: false,
+                bounds: self.bounds,
+                light: self
+                    .light
+                    .map(|settings| ParticleLight::new(settings, particles.len())),
+                casts_shadows: self.casts_shadows,
+                depth_only_uniform,
+            })
         }
     }
 }
@@ -189,16 +266,90 @@ impl ParticleSystem {
     pub fn builder<ParticleType: ParticleLike>() -> builder::ParticleSystemBuilder<ParticleType> {
         Default::default()
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particle_count
+    }
+
+    /// Multiplier applied to the elapsed time given to the simulation
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn light(&self) -> Option<&ParticleLight> {
+        self.light.as_ref()
+    }
+
+    pub fn light_mut(&mut self) -> Option<&mut ParticleLight> {
+        self.light.as_mut()
+    }
 }
 
 impl Drawable for ParticleSystem {
-    fn draw(&self, engine: &Engine) {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn collect_statistics(&self, statistics: &mut RenderStatistics) {
+        statistics.drawables += 1;
+        statistics.draw_calls += 1;
+        statistics.particle_systems += 1;
+        statistics.particles += self.particle_count;
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    fn casts_shadows(&self) -> bool {
+        self.casts_shadows
+    }
+
+    fn set_casts_shadows(&mut self, casts: bool) -> Result<(), String> {
+        if casts && self.depth_only_uniform.is_none() {
+            return Err(format!(
+                "the display program of `{}` can't draw into the shadow maps",
+                self.name
+            ));
+        }
+        self.casts_shadows = casts;
+        Ok(())
+    }
+
+    /// Draws the particles with the display program in its depth only mode, which discards
+    /// the fragments letting the light through
+    fn draw_depth(&self, context: &DrawContext) {
+        let Some(uniform) = self.depth_only_uniform.as_ref() else {
+            return;
+        };
+        uniform.borrow_mut().set_int(1);
+        self.draw(context);
+        uniform.borrow_mut().set_int(0);
+    }
+
+    fn draw(&self, context: &DrawContext) {
         unsafe {
             gl::BindVertexArray(self.vao_id);
             gl_check!();
         }
 
-        let (cam_up, cam_forward) = engine.main_camera().unwrap().borrow().up_forward();
+        let (cam_up, cam_forward) = context.camera.up_forward();
 
         {
             let p = self.display_program.borrow();
@@ -220,7 +371,17 @@ impl Drawable for ParticleSystem {
 }
 
 impl Updatable for ParticleSystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     fn update(&mut self, delta_time: f32) {
+        if self.paused {
+            return;
+        }
+        let delta_time = delta_time * self.time_scale;
+        let _group = DebugGroup::push(&format!("{} compute", self.name));
+
         unsafe {
             gl::BindVertexArray(self.vao_id);
             gl_check!();
@@ -241,5 +402,10 @@ impl Updatable for ParticleSystem {
                 gl::BindVertexArray(0);
             };
         }
+        drop(_ctx);
+
+        if let Some(light) = self.light.as_mut() {
+            light.update(delta_time, self.particle_count, &self.display_program);
+        }
     }
 }
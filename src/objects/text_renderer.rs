@@ -0,0 +1,243 @@
+use std::mem::size_of;
+
+use cgmath::{Vector2, Vector4};
+use font8x8::legacy::BASIC_LEGACY;
+use gl::types::GLfloat;
+
+use crate::{
+    error::{EngineError, EngineResult},
+    gl_checked, gl_debug,
+    gl_types::{BufferIdType, TextureIdType, VaoIdType},
+    program::ProgramSharedPointer,
+};
+
+const GLYPH_PIXELS: usize = 8;
+const ATLAS_COLUMNS: usize = 16;
+const ATLAS_ROWS: usize = BASIC_LEGACY.len() / ATLAS_COLUMNS;
+const ATLAS_WIDTH: usize = ATLAS_COLUMNS * GLYPH_PIXELS;
+const ATLAS_HEIGHT: usize = ATLAS_ROWS * GLYPH_PIXELS;
+/// The DEL character has no glyph, its atlas cell is filled to draw plain rectangles
+const SOLID_GLYPH: usize = 0x7f;
+const FLOATS_PER_VERTEX: usize = 8; // position: vec2, uv: vec2, color: vec4
+
+/// Screen space text renderer using an 8x8 bitmap font atlas.
+///
+/// Text and rectangles are queued in pixel coordinates from the top left corner of the window,
+/// then drawn all at once by [`TextRenderer::flush`].
+pub struct TextRenderer {
+    program: ProgramSharedPointer,
+    vao_id: VaoIdType,
+    buffer_id: BufferIdType,
+    atlas_id: TextureIdType,
+    scale: f32,
+    vertices: Vec<GLfloat>,
+}
+
+fn build_atlas() -> Vec<u8> {
+    let mut pixels = vec![0u8; ATLAS_WIDTH * ATLAS_HEIGHT];
+    for (index, glyph) in BASIC_LEGACY.iter().enumerate() {
+        let glyph = if index == SOLID_GLYPH {
+            &[0xff; GLYPH_PIXELS]
+        } else {
+            glyph
+        };
+        let origin_x = (index % ATLAS_COLUMNS) * GLYPH_PIXELS;
+        let origin_y = (index / ATLAS_COLUMNS) * GLYPH_PIXELS;
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..GLYPH_PIXELS {
+                // bit 0 is the leftmost pixel of the row
+                if bits & (1 << column) != 0 {
+                    pixels[(origin_y + row) * ATLAS_WIDTH + origin_x + column] = 0xff;
+                }
+            }
+        }
+    }
+    pixels
+}
+
+impl TextRenderer {
+    /// Creates a text renderer drawing with `program`, glyphs are `8 * scale` pixels wide.
+    pub fn new(program: ProgramSharedPointer, scale: f32) -> EngineResult<Self> {
+        if program.borrow().uniform("screen_size").is_none() {
+            return Err(EngineError::InvalidBuilderConfig {
+                builder: "text renderer",
+                problems: vec![format!(
+                    "program `{}` has no `screen_size` uniform",
+                    program.borrow().name()
+                )],
+            });
+        }
+        if let Some(uniform) = program.borrow().uniform("font_atlas") {
+            uniform.borrow_mut().set_int(0);
+        }
+
+        let mut atlas_id: TextureIdType = 0;
+        let pixels = build_atlas();
+        unsafe {
+            gl_checked! {
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut atlas_id);
+                gl::TextureStorage2D(atlas_id, 1, gl::R8, ATLAS_WIDTH as _, ATLAS_HEIGHT as _);
+                gl::TextureSubImage2D(
+                    atlas_id,
+                    0,
+                    0,
+                    0,
+                    ATLAS_WIDTH as _,
+                    ATLAS_HEIGHT as _,
+                    gl::RED,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_ptr() as _,
+                );
+                gl::TextureParameteri(atlas_id, gl::TEXTURE_MIN_FILTER, gl::NEAREST as _);
+                gl::TextureParameteri(atlas_id, gl::TEXTURE_MAG_FILTER, gl::NEAREST as _);
+                gl::TextureParameteri(atlas_id, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+                gl::TextureParameteri(atlas_id, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+            };
+        }
+        gl_debug::label_object(gl::TEXTURE, atlas_id, "font atlas");
+
+        let mut vao_id: VaoIdType = 0;
+        let mut buffer_id: BufferIdType = 0;
+        let stride = (FLOATS_PER_VERTEX * size_of::<GLfloat>()) as _;
+        unsafe {
+            gl_checked! {
+                gl::GenVertexArrays(1, &mut vao_id);
+                gl::BindVertexArray(vao_id);
+                gl::GenBuffers(1, &mut buffer_id);
+                gl::BindBuffer(gl::ARRAY_BUFFER, buffer_id);
+                gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, 0 as _);
+                gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, (2 * size_of::<GLfloat>()) as _);
+                gl::VertexAttribPointer(2, 4, gl::FLOAT, gl::FALSE, stride, (4 * size_of::<GLfloat>()) as _);
+                gl::EnableVertexAttribArray(0);
+                gl::EnableVertexAttribArray(1);
+                gl::EnableVertexAttribArray(2);
+                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+                gl::BindVertexArray(0);
+            };
+        }
+        gl_debug::label_object(gl::VERTEX_ARRAY, vao_id, "text vao");
+        gl_debug::label_object(gl::BUFFER, buffer_id, "text vertices");
+
+        Ok(Self {
+            program,
+            vao_id,
+            buffer_id,
+            atlas_id,
+            scale,
+            vertices: Vec::new(),
+        })
+    }
+
+    /// Size in pixels of a single character
+    pub fn glyph_size(&self) -> Vector2<f32> {
+        Vector2::new(GLYPH_PIXELS as f32, GLYPH_PIXELS as f32) * self.scale
+    }
+
+    /// Size in pixels of a possibly multiline text
+    pub fn text_size(&self, text: &str) -> Vector2<f32> {
+        let columns = text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+        let rows = text.lines().count();
+        let glyph = self.glyph_size();
+        Vector2::new(columns as f32 * glyph.x, rows as f32 * glyph.y)
+    }
+
+    fn push_quad(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        glyph: usize,
+        color: &Vector4<f32>,
+    ) {
+        let u0 = (glyph % ATLAS_COLUMNS) as f32 / ATLAS_COLUMNS as f32;
+        let v0 = (glyph / ATLAS_COLUMNS) as f32 / ATLAS_ROWS as f32;
+        let u1 = u0 + 1. / ATLAS_COLUMNS as f32;
+        let v1 = v0 + 1. / ATLAS_ROWS as f32;
+        let (x0, y0) = (position.x, position.y);
+        let (x1, y1) = (position.x + size.x, position.y + size.y);
+
+        for (x, y, u, v) in [
+            (x0, y0, u0, v0),
+            (x0, y1, u0, v1),
+            (x1, y1, u1, v1),
+            (x0, y0, u0, v0),
+            (x1, y1, u1, v1),
+            (x1, y0, u1, v0),
+        ] {
+            self.vertices
+                .extend_from_slice(&[x, y, u, v, color.x, color.y, color.z, color.w]);
+        }
+    }
+
+    /// Queues a filled rectangle
+    pub fn queue_rect(&mut self, position: Vector2<f32>, size: Vector2<f32>, color: Vector4<f32>) {
+        self.push_quad(position, size, SOLID_GLYPH, &color);
+    }
+
+    /// Queues a text, `position` being its top left corner. Non-ASCII characters are drawn as `?`.
+    pub fn queue_text(&mut self, position: Vector2<f32>, text: &str, color: Vector4<f32>) {
+        let glyph_size = self.glyph_size();
+        for (row, line) in text.lines().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                let glyph = if c.is_ascii() {
+                    c as usize
+                } else {
+                    '?' as usize
+                };
+                let offset = Vector2::new(column as f32 * glyph_size.x, row as f32 * glyph_size.y);
+                self.push_quad(position + offset, glyph_size, glyph, &color);
+            }
+        }
+    }
+
+    /// Draws and clears everything queued since the last flush, on top of the current frame.
+    pub fn flush(&mut self, screen_width: i32, screen_height: i32) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        if let Some(uniform) = self.program.borrow().uniform("screen_size") {
+            uniform
+                .borrow_mut()
+                .set_vec2(&Vector2::new(screen_width as f32, screen_height as f32));
+        }
+
+        let _ctx = self.program.borrow().bound_context();
+
+        unsafe {
+            gl_checked! {
+                gl::NamedBufferData(
+                    self.buffer_id,
+                    (self.vertices.len() * size_of::<GLfloat>()) as _,
+                    self.vertices.as_ptr() as _,
+                    gl::STREAM_DRAW,
+                );
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::CULL_FACE);
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+                gl::BindTextureUnit(0, self.atlas_id);
+                gl::BindVertexArray(self.vao_id);
+                gl::DrawArrays(gl::TRIANGLES, 0, (self.vertices.len() / FLOATS_PER_VERTEX) as _);
+                gl::BindVertexArray(0);
+                gl::BindTextureUnit(0, 0);
+                gl::Disable(gl::BLEND);
+                gl::Enable(gl::CULL_FACE);
+                gl::Enable(gl::DEPTH_TEST);
+            };
+        }
+
+        self.vertices.clear();
+    }
+}
+
+impl Drop for TextRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl_checked! {
+                gl::DeleteBuffers(1, &self.buffer_id);
+                gl::DeleteVertexArrays(1, &self.vao_id);
+                gl::DeleteTextures(1, &self.atlas_id);
+            };
+        }
+    }
+}
@@ -4,11 +4,11 @@ use std::{
     ops::Range,
 };
 
-use cgmath::Vector3;
+use cgmath::{Point3, Vector3};
 use gl::types::GLfloat;
 use rand::Rng;
 
-use crate::{gl_checked, traits::ParticleLike};
+use crate::{bounds::Aabb, gl_checked, traits::ParticleLike};
 
 #[repr(C, align(16))]
 #[derive(Debug, Default)]
@@ -45,22 +45,45 @@ impl ParticleLike for FireParticle {
     }
 }
 
+const X_POS_RANGE: Range<f32> = -1.0..1.0;
+const Y_POS_RANGE: Range<f32> = -1.0..1.0;
+const Z_POS: f32 = 0.0;
+const MAX_HOR_SPEED: f32 = 0.5;
+const MAX_VERT_SPEED: f32 = 2.0;
+/// Initial `max_fire_lifetime` of the fire's programs, the age at which the flames turn to smoke
+pub const MAX_FIRE_LIFETIME: f32 = 1.0;
+
 impl FireParticle {
-    pub fn spawn(count: usize) -> Vec<Self> {
-        const X_POS_RANGE: Range<f32> = -1.0..1.0;
-        const Y_POS_RANGE: Range<f32> = -1.0..1.0;
-        const Z_POS: f32 = 0.0;
+    /// Box containing the spawned particles during their whole life
+    pub fn bounds(max_lifetime: f32) -> Aabb {
+        let hor = MAX_HOR_SPEED * max_lifetime;
+        Aabb::new(
+            Point3::new(X_POS_RANGE.start - hor, Y_POS_RANGE.start - hor, Z_POS),
+            Point3::new(
+                X_POS_RANGE.end + hor,
+                Y_POS_RANGE.end + hor,
+                Z_POS + MAX_VERT_SPEED * max_lifetime,
+            ),
+        )
+    }
 
-        let mut particles = vec![];
+    /// Mean weight given to the particles by the light reduction program, for particles
+    /// spawned at lifetimes spread over `max_lifetime`, those younger than
+    /// `max_fire_lifetime` glowing the more the younger
+    pub fn steady_light_activity(max_lifetime: f32, max_fire_lifetime: f32) -> f32 {
+        (max_fire_lifetime / max_lifetime).min(1.) / 2.
+    }
 
-        let mut rng = rand::thread_rng();
+    /// Spawns `count` particles with random properties drawn from `rng`
+    pub fn spawn(count: usize, rng: &mut impl Rng) -> Vec<Self> {
+        let mut particles = vec![];
 
         for _ in 0..count {
             let lifetime = rng.gen_range(0.0..4.0);
 
             let yaw = rng.gen_range(0.0..(2.0 * PI));
-            let hor_scale = rng.gen_range(0.0..0.5);
-            let vert_scale = rng.gen_range(1.0..2.0);
+            let hor_scale = rng.gen_range(0.0..MAX_HOR_SPEED);
+            let vert_scale = rng.gen_range(1.0..MAX_VERT_SPEED);
             let velocity = Vector3::new(yaw.sin() * hor_scale, yaw.cos() * hor_scale, vert_scale);
 
             let position = Vector3::new(
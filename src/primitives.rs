@@ -0,0 +1,536 @@
+use std::{
+    f32::consts::{FRAC_PI_2, PI, TAU},
+    mem::offset_of,
+    str::FromStr,
+};
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{InnerSpace, Vector3, Vector4};
+use gl::types::GLfloat;
+
+use crate::{
+    extensions::SafeNormalize,
+    obj::Material,
+    objects::{Indices, MeshRenderer, MeshRendererBuilder, MeshSection},
+    program::ProgramSharedPointer,
+    vertex_layout::{VertexAttribute, VertexLayout},
+};
+
+/// Highest `detail` of [`PrimitiveShape::generate`], giving shapes of less than 150 000
+/// triangles
+pub const MAX_DETAIL: u32 = 16;
+/// Subdivisions beyond which an icosphere would have millions of triangles
+const MAX_ICOSPHERE_SUBDIVISIONS: u32 = 7;
+
+/// Vertex of a generated mesh, the tangent's `w` giving the bitangent's side like in glTF
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PrimitiveVertex {
+    pub position: [GLfloat; 3],
+    pub normal: [GLfloat; 3],
+    pub tangent: [GLfloat; 4],
+    pub uv: [GLfloat; 2],
+}
+
+impl PrimitiveVertex {
+    fn new(
+        position: Vector3<GLfloat>,
+        normal: Vector3<GLfloat>,
+        tangent: Vector3<GLfloat>,
+        uv: [GLfloat; 2],
+    ) -> Self {
+        Self {
+            position: position.into(),
+            normal: normal.safe_normalize().into(),
+            tangent: tangent.safe_normalize().extend(1.).into(),
+            uv,
+        }
+    }
+}
+
+/// Indexed triangle mesh of a standard shape, centered on the origin with Z up and
+/// counter-clockwise front faces
+#[derive(Clone, Debug, Default)]
+pub struct PrimitiveMesh {
+    pub vertices: Vec<PrimitiveVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl PrimitiveMesh {
+    /// Flat grid of `width` along X by `depth` along Y, facing up, with `columns` by `rows`
+    /// quads
+    pub fn plane(width: GLfloat, depth: GLfloat, columns: u32, rows: u32) -> Self {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        let mut mesh = Self::default();
+        mesh.add_grid(columns, rows, |u, v| {
+            PrimitiveVertex::new(
+                Vector3::new((u - 0.5) * width, (v - 0.5) * depth, 0.),
+                Vector3::unit_z(),
+                Vector3::unit_x(),
+                [u, v],
+            )
+        });
+        mesh
+    }
+
+    /// Sphere of `segments` meridians and `rings` parallels, u following the longitude and v
+    /// the latitude from the south pole
+    pub fn uv_sphere(radius: GLfloat, segments: u32, rings: u32) -> Self {
+        let (segments, rings) = (segments.max(3), rings.max(2));
+        let mut mesh = Self::default();
+        mesh.add_grid(segments, rings, |u, v| {
+            let normal = sphere_direction(u * TAU, v * PI - FRAC_PI_2);
+            PrimitiveVertex::new(normal * radius, normal, parallel_tangent(u * TAU), [u, v])
+        });
+        mesh
+    }
+
+    /// Sphere subdividing the faces of an icosahedron `subdivisions` times, its triangles of
+    /// about the same size unlike those of a UV sphere; its uvs are spherical and stretch
+    /// across the triangles crossing the -X meridian, `subdivisions` being at most 7
+    pub fn icosphere(radius: GLfloat, subdivisions: u32) -> Self {
+        let subdivisions = subdivisions.min(MAX_ICOSPHERE_SUBDIVISIONS);
+        let t = (1. + 5f32.sqrt()) / 2.;
+        let mut directions: Vec<Vector3<GLfloat>> = [
+            [-1., t, 0.],
+            [1., t, 0.],
+            [-1., -t, 0.],
+            [1., -t, 0.],
+            [0., -1., t],
+            [0., 1., t],
+            [0., -1., -t],
+            [0., 1., -t],
+            [t, 0., -1.],
+            [t, 0., 1.],
+            [-t, 0., -1.],
+            [-t, 0., 1.],
+        ]
+        .into_iter()
+        .map(|v| Vector3::from(v).normalize())
+        .collect();
+        let mut triangles: Vec<[u32; 3]> = vec![
+            [0, 11, 5],
+            [0, 5, 1],
+            [0, 1, 7],
+            [0, 7, 10],
+            [0, 10, 11],
+            [1, 5, 9],
+            [5, 11, 4],
+            [11, 10, 2],
+            [10, 7, 6],
+            [7, 1, 8],
+            [3, 9, 4],
+            [3, 4, 2],
+            [3, 2, 6],
+            [3, 6, 8],
+            [3, 8, 9],
+            [4, 9, 5],
+            [2, 4, 11],
+            [6, 2, 10],
+            [8, 6, 7],
+            [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            // midpoints shared by the two triangles of an edge
+            let mut midpoints = std::collections::HashMap::new();
+            let mut midpoint = |a: u32, b: u32| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    let direction = (directions[a as usize] + directions[b as usize]).normalize();
+                    directions.push(direction);
+                    directions.len() as u32 - 1
+                })
+            };
+            triangles = triangles
+                .into_iter()
+                .flat_map(|[a, b, c]| {
+                    let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                    [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+
+        let vertices = directions
+            .into_iter()
+            .map(|normal| {
+                let longitude = normal.y.atan2(normal.x);
+                let latitude = normal.z.clamp(-1., 1.).asin();
+                PrimitiveVertex::new(
+                    normal * radius,
+                    normal,
+                    parallel_tangent(longitude),
+                    [longitude / TAU + 0.5, latitude / PI + 0.5],
+                )
+            })
+            .collect();
+        Self {
+            vertices,
+            indices: triangles.into_iter().flatten().collect(),
+        }
+    }
+
+    /// Cylinder around the Z axis of `segments` sides and `rings` rows of quads, closed by
+    /// disks if `caps`
+    pub fn cylinder(
+        radius: GLfloat,
+        height: GLfloat,
+        segments: u32,
+        rings: u32,
+        caps: bool,
+    ) -> Self {
+        let (segments, rings) = (segments.max(3), rings.max(1));
+        let mut mesh = Self::default();
+        mesh.add_grid(segments, rings, |u, v| {
+            let normal = sphere_direction(u * TAU, 0.);
+            PrimitiveVertex::new(
+                normal * radius + Vector3::new(0., 0., (v - 0.5) * height),
+                normal,
+                parallel_tangent(u * TAU),
+                [u, v],
+            )
+        });
+        if caps {
+            mesh.add_disk(-height / 2., radius, segments, false);
+            mesh.add_disk(height / 2., radius, segments, true);
+        }
+        mesh
+    }
+
+    /// Cone around the Z axis pointing up, of `segments` sides and `rings` rows of quads,
+    /// closed by a disk at its base
+    pub fn cone(radius: GLfloat, height: GLfloat, segments: u32, rings: u32) -> Self {
+        let (segments, rings) = (segments.max(3), rings.max(1));
+        let mut mesh = Self::default();
+        mesh.add_grid(segments, rings, |u, v| {
+            let direction = sphere_direction(u * TAU, 0.);
+            // the apex is a ring of vertices, each with the normal of its side
+            PrimitiveVertex::new(
+                direction * radius * (1. - v) + Vector3::new(0., 0., (v - 0.5) * height),
+                direction * height + Vector3::new(0., 0., radius),
+                parallel_tangent(u * TAU),
+                [u, v],
+            )
+        });
+        mesh.add_disk(-height / 2., radius, segments, false);
+        mesh
+    }
+
+    /// Torus around the Z axis, its tube of `minor_radius` going round at `major_radius`
+    /// from the center in `segments` steps, and made of `sides` quads
+    pub fn torus(major_radius: GLfloat, minor_radius: GLfloat, segments: u32, sides: u32) -> Self {
+        let (segments, sides) = (segments.max(3), sides.max(3));
+        let mut mesh = Self::default();
+        mesh.add_grid(segments, sides, |u, v| {
+            let normal = sphere_direction(u * TAU, v * TAU - PI);
+            let center = sphere_direction(u * TAU, 0.) * major_radius;
+            PrimitiveVertex::new(
+                center + normal * minor_radius,
+                normal,
+                parallel_tangent(u * TAU),
+                [u, v],
+            )
+        });
+        mesh
+    }
+
+    /// Cylinder of `height` around the Z axis capped by hemispheres of `rings` parallels,
+    /// `height + 2 * radius` long overall
+    pub fn capsule(radius: GLfloat, height: GLfloat, segments: u32, rings: u32) -> Self {
+        let (segments, rings) = (segments.max(3), rings.max(1));
+        let length = height + 2. * radius;
+        let mut mesh = Self::default();
+        // the rows of the two hemispheres, those of the equators being the cylinder's ends
+        mesh.add_grid(segments, 2 * rings + 1, |u, v| {
+            let row = (v * (2 * rings + 1) as GLfloat).round() as u32;
+            let (latitude, z) = if row <= rings {
+                (
+                    (row as GLfloat / rings as GLfloat - 1.) * FRAC_PI_2,
+                    -height / 2.,
+                )
+            } else {
+                (
+                    (row - rings - 1) as GLfloat / rings as GLfloat * FRAC_PI_2,
+                    height / 2.,
+                )
+            };
+            let normal = sphere_direction(u * TAU, latitude);
+            let position = normal * radius + Vector3::new(0., 0., z);
+            PrimitiveVertex::new(
+                position,
+                normal,
+                parallel_tangent(u * TAU),
+                [u, position.z / length + 0.5],
+            )
+        });
+        mesh
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Layout of [`PrimitiveVertex`], with the attributes among `position`, `normal`,
+    /// `tangent` and `uv` that `keep` accepts
+    pub fn layout(keep: impl Fn(&str) -> bool) -> VertexLayout {
+        [
+            VertexAttribute::float("position", 3).offset(offset_of!(PrimitiveVertex, position)),
+            VertexAttribute::float("normal", 3).offset(offset_of!(PrimitiveVertex, normal)),
+            VertexAttribute::float("tangent", 4).offset(offset_of!(PrimitiveVertex, tangent)),
+            VertexAttribute::float("uv", 2).offset(offset_of!(PrimitiveVertex, uv)),
+        ]
+        .into_iter()
+        .filter(|attribute| keep(&attribute.name))
+        .fold(
+            VertexLayout::of::<PrimitiveVertex>(),
+            VertexLayout::attribute,
+        )
+    }
+
+    /// Mesh renderer builder with the position attribute, the other ones the program uses,
+    /// and a single section of a material of `color`
+    pub fn renderer_builder(
+        &self,
+        program: ProgramSharedPointer,
+        color: Vector4<GLfloat>,
+    ) -> MeshRendererBuilder {
+        let layout = {
+            let program = program.borrow();
+            Self::layout(|name| name == "position" || program.has_attribute(name))
+        };
+        MeshRenderer::builder()
+            .shader(program)
+            .add_vertex_buffer(&self.vertices, layout)
+            .add_section(MeshSection {
+                start: 0,
+                count: self.indices.len(),
+                uniforms: Material::with_color("primitive", color).uniforms(),
+                texture: None,
+            })
+            .indices(Indices::compact(self.indices.clone()))
+    }
+
+    /// Adds `(columns + 1) * (rows + 1)` vertices given their uv, and the two triangles of
+    /// each quad, front facing where the surface's derivative along u crossed with the one
+    /// along v points
+    fn add_grid(
+        &mut self,
+        columns: u32,
+        rows: u32,
+        vertex: impl Fn(GLfloat, GLfloat) -> PrimitiveVertex,
+    ) {
+        let first = self.vertices.len() as u32;
+        for row in 0..=rows {
+            for column in 0..=columns {
+                self.vertices.push(vertex(
+                    column as GLfloat / columns as GLfloat,
+                    row as GLfloat / rows as GLfloat,
+                ));
+            }
+        }
+        let index = |column: u32, row: u32| first + row * (columns + 1) + column;
+        for row in 0..rows {
+            for column in 0..columns {
+                let (a, b, c, d) = (
+                    index(column, row),
+                    index(column + 1, row),
+                    index(column + 1, row + 1),
+                    index(column, row + 1),
+                );
+                self.indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+    }
+
+    /// Adds a disk at height `z` as a fan around its center, facing up or down
+    fn add_disk(&mut self, z: GLfloat, radius: GLfloat, segments: u32, up: bool) {
+        let normal = Vector3::new(0., 0., if up { 1. } else { -1. });
+        let center = self.vertices.len() as u32;
+        self.vertices.push(PrimitiveVertex::new(
+            Vector3::new(0., 0., z),
+            normal,
+            Vector3::unit_x(),
+            [0.5, 0.5],
+        ));
+        for segment in 0..=segments {
+            let direction = sphere_direction(segment as GLfloat / segments as GLfloat * TAU, 0.);
+            self.vertices.push(PrimitiveVertex::new(
+                direction * radius + Vector3::new(0., 0., z),
+                normal,
+                Vector3::unit_x(),
+                [0.5 + direction.x / 2., 0.5 + direction.y / 2.],
+            ));
+        }
+        for segment in 0..segments {
+            let (a, b) = (center + 1 + segment, center + 2 + segment);
+            if up {
+                self.indices.extend_from_slice(&[center, a, b]);
+            } else {
+                self.indices.extend_from_slice(&[center, b, a]);
+            }
+        }
+    }
+}
+
+/// Unit vector at a longitude around Z from X and a latitude from the XY plane, in radians
+fn sphere_direction(longitude: GLfloat, latitude: GLfloat) -> Vector3<GLfloat> {
+    Vector3::new(
+        latitude.cos() * longitude.cos(),
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+    )
+}
+
+/// Direction of increasing longitude, the tangent of surfaces of revolution around Z
+fn parallel_tangent(longitude: GLfloat) -> Vector3<GLfloat> {
+    Vector3::new(-longitude.sin(), longitude.cos(), 0.)
+}
+
+/// Shapes the `shape` command adds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrimitiveShape {
+    Plane,
+    UvSphere,
+    Icosphere,
+    Cylinder,
+    Cone,
+    Torus,
+    Capsule,
+}
+
+impl PrimitiveShape {
+    pub const ALL: [Self; 7] = [
+        Self::Plane,
+        Self::UvSphere,
+        Self::Icosphere,
+        Self::Cylinder,
+        Self::Cone,
+        Self::Torus,
+        Self::Capsule,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Plane => "plane",
+            Self::UvSphere => "uv-sphere",
+            Self::Icosphere => "icosphere",
+            Self::Cylinder => "cylinder",
+            Self::Cone => "cone",
+            Self::Torus => "torus",
+            Self::Capsule => "capsule",
+        }
+    }
+
+    /// Shape about a unit in size, `detail` scaling its tessellation from 1 for a coarse one
+    /// to [`MAX_DETAIL`]
+    pub fn generate(&self, detail: u32) -> PrimitiveMesh {
+        let detail = detail.clamp(1, MAX_DETAIL);
+        match self {
+            Self::Plane => PrimitiveMesh::plane(2., 2., 4 * detail, 4 * detail),
+            Self::UvSphere => PrimitiveMesh::uv_sphere(1., 16 * detail, 8 * detail),
+            // each subdivision quadruples the triangles
+            Self::Icosphere => PrimitiveMesh::icosphere(1., detail.ilog2() + 2),
+            Self::Cylinder => PrimitiveMesh::cylinder(1., 2., 16 * detail, detail, true),
+            Self::Cone => PrimitiveMesh::cone(1., 2., 16 * detail, detail),
+            Self::Torus => PrimitiveMesh::torus(1., 0.35, 24 * detail, 12 * detail),
+            Self::Capsule => PrimitiveMesh::capsule(0.6, 1., 16 * detail, 4 * detail),
+        }
+    }
+}
+
+impl FromStr for PrimitiveShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|shape| shape.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(Self::name).collect();
+                format!("unknown shape `{s}`, expected one of {}", names.join(", "))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meshes(detail: u32) -> impl Iterator<Item = (PrimitiveShape, PrimitiveMesh)> {
+        PrimitiveShape::ALL
+            .into_iter()
+            .map(move |shape| (shape, shape.generate(detail)))
+    }
+
+    #[test]
+    fn indices_are_in_range() {
+        for detail in [1, 3, MAX_DETAIL] {
+            for (shape, mesh) in meshes(detail) {
+                assert_eq!(mesh.indices.len() % 3, 0, "{shape:?}");
+                let count = mesh.vertex_count() as u32;
+                assert!(mesh.indices.iter().all(|i| *i < count), "{shape:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn front_faces_are_counter_clockwise_around_the_normals() {
+        for (shape, mesh) in meshes(2) {
+            let position = |i: u32| Vector3::from(mesh.vertices[i as usize].position);
+            for triangle in mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(position);
+                let face_normal = (b - a).cross(c - a);
+                // the triangles collapsed at the poles have no orientation
+                if face_normal.magnitude() < 1e-6 {
+                    continue;
+                }
+                for i in triangle {
+                    let normal = Vector3::from(mesh.vertices[*i as usize].normal);
+                    assert!(
+                        face_normal.dot(normal) > 0.,
+                        "{shape:?}: triangle {triangle:?} faces away from its normals"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn normals_are_unit_vectors() {
+        for (shape, mesh) in meshes(2) {
+            for vertex in mesh.vertices.iter() {
+                let length = Vector3::from(vertex.normal).magnitude();
+                assert!((length - 1.).abs() < 1e-4, "{shape:?}: {vertex:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn detail_is_bounded_by_the_documented_size() {
+        for (shape, mesh) in meshes(MAX_DETAIL) {
+            assert!(mesh.triangle_count() < 150_000, "{shape:?}");
+            let above = shape.generate(MAX_DETAIL * 4);
+            assert_eq!(above.triangle_count(), mesh.triangle_count(), "{shape:?}");
+        }
+        // detail 0 is raised to 1
+        let coarsest = PrimitiveShape::Plane.generate(1);
+        assert_eq!(
+            PrimitiveShape::Plane.generate(0).triangle_count(),
+            coarsest.triangle_count()
+        );
+    }
+
+    #[test]
+    fn icosphere_subdivisions_are_capped() {
+        let capped = PrimitiveMesh::icosphere(1., MAX_ICOSPHERE_SUBDIVISIONS);
+        assert_eq!(
+            capped.triangle_count(),
+            20 * 4usize.pow(MAX_ICOSPHERE_SUBDIVISIONS)
+        );
+        let above = PrimitiveMesh::icosphere(1., 100);
+        assert_eq!(above.triangle_count(), capped.triangle_count());
+    }
+}
@@ -0,0 +1,451 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use gl::types::{GLint, GLuint, GLuint64};
+use serde::Serialize;
+
+use crate::{
+    error::{EngineError, EngineResult},
+    gl_check,
+};
+
+/// Amount of samples kept by each rolling statistic
+const WINDOW_SIZE: usize = 300;
+const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+const CPU_TRACE_THREAD: u32 = 1;
+const GPU_TRACE_THREAD: u32 = 2;
+
+/// Rolling window of timing samples, in milliseconds
+#[derive(Default)]
+pub struct RollingStats {
+    samples: VecDeque<f32>,
+    total_count: u64,
+}
+
+impl RollingStats {
+    pub fn push(&mut self, sample: f32) {
+        if self.samples.len() == WINDOW_SIZE {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        self.total_count += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn average(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.;
+        }
+        self.samples.iter().sum::<f32>() / self.samples.len() as f32
+    }
+
+    /// Nearest-rank percentile of the current window, `percent` is in the range [0,100]
+    pub fn percentile(&self, percent: f32) -> f32 {
+        if self.samples.is_empty() {
+            return 0.;
+        }
+        let mut sorted: Vec<f32> = self.samples.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let rank = ((percent / 100.) * sorted.len() as f32).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+
+    pub fn max(&self) -> f32 {
+        self.samples.iter().copied().fold(0., f32::max)
+    }
+}
+
+/// Pair of `GL_TIME_ELAPSED` queries used alternately, so that results are read one frame late
+/// instead of stalling the pipeline.
+struct GpuTimer {
+    queries: [GLuint; 2],
+    pending: [Option<u64>; 2], // CPU timestamp (µs) at which the pending query began
+    current: usize,
+}
+
+impl GpuTimer {
+    fn new() -> Self {
+        let mut queries = [0; 2];
+        unsafe {
+            gl::GenQueries(2, queries.as_mut_ptr());
+            gl_check!();
+        }
+        Self {
+            queries,
+            pending: [None; 2],
+            current: 0,
+        }
+    }
+
+    /// Collects the result of the query about to be reused, if it is available.
+    ///
+    /// Returns the begin timestamp and duration in milliseconds of the collected measure.
+    fn collect(&mut self) -> Option<(u64, f32)> {
+        let begin = self.pending[self.current]?;
+        let query = self.queries[self.current];
+        let mut available: GLint = 0;
+        unsafe {
+            gl::GetQueryObjectiv(query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+            gl_check!();
+        }
+        self.pending[self.current] = None;
+        if available == 0 {
+            // dropping the sample is preferable to waiting on the GPU
+            return None;
+        }
+        let mut elapsed: GLuint64 = 0;
+        unsafe {
+            gl::GetQueryObjectui64v(query, gl::QUERY_RESULT, &mut elapsed);
+            gl_check!();
+        }
+        Some((begin, elapsed as f32 / 1_000_000.))
+    }
+
+    fn begin(&mut self, timestamp: u64) {
+        self.pending[self.current] = Some(timestamp);
+        unsafe {
+            gl::BeginQuery(gl::TIME_ELAPSED, self.queries[self.current]);
+            gl_check!();
+        }
+    }
+
+    fn end(&mut self) {
+        unsafe {
+            gl::EndQuery(gl::TIME_ELAPSED);
+            gl_check!();
+        }
+        self.current = 1 - self.current;
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteQueries(2, self.queries.as_ptr());
+        }
+    }
+}
+
+#[derive(Default)]
+struct ScopeTimings {
+    cpu: RollingStats,
+    gpu: RollingStats,
+    gpu_timer: Option<GpuTimer>,
+}
+
+#[derive(Serialize)]
+struct TraceEvent {
+    name: String,
+    ph: &'static str,
+    ts: u64,
+    dur: u64,
+    pid: u32,
+    tid: u32,
+    /// Values of counter events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct ScopeSummary<'a> {
+    scope: &'a str,
+    clock: &'static str,
+    samples: u64,
+    average_ms: f32,
+    p50_ms: f32,
+    p95_ms: f32,
+    p99_ms: f32,
+    max_ms: f32,
+}
+
+impl<'a> ScopeSummary<'a> {
+    fn new(scope: &'a str, clock: &'static str, stats: &RollingStats) -> Self {
+        Self {
+            scope,
+            clock,
+            samples: stats.total_count,
+            average_ms: stats.average(),
+            p50_ms: stats.percentile(50.),
+            p95_ms: stats.percentile(95.),
+            p99_ms: stats.percentile(99.),
+            max_ms: stats.max(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ProfilerSettings {
+    /// File receiving the timing summary on exit, as CSV if its extension is `csv`, JSON otherwise
+    pub output: Option<PathBuf>,
+    /// File receiving every recorded scope in the Chrome trace event format
+    pub chrome_trace: Option<PathBuf>,
+}
+
+/// Frame profiler measuring CPU and GPU time of named scopes.
+///
+/// Disabled by default, in which case scopes cost nothing.
+#[derive(Default)]
+pub struct Profiler {
+    enabled: bool,
+    settings: ProfilerSettings,
+    scope_order: Vec<String>,
+    scopes: HashMap<String, ScopeTimings>,
+    trace: Vec<TraceEvent>,
+    start: Option<Instant>,
+    last_report: Option<Instant>,
+}
+
+/// RAII guard measuring a profiler scope until dropped, see [`Profiler::scope`]
+pub struct ProfileScope<'a> {
+    profiler: &'a RefCell<Profiler>,
+    name: String,
+    begin: Instant,
+    gpu: bool,
+}
+
+impl Drop for ProfileScope<'_> {
+    fn drop(&mut self) {
+        self.profiler
+            .borrow_mut()
+            .end_scope(&self.name, self.begin, self.gpu);
+    }
+}
+
+impl Profiler {
+    pub fn enable(&mut self, settings: ProfilerSettings) {
+        let now = Instant::now();
+        self.enabled = true;
+        self.settings = settings;
+        self.start = Some(now);
+        self.last_report = Some(now);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Starts measuring the scope `name` until the returned guard is dropped.
+    ///
+    /// If `gpu` is set, GPU time of the commands issued in the scope is measured too.
+    /// GPU scopes must not be nested.
+    #[must_use]
+    pub fn scope<'a>(
+        profiler: &'a RefCell<Profiler>,
+        name: impl Into<String>,
+        gpu: bool,
+    ) -> Option<ProfileScope<'a>> {
+        let mut this = profiler.borrow_mut();
+        if !this.enabled {
+            return None;
+        }
+        let name = name.into();
+        let begin = Instant::now();
+        if gpu {
+            let timestamp = this.timestamp(begin);
+            let timings = this.timings_mut(&name);
+            let timer = timings.gpu_timer.get_or_insert_with(GpuTimer::new);
+            let collected = timer.collect();
+            timer.begin(timestamp);
+            if let Some((gpu_begin, duration)) = collected {
+                timings.gpu.push(duration);
+                this.push_trace(&name, GPU_TRACE_THREAD, gpu_begin, duration);
+            }
+        }
+        Some(ProfileScope {
+            profiler,
+            name,
+            begin,
+            gpu,
+        })
+    }
+
+    fn timestamp(&self, instant: Instant) -> u64 {
+        self.start
+            .map(|start| instant.duration_since(start).as_micros() as u64)
+            .unwrap_or_default()
+    }
+
+    fn timings_mut(&mut self, name: &str) -> &mut ScopeTimings {
+        if !self.scopes.contains_key(name) {
+            self.scope_order.push(name.to_owned());
+        }
+        self.scopes.entry(name.to_owned()).or_default()
+    }
+
+    fn push_trace(&mut self, name: &str, thread: u32, begin: u64, duration_ms: f32) {
+        if self.settings.chrome_trace.is_none() {
+            return;
+        }
+        self.trace.push(TraceEvent {
+            name: name.to_owned(),
+            ph: "X",
+            ts: begin,
+            dur: (duration_ms * 1000.) as u64,
+            pid: 1,
+            tid: thread,
+            args: None,
+        });
+    }
+
+    /// Records the values of a named group of counters, shown as a graph in the Chrome trace
+    pub fn counters(&mut self, name: &str, values: &[(&str, usize)]) {
+        if !self.enabled || self.settings.chrome_trace.is_none() {
+            return;
+        }
+        let args = values
+            .iter()
+            .map(|(key, value)| (key.to_string(), serde_json::Value::from(*value)))
+            .collect();
+        let timestamp = self.timestamp(Instant::now());
+        self.trace.push(TraceEvent {
+            name: name.to_owned(),
+            ph: "C",
+            ts: timestamp,
+            dur: 0,
+            pid: 1,
+            tid: CPU_TRACE_THREAD,
+            args: Some(serde_json::Value::Object(args)),
+        });
+    }
+
+    fn end_scope(&mut self, name: &str, begin: Instant, gpu: bool) {
+        let duration = begin.elapsed().as_secs_f32() * 1000.;
+        let timestamp = self.timestamp(begin);
+        let timings = self.timings_mut(name);
+        if gpu {
+            if let Some(timer) = timings.gpu_timer.as_mut() {
+                timer.end();
+            }
+        }
+        timings.cpu.push(duration);
+        self.push_trace(name, CPU_TRACE_THREAD, timestamp, duration);
+    }
+
+    /// Logs a summary of the timings if the report interval has elapsed, to be called once per frame.
+    pub fn end_frame(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        let Some(last_report) = self.last_report else {
+            return;
+        };
+        if last_report.elapsed() < DEFAULT_REPORT_INTERVAL {
+            return;
+        }
+        self.last_report = Some(Instant::now());
+
+        let mut report = String::from("frame profile (average / p95, ms):");
+        for name in self.scope_order.iter() {
+            let timings = &self.scopes[name];
+            let _ = write!(
+                report,
+                "\n  {name:<24} cpu {:>7.3} / {:>7.3}",
+                timings.cpu.average(),
+                timings.cpu.percentile(95.)
+            );
+            if !timings.gpu.is_empty() {
+                let _ = write!(
+                    report,
+                    "  gpu {:>7.3} / {:>7.3}",
+                    timings.gpu.average(),
+                    timings.gpu.percentile(95.)
+                );
+            }
+        }
+        log::info!("{report}");
+    }
+
+    fn summaries(&self) -> Vec<ScopeSummary<'_>> {
+        let mut summaries = Vec::new();
+        for name in self.scope_order.iter() {
+            let timings = &self.scopes[name];
+            summaries.push(ScopeSummary::new(name, "cpu", &timings.cpu));
+            if !timings.gpu.is_empty() {
+                summaries.push(ScopeSummary::new(name, "gpu", &timings.gpu));
+            }
+        }
+        summaries
+    }
+
+    fn write_summary(&self, path: &Path) -> EngineResult<()> {
+        let summaries = self.summaries();
+        let content = if path.extension().is_some_and(|ext| ext == "csv") {
+            let mut csv =
+                String::from("scope,clock,samples,average_ms,p50_ms,p95_ms,p99_ms,max_ms\n");
+            for s in summaries.iter() {
+                let _ = writeln!(
+                    csv,
+                    "{},{},{},{},{},{},{},{}",
+                    csv_field(s.scope),
+                    s.clock,
+                    s.samples,
+                    s.average_ms,
+                    s.p50_ms,
+                    s.p95_ms,
+                    s.p99_ms,
+                    s.max_ms
+                );
+            }
+            csv
+        } else {
+            serde_json::to_string_pretty(&summaries).unwrap_or_default()
+        };
+        fs::write(path, content).map_err(|e| EngineError::io(path, e))
+    }
+
+    fn write_chrome_trace(&self, path: &Path) -> EngineResult<()> {
+        let trace = serde_json::json!({
+            "traceEvents": self.trace,
+            "displayTimeUnit": "ms",
+        });
+        fs::write(path, trace.to_string()).map_err(|e| EngineError::io(path, e))
+    }
+
+    /// Writes the configured output files, to be called once before exiting.
+    pub fn dump(&self) -> EngineResult<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if let Some(path) = self.settings.output.as_ref() {
+            self.write_summary(path)?;
+            log::info!("profile summary written to `{}`", path.display());
+        }
+        if let Some(path) = self.settings.chrome_trace.as_ref() {
+            self.write_chrome_trace(path)?;
+            log::info!("chrome trace written to `{}`", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Quotes a CSV field containing separators, quotes or line breaks, doubling its quotes
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("draw/main/cube"), "draw/main/cube");
+        assert_eq!(csv_field("draw/main/a,b"), "\"draw/main/a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+}
@@ -1,8 +1,19 @@
-use std::{cell::RefCell, collections::HashMap, ffi::CStr, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{CStr, CString},
+    rc::Rc,
+};
 
 use gl::types::{GLenum, GLint, GLuint};
 
-use crate::{gl_check, gl_utils::cstring_with_null_bytes, program::shader::ShaderCompileError};
+use crate::{
+    error::{EngineError, EngineResult},
+    gl_check,
+    gl_debug,
+    gl_utils::cstring_with_null_bytes,
+    program::shader::ShaderCompileError,
+};
 
 use self::{
     shader::{Shader, ShaderHandle, ShaderType},
@@ -20,6 +31,7 @@ pub type UniformEntryType = Rc<RefCell<Uniform>>;
 pub struct Program {
     id: ProgramIdType,
     _shaders: Vec<ShaderHandle>, // program needs to hold onto shader handles if needed and fro simplified cleanup, maybe unnecessary
+    sources: Vec<(String, Shader)>, // kept to reload the program
     shader_flags: u8,
     name: String,
     uniforms: HashMap<String, UniformEntryType>,
@@ -72,6 +84,23 @@ impl Program {
         self.uniforms.get(&name.into())
     }
 
+    /// Active uniforms of the program, sorted by name
+    pub fn uniforms(&self) -> Vec<(&String, &UniformEntryType)> {
+        let mut uniforms: Vec<_> = self.uniforms.iter().collect();
+        uniforms.sort_by_key(|(name, _)| *name);
+        uniforms
+    }
+
+    /// Whether the program has an active vertex attribute `name`
+    pub fn has_attribute(&self, name: &str) -> bool {
+        let Ok(c_name) = CString::new(name) else {
+            return false;
+        };
+        let location = unsafe { gl::GetAttribLocation(self.id, c_name.as_ptr()) };
+        gl_check!();
+        location != -1
+    }
+
     pub fn id(&self) -> ProgramIdType {
         self.id
     }
@@ -81,6 +110,62 @@ impl Program {
     }
 }
 
+impl Program {
+    /// Compiles and links the program again from its shader files, keeping its uniforms' values.
+    ///
+    /// On failure, the program is left untouched.
+    pub fn reload(program: &ProgramSharedPointer) -> EngineResult<()> {
+        let (name, sources, saved_values) = {
+            let p = program.borrow();
+            let sources = p
+                .sources
+                .iter()
+                .map(|(name, shader)| shader.reloaded().map(|s| (name.clone(), s)))
+                .collect::<EngineResult<Vec<_>>>()?;
+            let saved_values: Vec<_> = p
+                .uniforms
+                .values()
+                .filter_map(|u| u.borrow().get().map(|v| (u.clone(), v)))
+                .collect();
+            (p.name.clone(), sources, saved_values)
+        };
+
+        let (id, handles, shader_flags) = ProgramBuilder::link(&name, &sources)?;
+
+        {
+            let mut p = program.borrow_mut();
+            let mut current_program: GLint = 0;
+            unsafe {
+                gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut current_program);
+                gl::DeleteProgram(p.id);
+                gl_check!();
+            }
+            if current_program == p.id as GLint {
+                unsafe { gl::UseProgram(id) };
+            }
+            p.id = id;
+            p._shaders = handles;
+            p.sources = sources;
+            p.shader_flags = shader_flags;
+            for uniform in p.uniforms.values() {
+                let mut uniform = uniform.borrow_mut();
+                let (value_type, size) = (uniform.value_type(), uniform.size());
+                uniform.relocate(-1, value_type, size);
+            }
+        }
+        ProgramBuilder::build_uniform_map(program);
+
+        for (uniform, value) in saved_values {
+            let mut uniform = uniform.borrow_mut();
+            if uniform.is_active() && uniform.accepts(&value) {
+                uniform.set(&value);
+            }
+        }
+        log::info!("program `{name}` reloaded");
+        Ok(())
+    }
+}
+
 impl Drop for Program {
     fn drop(&mut self) {
         // unbind program if current
@@ -89,7 +174,7 @@ impl Drop for Program {
             gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut current_program);
             gl_check!();
         }
-        if current_program == self.id as _ {
+        if current_program == self.id as GLint {
             Program::unbind();
             log::warn!(
                 "Unbinding current program number {id} (`{name}`) because of dropping",
@@ -111,20 +196,6 @@ pub struct ProgramBuilder {
     shaders: Vec<(String, Shader)>,
 }
 
-pub enum ProgramBuildError {
-    CreationFailed,
-    LinkFail(String),
-}
-
-impl ProgramBuildError {
-    pub fn log_error(&self) {
-        match self {
-            Self::CreationFailed => log::error!("program creation failed"),
-            Self::LinkFail(log) => log::error!("Program linking failed, link log:\n{log}"),
-        }
-    }
-}
-
 impl ProgramBuilder {
     pub fn add_shader(mut self, name: &str, shader: Shader) -> Self {
         self.shaders.push((name.into(), shader));
@@ -178,6 +249,11 @@ impl ProgramBuilder {
                     .into_owned()
             };
 
+            if let Some(uniform) = prog.uniforms.get(&name) {
+                uniform.borrow_mut().relocate(loc, uniform_type, size);
+                continue;
+            }
+
             prog.uniforms.insert(
                 name.clone(),
                 Rc::new(RefCell::new(Uniform::new(
@@ -185,53 +261,61 @@ impl ProgramBuilder {
                     loc,
                     uniform_type,
                     size,
-                    Rc::downgrade(&program),
+                    Rc::downgrade(program),
                 ))),
             );
         }
     }
 
-    #[must_use]
-    pub fn build<'a>(self) -> Result<ProgramSharedPointer, ProgramBuildError> {
+    /// Compiles the shaders and links them into a new program object.
+    ///
+    /// Returns the program's id, the shaders' handles, and the program's shader flags.
+    fn link(
+        name: &str,
+        shaders: &[(String, Shader)],
+    ) -> EngineResult<(ProgramIdType, Vec<ShaderHandle>, u8)> {
         unsafe {
             let program_id = gl::CreateProgram();
             gl_check!();
 
             if program_id == 0 {
-                return Err(ProgramBuildError::CreationFailed);
+                return Err(EngineError::Context(format!(
+                    "couldn't create program `{name}`"
+                )));
             }
 
             let mut shader_flags = 0u8;
-
-            let compiled_shaders: Vec<_> = self
-                .shaders
-                .into_iter()
-                .filter_map(|(name, shader)| {
-                    let mask = shader.shader_type().mask();
-                    if shader_flags & mask != 0 {
-                        log::warn!(
-                            "Program already has shader of type: `{}`",
-                            shader.shader_type().to_string()
-                        )
+            let mut compiled_shaders = Vec::with_capacity(shaders.len());
+
+            for (shader_name, shader) in shaders.iter() {
+                let mask = shader.shader_type().mask();
+                if shader_flags & mask != 0 {
+                    log::warn!(
+                        "Program already has shader of type: `{}`",
+                        shader.shader_type().to_string()
+                    )
+                }
+                shader_flags |= mask;
+                match shader.compile() {
+                    Ok(handle) => {
+                        gl_debug::label_object(
+                            gl::SHADER,
+                            handle.id,
+                            &format!("{name}/{shader_name}"),
+                        );
+                        compiled_shaders.push(handle)
                     }
-                    shader_flags |= mask;
-                    let compilation_result = shader.compile();
-                    match compilation_result {
-                        Err(error) => {
-                            match error {
-                                ShaderCompileError::CompilationError(log) => {
-                                    log::warn!(
-                                        "[{prog_name}]<{name}> Shader compilation error.\n---- LOG ----\n{log}",
-                                        prog_name = self.name
-                                    )
-                                }
-                            }
-                            None
-                        }
-                        Ok(handle) => Some( handle),
+                    Err(ShaderCompileError::CompilationError(log)) => {
+                        // already compiled shader handles are deleted when dropped
+                        gl::DeleteProgram(program_id);
+                        return Err(EngineError::ShaderCompile {
+                            program: name.to_owned(),
+                            shader: shader_name.clone(),
+                            log,
+                        });
                     }
-                })
-                .collect();
+                }
+            }
 
             for handle in &compiled_shaders {
                 gl::AttachShader(program_id, handle.id);
@@ -242,7 +326,7 @@ impl ProgramBuilder {
             gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut link_status);
             gl_check!();
 
-            if link_status != gl::TRUE as _ {
+            if link_status != gl::TRUE as GLint {
                 let mut log_size = 0;
                 gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, &mut log_size);
                 gl_check!();
@@ -256,20 +340,31 @@ impl ProgramBuilder {
                 gl_check!();
 
                 gl::DeleteProgram(program_id);
-                return Err(ProgramBuildError::LinkFail(
-                    program_log.to_string_lossy().into_owned(),
-                ));
+                return Err(EngineError::Link {
+                    program: name.to_owned(),
+                    log: program_log.to_string_lossy().into_owned(),
+                });
             }
 
-            let prog = Rc::new(RefCell::new(Program {
-                id: program_id,
-                shader_flags,
-                _shaders: compiled_shaders,
-                name: self.name,
-                uniforms: Default::default(),
-            }));
-            Self::build_uniform_map(&prog);
-            Ok(prog)
+            gl_debug::label_object(gl::PROGRAM, program_id, name);
+
+            Ok((program_id, compiled_shaders, shader_flags))
         }
     }
+
+    #[must_use]
+    pub fn build(self) -> EngineResult<ProgramSharedPointer> {
+        let (id, handles, shader_flags) = Self::link(&self.name, &self.shaders)?;
+
+        let prog = Rc::new(RefCell::new(Program {
+            id,
+            shader_flags,
+            _shaders: handles,
+            sources: self.shaders,
+            name: self.name,
+            uniforms: Default::default(),
+        }));
+        Self::build_uniform_map(&prog);
+        Ok(prog)
+    }
 }
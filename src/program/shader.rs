@@ -1,10 +1,14 @@
 use std::{
     ffi::CString,
-    fs, io,
+    fs,
     ptr::{null, null_mut},
 };
 
-use crate::{gl_check, gl_utils::cstring_with_null_bytes};
+use crate::{
+    error::{EngineError, EngineResult},
+    gl_check,
+    gl_utils::cstring_with_null_bytes,
+};
 
 #[derive(Clone, Copy, Debug)]
 #[allow(unused)]
@@ -55,9 +59,12 @@ impl ToString for ShaderType {
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct Shader {
     shader_type: ShaderType,
     sources: Vec<String>,
+    /// File each source has been loaded from, if any, used when reloading
+    files: Vec<Option<String>>,
 }
 
 #[derive(Debug)]
@@ -79,19 +86,35 @@ impl Shader {
         Shader {
             shader_type,
             sources: vec![],
+            files: vec![],
         }
     }
-    pub fn load(mut self, path: &str) -> io::Result<Self> {
-        self.sources.push(fs::read_to_string(path)?);
+    pub fn load(mut self, path: &str) -> EngineResult<Self> {
+        let source = fs::read_to_string(path).map_err(|e| EngineError::io(path, e))?;
+        self.sources.push(source);
+        self.files.push(Some(path.into()));
         Ok(self)
     }
 
     pub fn source(mut self, source: &str) -> Self {
         self.sources.push(source.into());
+        self.files.push(None);
         self
     }
 
-    pub fn compile(self) -> Result<ShaderHandle, ShaderCompileError> {
+    /// Creates a copy of the shader with its file sources read again from disk
+    pub fn reloaded(&self) -> EngineResult<Self> {
+        let mut shader = Shader::new(self.shader_type);
+        for (source, file) in self.sources.iter().zip(self.files.iter()) {
+            shader = match file {
+                Some(path) => shader.load(path)?,
+                None => shader.source(source),
+            };
+        }
+        Ok(shader)
+    }
+
+    pub fn compile(&self) -> Result<ShaderHandle, ShaderCompileError> {
         unsafe {
             let shader_id = gl::CreateShader(self.shader_type.gl_constant());
             gl_check!();
@@ -115,7 +138,7 @@ impl Shader {
 
             let mut compile_status: gl::types::GLint = gl::TRUE as _;
             gl::GetShaderiv(shader_id, gl::COMPILE_STATUS, &mut compile_status);
-            if compile_status != gl::TRUE as _ {
+            if compile_status != gl::TRUE as gl::types::GLint {
                 let mut log_size: gl::types::GLint = 0;
                 gl::GetShaderiv(shader_id, gl::INFO_LOG_LENGTH, &mut log_size);
                 gl_check!();
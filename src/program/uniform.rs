@@ -1,6 +1,10 @@
-use std::{cell::RefCell, fmt::Debug, rc::Weak};
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+    rc::Weak,
+};
 
-use cgmath::{Matrix4, Vector3, Vector4};
+use cgmath::{Matrix4, Vector2, Vector3, Vector4};
 
 use crate::gl_check;
 
@@ -24,10 +28,106 @@ pub fn stringify_type(value_type: TypeEnum) -> String {
         gl::INT => "int".to_owned(),
         gl::UNSIGNED_INT => "uint".to_owned(),
         gl::SAMPLER_2D => "sampler2D".to_owned(),
+        gl::SAMPLER_2D_ARRAY_SHADOW => "sampler2DArrayShadow".to_owned(),
         _ => format!("<unknown type 0x{value_type:x} ({value_type})>"),
     }
 }
 
+/// Value of a uniform, for the types the engine knows how to set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformValue {
+    Float(GLfloat),
+    Vec2(Vector2<GLfloat>),
+    Vec3(Vector3<GLfloat>),
+    Vec4(Vector4<GLfloat>),
+    Int(GLint),
+    UInt(GLuint),
+    Mat4(Matrix4<GLfloat>),
+}
+
+impl UniformValue {
+    /// Parses a value of the OpenGL type `value_type` from its components, matrices being column major
+    pub fn parse(value_type: TypeEnum, components: &[&str]) -> Result<Self, String> {
+        let expected = match value_type {
+            gl::FLOAT | gl::INT | gl::SAMPLER_2D | gl::UNSIGNED_INT => 1,
+            gl::FLOAT_VEC2 => 2,
+            gl::FLOAT_VEC3 => 3,
+            gl::FLOAT_VEC4 => 4,
+            gl::FLOAT_MAT4 => 16,
+            _ => {
+                return Err(format!(
+                    "uniforms of type {} can't be set",
+                    stringify_type(value_type)
+                ))
+            }
+        };
+        if components.len() != expected {
+            return Err(format!(
+                "{} expects {expected} component(s), got {}",
+                stringify_type(value_type),
+                components.len()
+            ));
+        }
+
+        let parse_error = |c: &str| format!("invalid component `{c}`");
+        match value_type {
+            gl::INT | gl::SAMPLER_2D => components[0]
+                .parse()
+                .map(Self::Int)
+                .map_err(|_| parse_error(components[0])),
+            gl::UNSIGNED_INT => components[0]
+                .parse()
+                .map(Self::UInt)
+                .map_err(|_| parse_error(components[0])),
+            _ => {
+                let floats = components
+                    .iter()
+                    .map(|c| c.parse::<GLfloat>().map_err(|_| parse_error(c)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(match value_type {
+                    gl::FLOAT => Self::Float(floats[0]),
+                    gl::FLOAT_VEC2 => Self::Vec2(Vector2::new(floats[0], floats[1])),
+                    gl::FLOAT_VEC3 => Self::Vec3(Vector3::new(floats[0], floats[1], floats[2])),
+                    gl::FLOAT_VEC4 => {
+                        Self::Vec4(Vector4::new(floats[0], floats[1], floats[2], floats[3]))
+                    }
+                    _ => {
+                        let array: &[GLfloat; 16] = floats.as_slice().try_into().unwrap();
+                        Self::Mat4(*<&Matrix4<GLfloat>>::from(array))
+                    }
+                })
+            }
+        }
+    }
+
+    /// Components of the value, matrices being column major
+    pub fn components(&self) -> Vec<f64> {
+        match self {
+            Self::Float(v) => vec![*v as _],
+            Self::Vec2(v) => vec![v.x as _, v.y as _],
+            Self::Vec3(v) => vec![v.x as _, v.y as _, v.z as _],
+            Self::Vec4(v) => vec![v.x as _, v.y as _, v.z as _, v.w as _],
+            Self::Int(v) => vec![*v as _],
+            Self::UInt(v) => vec![*v as _],
+            Self::Mat4(m) => AsRef::<[GLfloat; 16]>::as_ref(m)
+                .iter()
+                .map(|v| *v as _)
+                .collect(),
+        }
+    }
+}
+
+impl Display for UniformValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let components = self
+            .components()
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", components.join(" "))
+    }
+}
+
 pub struct Uniform {
     location: LocType,
     size: SizeType,
@@ -53,10 +153,41 @@ impl Uniform {
         }
     }
 
+    /// Updates the uniform after its program has been linked again
+    pub(super) fn relocate(&mut self, location: LocType, value_type: TypeEnum, size: SizeType) {
+        self.location = location;
+        self.value_type = value_type;
+        self.size = size;
+    }
+
+    /// Whether the uniform is still active in its program, uniforms may be removed when reloading
+    pub fn is_active(&self) -> bool {
+        self.location != -1
+    }
+
+    /// Whether `value` has the right type to be set to this uniform
+    pub fn accepts(&self, value: &UniformValue) -> bool {
+        match value {
+            UniformValue::Float(_) => self.value_type == gl::FLOAT,
+            UniformValue::Vec2(_) => self.value_type == gl::FLOAT_VEC2,
+            UniformValue::Vec3(_) => self.value_type == gl::FLOAT_VEC3,
+            UniformValue::Vec4(_) => self.value_type == gl::FLOAT_VEC4,
+            UniformValue::Int(_) => {
+                self.value_type == gl::INT || self.value_type == gl::SAMPLER_2D
+            }
+            UniformValue::UInt(_) => self.value_type == gl::UNSIGNED_INT,
+            UniformValue::Mat4(_) => self.value_type == gl::FLOAT_MAT4,
+        }
+    }
+
     fn program_id(&self) -> ProgramIdType {
         self.program.upgrade().unwrap().borrow().id
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn size(&self) -> SizeType {
         self.size
     }
@@ -95,6 +226,16 @@ impl Uniform {
         }
     }
 
+    pub fn set_vec2(&mut self, vec: &Vector2<f32>) {
+        if self.value_type != gl::FLOAT_VEC2 {
+            self.type_error("vec2");
+        }
+        unsafe {
+            gl::ProgramUniform2f(self.program_id(), self.location, vec.x, vec.y);
+            gl_check!();
+        }
+    }
+
     pub fn set_vec3(&mut self, vec: &Vector3<f32>) {
         if self.value_type != gl::FLOAT_VEC3 {
             self.type_error("vec3");
@@ -135,6 +276,62 @@ impl Uniform {
     }
 }
 
+impl Uniform {
+    /// Reads the current value of the uniform from its program.
+    ///
+    /// Returns `None` if the uniform's type is not one of [`UniformValue`]'s.
+    pub fn get(&self) -> Option<UniformValue> {
+        if !self.is_active() {
+            return None;
+        }
+        let program_id = self.program_id();
+        let mut floats = [0 as GLfloat; 16];
+        let mut int: GLint = 0;
+        let mut uint: GLuint = 0;
+        unsafe {
+            match self.value_type {
+                gl::INT | gl::SAMPLER_2D => {
+                    gl::GetUniformiv(program_id, self.location, &mut int)
+                }
+                gl::UNSIGNED_INT => gl::GetUniformuiv(program_id, self.location, &mut uint),
+                _ => gl::GetnUniformfv(
+                    program_id,
+                    self.location,
+                    std::mem::size_of_val(&floats) as _,
+                    floats.as_mut_ptr(),
+                ),
+            }
+            gl_check!();
+        }
+        let value = match self.value_type {
+            gl::FLOAT => UniformValue::Float(floats[0]),
+            gl::FLOAT_VEC2 => UniformValue::Vec2(Vector2::new(floats[0], floats[1])),
+            gl::FLOAT_VEC3 => UniformValue::Vec3(Vector3::new(floats[0], floats[1], floats[2])),
+            gl::FLOAT_VEC4 => {
+                UniformValue::Vec4(Vector4::new(floats[0], floats[1], floats[2], floats[3]))
+            }
+            gl::FLOAT_MAT4 => UniformValue::Mat4(*<&Matrix4<GLfloat>>::from(&floats)),
+            gl::INT | gl::SAMPLER_2D => UniformValue::Int(int),
+            gl::UNSIGNED_INT => UniformValue::UInt(uint),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Sets the uniform from a dynamically typed value, the value's type must match the uniform's.
+    pub fn set(&mut self, value: &UniformValue) {
+        match value {
+            UniformValue::Float(v) => self.set_float(*v),
+            UniformValue::Vec2(v) => self.set_vec2(v),
+            UniformValue::Vec3(v) => self.set_vec3(v),
+            UniformValue::Vec4(v) => self.set_vec4(v),
+            UniformValue::Int(v) => self.set_int(*v),
+            UniformValue::UInt(v) => self.set_uint(*v),
+            UniformValue::Mat4(v) => self.set_mat4(v),
+        }
+    }
+}
+
 impl Debug for Uniform {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Uniform")
@@ -0,0 +1,251 @@
+use std::{fmt::Display, str::FromStr};
+
+use cgmath::{ortho, Matrix4, PerspectiveFov, Rad, Vector4};
+use clap::ValueEnum;
+use gl::types::GLfloat;
+use serde::{Deserialize, Serialize};
+
+use crate::{definitions, gl_checked};
+
+const MIN_FOV: f32 = 0.01;
+const MAX_FOV: f32 = std::f32::consts::PI - 0.01;
+const MIN_NEAR: f32 = 1e-4;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectionKind {
+    /// Objects get smaller with the distance, within the field of view
+    #[default]
+    Perspective,
+    /// Objects keep their size, within a box of fixed height
+    Orthographic,
+}
+
+impl Display for ProjectionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Perspective => write!(f, "perspective"),
+            Self::Orthographic => write!(f, "orthographic"),
+        }
+    }
+}
+
+impl FromStr for ProjectionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perspective" => Ok(Self::Perspective),
+            "orthographic" => Ok(Self::Orthographic),
+            _ => Err(format!(
+                "unknown projection `{s}`, expected `perspective` or `orthographic`"
+            )),
+        }
+    }
+}
+
+/// Parameters of a camera's projection, the aspect ratio following the viewport
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Projection {
+    #[serde(rename = "projection")]
+    pub kind: ProjectionKind,
+    /// Vertical field of view in radians, of perspective projections
+    pub fov: GLfloat,
+    /// Height of the view box in world units, of orthographic projections
+    pub ortho_height: GLfloat,
+    pub near: GLfloat,
+    /// Far clip plane, ignored by reversed-Z perspective projections whose far plane is at infinity
+    pub far: GLfloat,
+    /// Maps the near plane to depth 1 and the far one to 0, for a more even depth precision
+    pub reversed_z: bool,
+    /// Width to height ratio of the view
+    #[serde(skip)]
+    pub aspect_ratio: GLfloat,
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self {
+            kind: Default::default(),
+            fov: definitions::DEFAULT_FOV,
+            ortho_height: definitions::DEFAULT_ORTHO_HEIGHT,
+            near: definitions::DEFAULT_ZNEAR,
+            far: definitions::DEFAULT_ZFAR,
+            reversed_z: false,
+            aspect_ratio: definitions::DEFAULT_ASPECT_RATIO,
+        }
+    }
+}
+
+impl Projection {
+    pub const NAMES: [&'static str; 6] = [
+        "projection",
+        "fov",
+        "ortho_height",
+        "near",
+        "far",
+        "reversed_z",
+    ];
+
+    /// Value of the named parameter as text
+    pub fn get(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "projection" => self.kind.to_string(),
+            "fov" => self.fov.to_string(),
+            "ortho_height" => self.ortho_height.to_string(),
+            "near" => self.near.to_string(),
+            "far" => self.far.to_string(),
+            "reversed_z" => self.reversed_z.to_string(),
+            _ => return None,
+        })
+    }
+
+    /// Parses and sets the named parameter
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let float = || -> Result<f32, String> {
+            value
+                .parse()
+                .map_err(|_| format!("invalid number `{value}`"))
+        };
+        match name {
+            "projection" => self.kind = value.parse()?,
+            "fov" => self.fov = float()?,
+            "ortho_height" => self.ortho_height = float()?,
+            "near" => self.near = float()?,
+            "far" => self.far = float()?,
+            "reversed_z" => {
+                self.reversed_z = value.parse().map_err(|_| "expected `true` or `false`")?
+            }
+            _ => return Err(format!("unknown projection parameter `{name}`")),
+        }
+        self.sanitize();
+        Ok(())
+    }
+
+    /// Keeps the parameters in ranges giving a valid matrix
+    pub fn sanitize(&mut self) {
+        self.fov = self.fov.clamp(MIN_FOV, MAX_FOV);
+        self.ortho_height = self.ortho_height.max(MIN_NEAR);
+        self.near = self.near.max(MIN_NEAR);
+        self.far = self.far.max(self.near * 2.);
+    }
+
+    pub fn matrix(&self) -> Matrix4<GLfloat> {
+        match (self.kind, self.reversed_z) {
+            (ProjectionKind::Perspective, false) => Matrix4::from(PerspectiveFov {
+                aspect: self.aspect_ratio,
+                fovy: Rad(self.fov),
+                near: self.near,
+                far: self.far,
+            }),
+            (ProjectionKind::Perspective, true) => {
+                // depth = near / distance, reaching 0 at infinity
+                let f = 1. / (self.fov / 2.).tan();
+                Matrix4::from_cols(
+                    Vector4::new(f / self.aspect_ratio, 0., 0., 0.),
+                    Vector4::new(0., f, 0., 0.),
+                    Vector4::new(0., 0., 0., -1.),
+                    Vector4::new(0., 0., self.near, 0.),
+                )
+            }
+            (ProjectionKind::Orthographic, reversed_z) => {
+                let top = self.ortho_height / 2.;
+                let right = top * self.aspect_ratio;
+                if !reversed_z {
+                    return ortho(-right, right, -top, top, self.near, self.far);
+                }
+                let depth = self.far - self.near;
+                Matrix4::from_cols(
+                    Vector4::new(1. / right, 0., 0., 0.),
+                    Vector4::new(0., 1. / top, 0., 0.),
+                    Vector4::new(0., 0., 1. / depth, 0.),
+                    Vector4::new(0., 0., self.far / depth, 1.),
+                )
+            }
+        }
+    }
+
+    /// Sets the depth range, test and clear value matching the projection's depth convention
+    pub fn apply_depth_convention(&self) {
+        let (range, func, clear) = if self.reversed_z {
+            (gl::ZERO_TO_ONE, gl::GREATER, 0.)
+        } else {
+            (gl::NEGATIVE_ONE_TO_ONE, gl::LESS, 1.)
+        };
+        unsafe {
+            gl_checked! {
+                gl::ClipControl(gl::LOWER_LEFT, range);
+                gl::DepthFunc(func);
+                gl::ClearDepth(clear);
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn projection(kind: ProjectionKind, reversed_z: bool) -> Projection {
+        Projection {
+            kind,
+            near: 0.5,
+            far: 100.,
+            reversed_z,
+            aspect_ratio: 16. / 9.,
+            ..Default::default()
+        }
+    }
+
+    /// Normalized device depth of the point `distance` units in front of the camera
+    fn depth(projection: &Projection, distance: GLfloat) -> GLfloat {
+        let clip = projection.matrix() * Vector4::new(0., 0., -distance, 1.);
+        clip.z / clip.w
+    }
+
+    fn assert_close(value: GLfloat, expected: GLfloat) {
+        assert!(
+            (value - expected).abs() < 1e-4,
+            "{value} isn't close to {expected}"
+        );
+    }
+
+    #[test]
+    fn depth_goes_from_minus_one_to_one() {
+        for kind in [ProjectionKind::Perspective, ProjectionKind::Orthographic] {
+            let projection = projection(kind, false);
+            assert_close(depth(&projection, 0.5), -1.);
+            assert_close(depth(&projection, 100.), 1.);
+        }
+    }
+
+    #[test]
+    fn reversed_perspective_depth_goes_from_one_to_zero_at_infinity() {
+        let projection = projection(ProjectionKind::Perspective, true);
+        assert_close(depth(&projection, 0.5), 1.);
+        assert_close(depth(&projection, 1.), 0.5);
+        // the far plane is ignored
+        assert!(depth(&projection, 1000.) > 0.);
+        assert_close(depth(&projection, 1e7), 0.);
+    }
+
+    #[test]
+    fn reversed_orthographic_depth_goes_from_one_to_zero() {
+        let projection = projection(ProjectionKind::Orthographic, true);
+        assert_close(depth(&projection, 0.5), 1.);
+        assert_close(depth(&projection, 100.), 0.);
+    }
+
+    #[test]
+    fn reversed_z_keeps_the_field_of_view() {
+        let normal = projection(ProjectionKind::Perspective, false);
+        let reversed = projection(ProjectionKind::Perspective, true);
+        // the top edge of the view, at half the field of view above the axis
+        let point = Vector4::new(0., 10. * (normal.fov / 2.).tan(), -10., 1.);
+        for projection in [normal, reversed] {
+            let clip = projection.matrix() * point;
+            assert_close(clip.y / clip.w, 1.);
+        }
+    }
+}
@@ -0,0 +1,676 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::PathBuf,
+};
+
+#[cfg(unix)]
+use std::os::unix::{
+    fs::FileTypeExt,
+    net::{UnixListener, UnixStream},
+};
+
+use cgmath::Point3;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    commands,
+    engine::Engine,
+    error::{EngineError, EngineResult},
+    program::{
+        uniform::{stringify_type, UniformValue},
+        Program,
+    },
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Error raised by the engine while executing a valid request
+const ENGINE_ERROR: i64 = -32000;
+
+/// Longest request line, a connection sending more without a line break is closed
+const MAX_LINE_LENGTH: usize = 256 * 1024;
+/// Bytes read from a connection per poll, so that a client sending continuously doesn't stall
+/// the frame
+const MAX_READ_PER_POLL: usize = 64 * 1024;
+
+/// Address the remote control server listens on
+#[derive(Clone, Debug)]
+pub enum RemoteEndpoint {
+    #[cfg(unix)]
+    Unix(PathBuf),
+    /// Port on the loopback interface
+    Tcp(u16),
+}
+
+enum Listener {
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+    Tcp(TcpListener),
+}
+
+enum Stream {
+    #[cfg(unix)]
+    Unix(UnixStream),
+    Tcp(TcpStream),
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read(buf),
+            Self::Tcp(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+            Self::Tcp(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+            Self::Tcp(stream) => stream.flush(),
+        }
+    }
+}
+
+struct Connection {
+    id: u64,
+    stream: Stream,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    closed: bool,
+}
+
+impl Connection {
+    /// Reads what is available without blocking, returns the complete lines received
+    fn receive(&mut self) -> Vec<String> {
+        let mut buffer = [0u8; 4096];
+        let mut read = 0;
+        while read < MAX_READ_PER_POLL {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(n) => {
+                    self.input.extend_from_slice(&buffer[..n]);
+                    read += n;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    log::warn!("remote connection {} read error: {e}", self.id);
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+
+        let mut lines = Vec::new();
+        while let Some(end) = self.input.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.input.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_owned();
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+        if self.input.len() > MAX_LINE_LENGTH {
+            log::warn!(
+                "remote connection {} sent a line longer than {MAX_LINE_LENGTH} bytes",
+                self.id
+            );
+            self.input.clear();
+            self.closed = true;
+        }
+        lines
+    }
+
+    /// Writes as much of the pending output as possible without blocking
+    fn send(&mut self) {
+        while !self.output.is_empty() {
+            match self.stream.write(&self.output) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(n) => {
+                    self.output.drain(..n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    log::warn!("remote connection {} write error: {e}", self.id);
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Non-blocking JSON-RPC 2.0 server, one request or response object per line.
+///
+/// Sockets are only polled by [`RemoteServer::poll`], so requests are executed on the main thread.
+pub struct RemoteServer {
+    listener: Listener,
+    connections: Vec<Connection>,
+    next_id: u64,
+}
+
+impl RemoteServer {
+    pub fn bind(endpoint: &RemoteEndpoint) -> EngineResult<Self> {
+        let listener = match endpoint {
+            #[cfg(unix)]
+            RemoteEndpoint::Unix(path) => {
+                // a socket file left by a previous instance would make binding fail, anything
+                // else at the path is kept
+                match fs::symlink_metadata(path) {
+                    Ok(metadata) if metadata.file_type().is_socket() => {
+                        if UnixStream::connect(path).is_err() {
+                            let _ = fs::remove_file(path);
+                        }
+                    }
+                    Ok(_) => {
+                        return Err(EngineError::io(
+                            path,
+                            io::Error::new(io::ErrorKind::AlreadyExists, "not a socket"),
+                        ))
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(EngineError::io(path, e)),
+                }
+                let listener = UnixListener::bind(path).map_err(|e| EngineError::io(path, e))?;
+                listener
+                    .set_nonblocking(true)
+                    .map_err(|e| EngineError::io(path, e))?;
+                Listener::Unix(listener, path.clone())
+            }
+            RemoteEndpoint::Tcp(port) => {
+                let address = (Ipv4Addr::LOCALHOST, *port);
+                let listener = TcpListener::bind(address)
+                    .and_then(|l| l.set_nonblocking(true).map(|_| l))
+                    .map_err(|e| EngineError::io(format!("localhost:{port}"), e))?;
+                Listener::Tcp(listener)
+            }
+        };
+        log::info!("remote control listening on {endpoint:?}");
+        Ok(Self {
+            listener,
+            connections: Vec::new(),
+            next_id: 0,
+        })
+    }
+
+    fn accept(&mut self) {
+        loop {
+            let accepted = match &self.listener {
+                #[cfg(unix)]
+                Listener::Unix(listener, _) => listener.accept().and_then(|(stream, _)| {
+                    stream.set_nonblocking(true)?;
+                    Ok(Stream::Unix(stream))
+                }),
+                Listener::Tcp(listener) => listener.accept().and_then(|(stream, _)| {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    Ok(Stream::Tcp(stream))
+                }),
+            };
+            match accepted {
+                Ok(stream) => {
+                    log::debug!("remote connection {} opened", self.next_id);
+                    self.connections.push(Connection {
+                        id: self.next_id,
+                        stream,
+                        input: Vec::new(),
+                        output: Vec::new(),
+                        closed: false,
+                    });
+                    self.next_id += 1;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::warn!("remote control couldn't accept a connection: {e}");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Accepts new connections and returns the request lines received since the last call,
+    /// with the id of the connection they came from.
+    pub fn poll(&mut self) -> Vec<(u64, String)> {
+        self.accept();
+        let mut requests = Vec::new();
+        for connection in self.connections.iter_mut() {
+            requests.extend(connection.receive().into_iter().map(|l| (connection.id, l)));
+        }
+        requests
+    }
+
+    /// Queues a response to a connection, sent by the next [`RemoteServer::flush`]
+    pub fn respond(&mut self, connection: u64, response: &Value) {
+        if let Some(connection) = self.connections.iter_mut().find(|c| c.id == connection) {
+            connection.output.extend(response.to_string().into_bytes());
+            connection.output.push(b'\n');
+        }
+    }
+
+    /// Sends the queued responses and forgets closed connections
+    pub fn flush(&mut self) {
+        for connection in self.connections.iter_mut() {
+            connection.send();
+        }
+        self.connections.retain(|c| {
+            if c.closed {
+                log::debug!("remote connection {} closed", c.id);
+            }
+            !c.closed
+        });
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix(_, path) = &self.listener {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn engine(message: impl Into<String>) -> Self {
+        Self::new(ENGINE_ERROR, message)
+    }
+}
+
+type RpcResult = Result<Value, RpcError>;
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    // omitted params are accepted for methods whose parameters are all optional
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+#[derive(Deserialize)]
+struct ProgramParams {
+    program: String,
+}
+
+#[derive(Deserialize)]
+struct ReloadParams {
+    program: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UniformParams {
+    program: String,
+    uniform: String,
+}
+
+#[derive(Deserialize)]
+struct SetUniformParams {
+    program: String,
+    uniform: String,
+    /// A number, or an array of numbers for vectors and column major matrices
+    value: Value,
+}
+
+#[derive(Deserialize)]
+struct SetCameraParams {
+    position: Option<[f32; 3]>,
+    pitch: Option<f32>,
+    yaw: Option<f32>,
+    mode: Option<String>,
+    target: Option<[f32; 3]>,
+    distance: Option<f32>,
+    /// Projection parameters to change, by name
+    projection: Option<Map<String, Value>>,
+    /// Camera settings to change, by name
+    settings: Option<Map<String, Value>>,
+}
+
+#[derive(Deserialize)]
+struct SetParticlesParams {
+    name: String,
+    time_scale: Option<f32>,
+    paused: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct PauseParams {
+    paused: bool,
+}
+
+#[derive(Deserialize)]
+struct StepParams {
+    #[serde(default = "one")]
+    frames: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+struct ScreenshotParams {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct CommandParams {
+    line: String,
+}
+
+fn uniform_json(name: &str, value_type: u32, value: Option<UniformValue>) -> Value {
+    json!({
+        "name": name,
+        "type": stringify_type(value_type),
+        "value": value.map(|v| v.components()),
+    })
+}
+
+fn list_uniforms(engine: &Engine, params: ProgramParams) -> RpcResult {
+    let program = engine
+        .programs()
+        .get(&params.program)
+        .ok_or_else(|| RpcError::engine(format!("unknown program `{}`", params.program)))?
+        .borrow();
+    let uniforms: Vec<Value> = program
+        .uniforms()
+        .iter()
+        .map(|(name, uniform)| {
+            let uniform = uniform.borrow();
+            uniform_json(name, uniform.value_type(), uniform.get())
+        })
+        .collect();
+    Ok(Value::Array(uniforms))
+}
+
+fn get_uniform(engine: &Engine, params: UniformParams) -> RpcResult {
+    let program = engine
+        .programs()
+        .get(&params.program)
+        .ok_or_else(|| RpcError::engine(format!("unknown program `{}`", params.program)))?
+        .borrow();
+    let uniform = program
+        .uniform(&params.uniform)
+        .ok_or_else(|| RpcError::engine(format!("unknown uniform `{}`", params.uniform)))?
+        .borrow();
+    Ok(uniform_json(
+        &params.uniform,
+        uniform.value_type(),
+        uniform.get(),
+    ))
+}
+
+fn set_uniform(engine: &Engine, params: SetUniformParams) -> RpcResult {
+    let program = engine
+        .programs()
+        .get(&params.program)
+        .ok_or_else(|| RpcError::engine(format!("unknown program `{}`", params.program)))?
+        .borrow();
+    let mut uniform = program
+        .uniform(&params.uniform)
+        .ok_or_else(|| RpcError::engine(format!("unknown uniform `{}`", params.uniform)))?
+        .borrow_mut();
+
+    let numbers = match params.value {
+        Value::Array(values) => values,
+        value => vec![value],
+    };
+    let components = numbers
+        .iter()
+        .map(|n| match n {
+            Value::Number(n) => Ok(n.to_string()),
+            _ => Err(RpcError::new(
+                INVALID_PARAMS,
+                "`value` must contain numbers",
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let components: Vec<&str> = components.iter().map(String::as_str).collect();
+    let value = UniformValue::parse(uniform.value_type(), &components)
+        .map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+    uniform.set(&value);
+    Ok(json!(value.components()))
+}
+
+fn camera_json(engine: &Engine) -> RpcResult {
+    let camera = engine
+        .main_camera()
+        .ok_or_else(|| RpcError::engine("no camera"))?
+        .borrow();
+    let position = camera.position();
+    let target = camera.target();
+    Ok(json!({
+        "position": [position.x, position.y, position.z],
+        "pitch": camera.pitch(),
+        "yaw": camera.yaw(),
+        "mode": camera.mode().to_string(),
+        "target": [target.x, target.y, target.z],
+        "distance": camera.distance(),
+        "projection": camera.projection(),
+        "settings": camera.settings(),
+    }))
+}
+
+fn set_camera(engine: &Engine, params: SetCameraParams) -> RpcResult {
+    {
+        let mut camera = engine
+            .main_camera()
+            .ok_or_else(|| RpcError::engine("no camera"))?
+            .borrow_mut();
+        if let Some(mode) = params.mode {
+            camera.set_mode(mode.parse().map_err(|e| RpcError::new(INVALID_PARAMS, e))?);
+        }
+        if let Some(position) = params.position {
+            camera.set_position(Point3::from(position));
+        }
+        if let Some(pitch) = params.pitch {
+            camera.set_pitch(pitch);
+        }
+        if let Some(yaw) = params.yaw {
+            camera.set_yaw(yaw);
+        }
+        if let Some(target) = params.target {
+            camera.set_target(Point3::from(target));
+        }
+        if let Some(distance) = params.distance {
+            camera.set_distance(distance);
+        }
+        if let Some(parameters) = params.projection {
+            let mut projection = *camera.projection();
+            for (name, value) in parameters {
+                let value = value
+                    .as_str()
+                    .map_or_else(|| value.to_string(), str::to_owned);
+                projection
+                    .set(&name, &value)
+                    .map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+            }
+            camera.set_projection(projection);
+        }
+        for (name, value) in params.settings.unwrap_or_default() {
+            camera
+                .settings_mut()
+                .set(&name, &value.to_string())
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+        }
+    }
+    camera_json(engine)
+}
+
+fn list_particles(engine: &Engine) -> RpcResult {
+    let systems: Vec<Value> = engine
+        .particle_systems()
+        .iter()
+        .map(|s| {
+            let s = s.borrow();
+            json!({
+                "name": s.name(),
+                "particle_count": s.particle_count(),
+                "time_scale": s.time_scale(),
+                "paused": s.is_paused(),
+            })
+        })
+        .collect();
+    Ok(Value::Array(systems))
+}
+
+fn set_particles(engine: &Engine, params: SetParticlesParams) -> RpcResult {
+    let system = engine
+        .particle_system(&params.name)
+        .ok_or_else(|| RpcError::engine(format!("unknown particle system `{}`", params.name)))?;
+    let mut system = system.borrow_mut();
+    if let Some(time_scale) = params.time_scale {
+        system.set_time_scale(time_scale);
+    }
+    if let Some(paused) = params.paused {
+        system.set_paused(paused);
+    }
+    Ok(json!({
+        "name": system.name(),
+        "time_scale": system.time_scale(),
+        "paused": system.is_paused(),
+    }))
+}
+
+fn reload_programs(engine: &Engine, params: ReloadParams) -> RpcResult {
+    let programs: Vec<_> = match params.program.as_ref() {
+        Some(name) => vec![engine
+            .programs()
+            .get_key_value(name)
+            .ok_or_else(|| RpcError::engine(format!("unknown program `{name}`")))?],
+        None => engine.programs().iter().collect(),
+    };
+    let mut reloaded = Vec::new();
+    for (name, program) in programs {
+        Program::reload(program).map_err(|e| RpcError::engine(e.chain()))?;
+        reloaded.push(name.clone());
+    }
+    Ok(json!(reloaded))
+}
+
+fn status(engine: &Engine) -> RpcResult {
+    let statistics = engine.statistics().borrow();
+    Ok(json!({
+        "paused": engine.is_paused(),
+        "fps": statistics.fps(),
+        "frame_time_ms": statistics.frame_time_ms(),
+        "draw_calls": statistics.render.draw_calls,
+        "drawables": statistics.render.drawables,
+        "culled": statistics.render.culled,
+        "particles": statistics.render.particles,
+    }))
+}
+
+fn call(engine: &mut Engine, method: &str, p: Value) -> RpcResult {
+    match method {
+        "engine.status" => status(engine),
+        "engine.pause" => {
+            let params: PauseParams = params(p)?;
+            engine.set_paused(params.paused);
+            Ok(json!({ "paused": engine.is_paused() }))
+        }
+        "engine.step" => {
+            let params: StepParams = params(p)?;
+            engine.step(params.frames);
+            Ok(json!({ "frames": params.frames }))
+        }
+        "engine.screenshot" => {
+            let params: ScreenshotParams = params(p)?;
+            engine.request_screenshot(params.path.clone());
+            Ok(json!({ "path": params.path }))
+        }
+        "engine.command" => {
+            let params: CommandParams = params(p)?;
+            commands::execute(engine, &params.line)
+                .map(|output| json!({ "output": output }))
+                .map_err(RpcError::engine)
+        }
+        "programs.list" => {
+            let mut names: Vec<_> = engine.programs().keys().cloned().collect();
+            names.sort();
+            Ok(json!(names))
+        }
+        "programs.reload" => reload_programs(engine, params(p)?),
+        "uniforms.list" => list_uniforms(engine, params(p)?),
+        "uniforms.get" => get_uniform(engine, params(p)?),
+        "uniforms.set" => set_uniform(engine, params(p)?),
+        "camera.get" => camera_json(engine),
+        "camera.set" => set_camera(engine, params(p)?),
+        "particles.list" => list_particles(engine),
+        "particles.set" => set_particles(engine, params(p)?),
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method `{method}`"),
+        )),
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+/// Executes a JSON-RPC request line, returns the response or `None` for notifications.
+pub fn handle_request(engine: &mut Engine, line: &str) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            ))
+        }
+    };
+
+    let id = request.get("id").cloned();
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return Some(error_response(
+            id.unwrap_or(Value::Null),
+            RpcError::new(INVALID_REQUEST, "missing `method`"),
+        ));
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = call(engine, method, params);
+    if let Err(error) = &result {
+        log::debug!("remote call `{method}` failed: {}", error.message);
+    }
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => error_response(id, error),
+    })
+}
@@ -0,0 +1,362 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use sdl2::{
+    controller::{Axis, Button},
+    event::{Event, WindowEvent},
+    keyboard::{Keycode, Mod},
+    mouse::{MouseButton, MouseState, MouseWheelDirection},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{EngineError, EngineResult};
+
+const FORMAT_VERSION: u32 = 2;
+
+/// Input event in a form that can be saved, with only the fields the engine reads
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedEvent {
+    Quit,
+    KeyDown {
+        keycode: i32,
+        keymod: u16,
+        repeat: bool,
+    },
+    KeyUp {
+        keycode: i32,
+        keymod: u16,
+    },
+    TextInput {
+        text: String,
+    },
+    MouseMotion {
+        x: i32,
+        y: i32,
+        xrel: i32,
+        yrel: i32,
+    },
+    MouseButtonDown {
+        button: u8,
+        x: i32,
+        y: i32,
+    },
+    MouseButtonUp {
+        button: u8,
+        x: i32,
+        y: i32,
+    },
+    MouseWheel {
+        x: i32,
+        y: i32,
+    },
+    ControllerAxis {
+        axis: String,
+        value: i16,
+    },
+    ControllerButtonDown {
+        button: String,
+    },
+    ControllerButtonUp {
+        button: String,
+    },
+    FocusGained,
+    FocusLost,
+}
+
+impl RecordedEvent {
+    /// The recorded form of an event, `None` for events the engine ignores
+    pub fn from_event(event: &Event) -> Option<Self> {
+        Some(match event {
+            Event::Quit { .. } => Self::Quit,
+            Event::KeyDown {
+                keycode: Some(keycode),
+                keymod,
+                repeat,
+                ..
+            } => Self::KeyDown {
+                keycode: *keycode as i32,
+                keymod: keymod.bits(),
+                repeat: *repeat,
+            },
+            Event::KeyUp {
+                keycode: Some(keycode),
+                keymod,
+                ..
+            } => Self::KeyUp {
+                keycode: *keycode as i32,
+                keymod: keymod.bits(),
+            },
+            Event::TextInput { text, .. } => Self::TextInput { text: text.clone() },
+            Event::MouseMotion {
+                x, y, xrel, yrel, ..
+            } => Self::MouseMotion {
+                x: *x,
+                y: *y,
+                xrel: *xrel,
+                yrel: *yrel,
+            },
+            Event::MouseButtonDown {
+                mouse_btn, x, y, ..
+            } => Self::MouseButtonDown {
+                button: *mouse_btn as u8,
+                x: *x,
+                y: *y,
+            },
+            Event::MouseButtonUp {
+                mouse_btn, x, y, ..
+            } => Self::MouseButtonUp {
+                button: *mouse_btn as u8,
+                x: *x,
+                y: *y,
+            },
+            // replayed wheel events are not flipped
+            Event::MouseWheel {
+                x, y, direction, ..
+            } => match direction {
+                MouseWheelDirection::Flipped => Self::MouseWheel { x: -x, y: -y },
+                _ => Self::MouseWheel { x: *x, y: *y },
+            },
+            Event::ControllerAxisMotion { axis, value, .. } => Self::ControllerAxis {
+                axis: axis.string(),
+                value: *value,
+            },
+            Event::ControllerButtonDown { button, .. } => Self::ControllerButtonDown {
+                button: button.string(),
+            },
+            Event::ControllerButtonUp { button, .. } => Self::ControllerButtonUp {
+                button: button.string(),
+            },
+            // the window keeps its live size while replaying, so resizes aren't recorded
+            Event::Window { win_event, .. } => match win_event {
+                WindowEvent::FocusGained => Self::FocusGained,
+                WindowEvent::FocusLost => Self::FocusLost,
+                _ => return None,
+            },
+            _ => return None,
+        })
+    }
+
+    /// Rebuilds the event as if it was sent to the window `window_id`
+    pub fn to_event(&self, window_id: u32) -> Option<Event> {
+        let timestamp = 0;
+        let window = |win_event| Event::Window {
+            timestamp,
+            window_id,
+            win_event,
+        };
+        Some(match self {
+            Self::Quit => Event::Quit { timestamp },
+            Self::KeyDown {
+                keycode,
+                keymod,
+                repeat,
+            } => Event::KeyDown {
+                timestamp,
+                window_id,
+                keycode: Some(Keycode::from_i32(*keycode)?),
+                scancode: None,
+                keymod: Mod::from_bits_truncate(*keymod),
+                repeat: *repeat,
+            },
+            Self::KeyUp { keycode, keymod } => Event::KeyUp {
+                timestamp,
+                window_id,
+                keycode: Some(Keycode::from_i32(*keycode)?),
+                scancode: None,
+                keymod: Mod::from_bits_truncate(*keymod),
+                repeat: false,
+            },
+            Self::TextInput { text } => Event::TextInput {
+                timestamp,
+                window_id,
+                text: text.clone(),
+            },
+            Self::MouseMotion { x, y, xrel, yrel } => Event::MouseMotion {
+                timestamp,
+                window_id,
+                which: 0,
+                mousestate: MouseState::from_sdl_state(0),
+                x: *x,
+                y: *y,
+                xrel: *xrel,
+                yrel: *yrel,
+            },
+            Self::MouseButtonDown { button, x, y } => Event::MouseButtonDown {
+                timestamp,
+                window_id,
+                which: 0,
+                mouse_btn: MouseButton::from_ll(*button),
+                clicks: 1,
+                x: *x,
+                y: *y,
+            },
+            Self::MouseButtonUp { button, x, y } => Event::MouseButtonUp {
+                timestamp,
+                window_id,
+                which: 0,
+                mouse_btn: MouseButton::from_ll(*button),
+                clicks: 1,
+                x: *x,
+                y: *y,
+            },
+            Self::MouseWheel { x, y } => Event::MouseWheel {
+                timestamp,
+                window_id,
+                which: 0,
+                x: *x,
+                y: *y,
+                direction: MouseWheelDirection::Normal,
+            },
+            Self::ControllerAxis { axis, value } => Event::ControllerAxisMotion {
+                timestamp,
+                which: 0,
+                axis: Axis::from_string(axis)?,
+                value: *value,
+            },
+            Self::ControllerButtonDown { button } => Event::ControllerButtonDown {
+                timestamp,
+                which: 0,
+                button: Button::from_string(button)?,
+            },
+            Self::ControllerButtonUp { button } => Event::ControllerButtonUp {
+                timestamp,
+                which: 0,
+                button: Button::from_string(button)?,
+            },
+            Self::FocusGained => window(WindowEvent::FocusGained),
+            Self::FocusLost => window(WindowEvent::FocusLost),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordingHeader {
+    version: u32,
+    /// Seed of the random number generators used while recording
+    seed: u64,
+}
+
+/// Input and time step of a single frame
+#[derive(Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub delta: f32,
+    pub events: Vec<RecordedEvent>,
+}
+
+/// Writes each frame's input events and time step to a file, one JSON object per line.
+pub struct Recorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    events: Vec<RecordedEvent>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, seed: u64) -> EngineResult<Self> {
+        let file = File::create(path).map_err(|e| EngineError::io(path, e))?;
+        let mut recorder = Self {
+            path: path.to_owned(),
+            writer: BufWriter::new(file),
+            events: Vec::new(),
+        };
+        let header = RecordingHeader {
+            version: FORMAT_VERSION,
+            seed,
+        };
+        recorder.write_line(&header)?;
+        log::info!("recording input to `{}`", path.display());
+        Ok(recorder)
+    }
+
+    fn write_line(&mut self, value: &impl Serialize) -> EngineResult<()> {
+        serde_json::to_writer(&mut self.writer, value)
+            .map_err(|e| EngineError::io(&self.path, e.into()))?;
+        self.writer
+            .write_all(b"\n")
+            .map_err(|e| EngineError::io(&self.path, e))
+    }
+
+    /// Adds an event to the current frame
+    pub fn record(&mut self, event: &Event) {
+        if let Some(event) = RecordedEvent::from_event(event) {
+            self.events.push(event);
+        }
+    }
+
+    /// Writes the current frame with its time step
+    pub fn end_frame(&mut self, delta: f32) -> EngineResult<()> {
+        let frame = RecordedFrame {
+            delta,
+            events: std::mem::take(&mut self.events),
+        };
+        self.write_line(&frame)
+    }
+
+    pub fn finish(mut self) -> EngineResult<()> {
+        self.writer
+            .flush()
+            .map_err(|e| EngineError::io(&self.path, e))?;
+        log::info!("input recording saved to `{}`", self.path.display());
+        Ok(())
+    }
+}
+
+/// Frames of a recording, fed to the engine instead of the live input and clock
+pub struct Replayer {
+    seed: u64,
+    frames: VecDeque<RecordedFrame>,
+    exit_at_end: bool,
+}
+
+impl Replayer {
+    pub fn load(path: &Path, exit_at_end: bool) -> EngineResult<Self> {
+        let content = fs::read_to_string(path).map_err(|e| EngineError::io(path, e))?;
+        // numbered from 1 like in editors, blank lines being skipped
+        let mut lines = (1..)
+            .zip(content.lines())
+            .filter(|(_, l)| !l.trim().is_empty());
+        let (header_line, header) = lines
+            .next()
+            .ok_or_else(|| EngineError::parse(path, 1, "empty recording"))?;
+        let header: RecordingHeader =
+            serde_json::from_str(header).map_err(|e| EngineError::json(path, header_line, &e))?;
+        if header.version != FORMAT_VERSION {
+            return Err(EngineError::parse(
+                path,
+                header_line,
+                format!("unsupported recording version {}", header.version),
+            ));
+        }
+        let frames = lines
+            .map(|(number, line)| {
+                serde_json::from_str(line).map_err(|e| EngineError::json(path, number, &e))
+            })
+            .collect::<EngineResult<VecDeque<RecordedFrame>>>()?;
+        log::info!(
+            "replaying {} frames from `{}`",
+            frames.len(),
+            path.display()
+        );
+        Ok(Self {
+            seed: header.seed,
+            frames,
+            exit_at_end,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn exit_at_end(&self) -> bool {
+        self.exit_at_end
+    }
+
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        self.frames.pop_front()
+    }
+}
@@ -0,0 +1,56 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    path::Path,
+};
+
+use crate::{
+    error::{EngineError, EngineResult},
+    gl_check,
+};
+
+/// Saves the content of the current read framebuffer to a PNG file
+pub fn save_screenshot(path: &Path, width: i32, height: i32) -> EngineResult<()> {
+    if width <= 0 || height <= 0 {
+        return Err(EngineError::io(
+            path,
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the window has no pixels, e.g. while minimized",
+            ),
+        ));
+    }
+    let (width, height) = (width as usize, height as usize);
+    let row_size = width * 4;
+    let mut pixels = vec![0u8; row_size * height];
+    unsafe {
+        // pack alignment is set to 1 by the engine, rows are tightly packed
+        gl::ReadPixels(
+            0,
+            0,
+            width as _,
+            height as _,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as _,
+        );
+        gl_check!();
+    }
+
+    // OpenGL rows go from bottom to top, PNG rows from top to bottom
+    let flipped: Vec<u8> = pixels
+        .chunks_exact(row_size)
+        .rev()
+        .flatten()
+        .copied()
+        .collect();
+
+    let file = File::create(path).map_err(|e| EngineError::io(path, e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as _, height as _);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&flipped))
+        .map_err(|e| EngineError::io(path, e.into()))
+}
@@ -0,0 +1,470 @@
+use std::mem::size_of;
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Vector3};
+use gl::types::{GLfloat, GLint, GLuint};
+
+use crate::{
+    error::{EngineError, EngineResult},
+    gl_check, gl_checked, gl_debug,
+    gl_types::{BufferIdType, FramebufferIdType, TextureIdType},
+    lights::{Light, LightKind, LightSet},
+    objects::Camera,
+    projection::{Projection, ProjectionKind},
+};
+
+/// Layers of the shadow map array, shared by the cascades and the spot lights
+pub const MAX_SHADOW_MAPS: usize = 8;
+/// Uniform block binding point of the shadow matrices, `layout(std140, binding = 1) uniform Shadows`
+pub const SHADOWS_BINDING: GLuint = 1;
+/// Texture unit of the shadow map array, unit 0 being the base color textures'
+pub const SHADOW_MAPS_UNIT: GLuint = 1;
+const MAX_CASCADES: usize = 4;
+const MAX_PCF_RADIUS: i32 = 3;
+const MIN_MAP_SIZE: i32 = 64;
+const MAX_MAP_SIZE: i32 = 8192;
+/// Distance in front of a cascade, towards the light, within which the casters are rendered
+const CASTER_DISTANCE: GLfloat = 50.;
+/// Near plane of the spot lights' cameras, relative to their range
+const SPOT_NEAR: GLfloat = 0.01;
+/// Slope scaled and constant depth offsets of the casters, against shadow acne
+const POLYGON_OFFSET: (GLfloat, GLfloat) = (2., 4.);
+
+/// Tunable shadow rendering, editable at runtime
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    /// Width and height in texels of each shadow map
+    pub map_size: i32,
+    /// Shadow maps splitting the view of the directional lights, from the nearest to the farthest
+    pub cascades: usize,
+    /// Distance from the camera beyond which directional lights cast no shadows
+    pub distance: GLfloat,
+    /// Blend of the cascade splits, from evenly spaced at 0 to logarithmic at 1
+    pub split_lambda: GLfloat,
+    /// Depth subtracted from the compared depth, in the [0, 1] range of the shadow maps
+    pub bias: GLfloat,
+    /// Offset of the shaded points along their normal, in world units
+    pub normal_offset: GLfloat,
+    /// Texels on each side of the percentage closer filtering kernel, 0 for a single sample
+    pub pcf_radius: i32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            map_size: 2048,
+            cascades: 3,
+            distance: 40.,
+            split_lambda: 0.75,
+            bias: 0.0005,
+            normal_offset: 0.02,
+            pcf_radius: 1,
+        }
+    }
+}
+
+impl ShadowSettings {
+    pub const NAMES: [&'static str; 8] = [
+        "enabled",
+        "map_size",
+        "cascades",
+        "distance",
+        "split_lambda",
+        "bias",
+        "normal_offset",
+        "pcf_radius",
+    ];
+
+    /// Value of the named setting as text
+    pub fn get(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "enabled" => self.enabled.to_string(),
+            "map_size" => self.map_size.to_string(),
+            "cascades" => self.cascades.to_string(),
+            "distance" => self.distance.to_string(),
+            "split_lambda" => self.split_lambda.to_string(),
+            "bias" => self.bias.to_string(),
+            "normal_offset" => self.normal_offset.to_string(),
+            "pcf_radius" => self.pcf_radius.to_string(),
+            _ => return None,
+        })
+    }
+
+    /// Parses and sets the named setting
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("invalid number `{value}`");
+        match name {
+            "enabled" => self.enabled = value.parse().map_err(|_| "expected `true` or `false`")?,
+            "map_size" => self.map_size = value.parse().map_err(|_| invalid())?,
+            "cascades" => self.cascades = value.parse().map_err(|_| invalid())?,
+            "distance" => self.distance = value.parse().map_err(|_| invalid())?,
+            "split_lambda" => self.split_lambda = value.parse().map_err(|_| invalid())?,
+            "bias" => self.bias = value.parse().map_err(|_| invalid())?,
+            "normal_offset" => self.normal_offset = value.parse().map_err(|_| invalid())?,
+            "pcf_radius" => self.pcf_radius = value.parse().map_err(|_| invalid())?,
+            _ => return Err(format!("unknown shadow setting `{name}`")),
+        }
+        self.sanitize();
+        Ok(())
+    }
+
+    /// Keeps the settings in ranges the shadow maps and shaders handle
+    pub fn sanitize(&mut self) {
+        self.map_size = self.map_size.clamp(MIN_MAP_SIZE, MAX_MAP_SIZE);
+        self.cascades = self.cascades.clamp(1, MAX_CASCADES);
+        self.distance = self.distance.max(1.);
+        self.split_lambda = self.split_lambda.clamp(0., 1.);
+        self.bias = self.bias.max(0.);
+        self.normal_offset = self.normal_offset.max(0.);
+        self.pcf_radius = self.pcf_radius.clamp(0, MAX_PCF_RADIUS);
+    }
+}
+
+/// Shadow map layers rendered for a light
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShadowCaster {
+    /// Index of the light in the light list
+    pub light: usize,
+    pub first_layer: usize,
+    pub layer_count: usize,
+}
+
+/// Lights casting shadows for a view, and the cameras rendering each layer of their shadow maps
+pub struct ShadowLayout {
+    pub casters: Vec<ShadowCaster>,
+    /// Camera of each layer, from the first
+    pub cameras: Vec<Camera>,
+}
+
+impl ShadowLayout {
+    /// Cascades covering the view of `camera` for the directional lights, and a perspective
+    /// camera for the spot lights, as long as there are layers left; point lights cast no
+    /// shadows
+    pub fn new(lights: &LightSet, camera: &Camera, settings: &ShadowSettings) -> Self {
+        let mut layout = Self {
+            casters: Vec::new(),
+            cameras: Vec::new(),
+        };
+        if !settings.enabled {
+            return layout;
+        }
+        for (index, light) in lights.lights().iter().enumerate() {
+            if !light.enabled || !light.cast_shadows {
+                continue;
+            }
+            let cameras = match light.kind {
+                LightKind::Directional => cascade_cameras(light, camera, settings),
+                LightKind::Spot => vec![spot_camera(light)],
+                LightKind::Point => continue,
+            };
+            // the lights after the last layer are left unshadowed
+            if layout.cameras.len() + cameras.len() > MAX_SHADOW_MAPS {
+                continue;
+            }
+            layout.casters.push(ShadowCaster {
+                light: index,
+                first_layer: layout.cameras.len(),
+                layer_count: cameras.len(),
+            });
+            layout.cameras.extend(cameras);
+        }
+        layout
+    }
+}
+
+/// Pitch and yaw of a camera looking along `direction`
+fn looking_along(direction: Vector3<GLfloat>) -> (GLfloat, GLfloat) {
+    let direction = direction.normalize();
+    (
+        direction.z.clamp(-1., 1.).asin(),
+        direction.y.atan2(direction.x),
+    )
+}
+
+/// Right and up unit vectors of a camera's image plane
+fn image_axes(camera: &Camera) -> (Vector3<GLfloat>, Vector3<GLfloat>) {
+    let (up, forward) = camera.up_forward();
+    let right = forward.cross(up).normalize();
+    (right, right.cross(forward))
+}
+
+/// Distances from the camera splitting its view into the settings' cascades, from the near plane to
+/// the shadow distance, the last being the far end of the last cascade
+fn cascade_splits(camera: &Camera, settings: &ShadowSettings) -> Vec<GLfloat> {
+    let near = camera.projection().near;
+    let far = settings
+        .distance
+        .min(camera.projection().far)
+        .max(near * 2.);
+    let count = settings.cascades;
+    (0..=count)
+        .map(|i| {
+            let ratio = i as GLfloat / count as GLfloat;
+            let logarithmic = near * (far / near).powf(ratio);
+            let uniform = near + (far - near) * ratio;
+            settings.split_lambda * logarithmic + (1. - settings.split_lambda) * uniform
+        })
+        .collect()
+}
+
+/// Orthographic cameras looking along a directional light, each containing a slice of the view
+/// of `camera` whatever its orientation, moved by whole texels to keep the shadows' edges still
+fn cascade_cameras(light: &Light, camera: &Camera, settings: &ShadowSettings) -> Vec<Camera> {
+    let projection = camera.projection();
+    let forward = camera.forward();
+    let (right, up) = image_axes(camera);
+    // half size of the view at a distance
+    let half_size = |distance: GLfloat| {
+        let height = match projection.kind {
+            ProjectionKind::Perspective => distance * (projection.fov / 2.).tan(),
+            ProjectionKind::Orthographic => projection.ortho_height / 2.,
+        };
+        (height * projection.aspect_ratio, height)
+    };
+    let (pitch, yaw) = looking_along(light.direction);
+    let splits = cascade_splits(camera, settings);
+    splits
+        .windows(2)
+        .map(|split| {
+            let corners: Vec<Point3<GLfloat>> = split
+                .iter()
+                .flat_map(|&distance| {
+                    let (width, height) = half_size(distance);
+                    let center = camera.position() + forward * distance;
+                    [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)]
+                        .map(|(x, y)| center + right * (x * width) + up * (y * height))
+                })
+                .collect();
+            let center = Point3::centroid(&corners);
+            // a sphere keeps the same size whatever the camera's orientation, rounded up to
+            // keep it from flickering with rounding errors
+            let radius = corners
+                .iter()
+                .map(|c| (c - center).magnitude())
+                .fold(0., GLfloat::max);
+            let radius = (radius * 16.).ceil() / 16.;
+
+            let mut light_camera = Camera::new(
+                center,
+                pitch,
+                yaw,
+                Projection {
+                    kind: ProjectionKind::Orthographic,
+                    ortho_height: radius * 2.,
+                    near: 0.01,
+                    far: CASTER_DISTANCE + radius * 2.,
+                    reversed_z: false,
+                    aspect_ratio: 1.,
+                    ..Default::default()
+                },
+            );
+            // snaps the center to the texels of the map
+            let (light_right, light_up) = image_axes(&light_camera);
+            let texel = radius * 2. / settings.map_size as GLfloat;
+            let snap = |axis: Vector3<GLfloat>| {
+                let offset = axis.dot(center.to_vec());
+                axis * ((offset / texel).floor() * texel - offset)
+            };
+            let center = center + snap(light_right) + snap(light_up);
+            light_camera.set_position(center - light_camera.forward() * (CASTER_DISTANCE + radius));
+            light_camera
+        })
+        .collect()
+}
+
+/// Perspective camera covering the cone of a spot light, up to its range
+fn spot_camera(light: &Light) -> Camera {
+    let (pitch, yaw) = looking_along(light.direction);
+    let mut projection = Projection {
+        kind: ProjectionKind::Perspective,
+        fov: light.outer_angle.0.to_radians() * 2.,
+        near: light.range * SPOT_NEAR,
+        far: light.range,
+        reversed_z: false,
+        aspect_ratio: 1.,
+        ..Default::default()
+    };
+    projection.sanitize();
+    Camera::new(light.position, pitch, yaw, projection)
+}
+
+/// Matrix from world space to the [0, 1] texture coordinates and depth seen by `camera`
+fn shadow_matrix(camera: &Camera) -> Matrix4<GLfloat> {
+    let to_texture =
+        Matrix4::from_translation(Vector3::new(0.5, 0.5, 0.5)) * Matrix4::from_scale(0.5);
+    to_texture * camera.projection_matrix() * camera.transform()
+}
+
+/// std140 layout of the `Shadows` uniform block
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuShadows {
+    matrices: [[[GLfloat; 4]; 4]; MAX_SHADOW_MAPS],
+    texel_size: GLfloat,
+    bias: GLfloat,
+    normal_offset: GLfloat,
+    pcf_radius: GLint,
+}
+
+/// Depth texture array the shadow casters are rendered into, one layer per light camera, and
+/// the uniform buffer of the `Shadows` block, bound to [`SHADOWS_BINDING`]
+pub struct ShadowMaps {
+    framebuffer_id: FramebufferIdType,
+    texture_id: TextureIdType,
+    buffer_id: BufferIdType,
+    size: i32,
+}
+
+impl ShadowMaps {
+    pub fn new(size: i32) -> EngineResult<Self> {
+        if !(MIN_MAP_SIZE..=MAX_MAP_SIZE).contains(&size) {
+            return Err(EngineError::InvalidBuilderConfig {
+                builder: "shadow maps",
+                problems: vec![format!(
+                    "invalid size {size}, expected {MIN_MAP_SIZE} to {MAX_MAP_SIZE}"
+                )],
+            });
+        }
+
+        let mut framebuffer_id: FramebufferIdType = 0;
+        let mut texture_id: TextureIdType = 0;
+        let mut buffer_id: BufferIdType = 0;
+        // points out of the maps are lit
+        let border: [GLfloat; 4] = [1.; 4];
+        let status = unsafe {
+            gl_checked! {
+                gl::CreateTextures(gl::TEXTURE_2D_ARRAY, 1, &mut texture_id);
+                gl::TextureStorage3D(
+                    texture_id,
+                    1,
+                    gl::DEPTH_COMPONENT32F,
+                    size,
+                    size,
+                    MAX_SHADOW_MAPS as _,
+                );
+                gl::TextureParameteri(texture_id, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+                gl::TextureParameteri(texture_id, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+                gl::TextureParameteri(texture_id, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as _);
+                gl::TextureParameteri(texture_id, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as _);
+                gl::TextureParameterfv(texture_id, gl::TEXTURE_BORDER_COLOR, border.as_ptr());
+                gl::TextureParameteri(
+                    texture_id,
+                    gl::TEXTURE_COMPARE_MODE,
+                    gl::COMPARE_REF_TO_TEXTURE as _,
+                );
+                gl::TextureParameteri(texture_id, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as _);
+                gl::CreateFramebuffers(1, &mut framebuffer_id);
+                gl::NamedFramebufferTextureLayer(framebuffer_id, gl::DEPTH_ATTACHMENT, texture_id, 0, 0);
+                gl::NamedFramebufferDrawBuffer(framebuffer_id, gl::NONE);
+                gl::NamedFramebufferReadBuffer(framebuffer_id, gl::NONE);
+                gl::CreateBuffers(1, &mut buffer_id);
+                gl::NamedBufferStorage(
+                    buffer_id,
+                    size_of::<GpuShadows>() as _,
+                    std::ptr::null(),
+                    gl::DYNAMIC_STORAGE_BIT,
+                );
+                gl::BindBufferBase(gl::UNIFORM_BUFFER, SHADOWS_BINDING, buffer_id);
+            };
+            let status = gl::CheckNamedFramebufferStatus(framebuffer_id, gl::FRAMEBUFFER);
+            gl_check!();
+            status
+        };
+        gl_debug::label_object(gl::FRAMEBUFFER, framebuffer_id, "shadow maps");
+        gl_debug::label_object(gl::TEXTURE, texture_id, "shadow maps depth");
+        gl_debug::label_object(gl::BUFFER, buffer_id, "shadows");
+
+        let shadow_maps = Self {
+            framebuffer_id,
+            texture_id,
+            buffer_id,
+            size,
+        };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(EngineError::Context(format!(
+                "shadow map framebuffer is incomplete, status {status:#x}"
+            )));
+        }
+        Ok(shadow_maps)
+    }
+
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    /// Binds the framebuffer to render into a layer, cleared to the far plane
+    pub fn begin_layer(&self, layer: usize) {
+        let far: GLfloat = 1.;
+        unsafe {
+            gl_checked! {
+                gl::NamedFramebufferTextureLayer(
+                    self.framebuffer_id,
+                    gl::DEPTH_ATTACHMENT,
+                    self.texture_id,
+                    0,
+                    layer as _,
+                );
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer_id);
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::Viewport(0, 0, self.size, self.size);
+                gl::ClearNamedFramebufferfv(self.framebuffer_id, gl::DEPTH, 0, &far);
+            };
+        }
+    }
+
+    /// Sets the state rendering the casters' depth, offset against shadow acne, and unbinds the
+    /// maps from their unit while they are rendered into
+    pub fn begin() {
+        unsafe {
+            gl_checked! {
+                gl::BindTextureUnit(SHADOW_MAPS_UNIT, 0);
+                // casters between the light and the near plane still cast shadows
+                gl::Enable(gl::DEPTH_CLAMP);
+                gl::Enable(gl::POLYGON_OFFSET_FILL);
+                gl::PolygonOffset(POLYGON_OFFSET.0, POLYGON_OFFSET.1);
+            };
+        }
+    }
+
+    /// Restores the state changed by [`Self::begin`], uploads the matrices of the layers'
+    /// cameras and binds the maps for the shaders to sample
+    pub fn end(&self, cameras: &[Camera], settings: &ShadowSettings) {
+        let mut shadows = GpuShadows {
+            matrices: [[[0.; 4]; 4]; MAX_SHADOW_MAPS],
+            texel_size: 1. / self.size as GLfloat,
+            bias: settings.bias,
+            normal_offset: settings.normal_offset,
+            pcf_radius: settings.pcf_radius,
+        };
+        for (matrix, camera) in shadows.matrices.iter_mut().zip(cameras) {
+            *matrix = shadow_matrix(camera).into();
+        }
+        unsafe {
+            gl_checked! {
+                gl::Disable(gl::DEPTH_CLAMP);
+                gl::Disable(gl::POLYGON_OFFSET_FILL);
+                gl::NamedBufferSubData(
+                    self.buffer_id,
+                    0,
+                    size_of::<GpuShadows>() as _,
+                    bytemuck::bytes_of(&shadows).as_ptr() as _,
+                );
+                gl::BindTextureUnit(SHADOW_MAPS_UNIT, self.texture_id);
+            };
+        }
+    }
+}
+
+impl Drop for ShadowMaps {
+    fn drop(&mut self) {
+        unsafe {
+            gl_checked! {
+                gl::DeleteFramebuffers(1, &self.framebuffer_id);
+                gl::DeleteTextures(1, &self.texture_id);
+                gl::DeleteBuffers(1, &self.buffer_id);
+            };
+        }
+    }
+}
@@ -0,0 +1,47 @@
+/// Smoothing factor of the exponential moving average of the frame time
+const FRAME_TIME_SMOOTHING: f32 = 0.1;
+
+/// Rendering counters, reset at the beginning of each displayed frame
+#[derive(Default, Debug, Clone)]
+pub struct RenderStatistics {
+    pub drawables: usize,
+    pub draw_calls: usize,
+    pub particle_systems: usize,
+    pub particles: usize,
+    /// Drawables skipped for being out of the camera's view
+    pub culled: usize,
+}
+
+/// Runtime statistics of the engine, gathered every frame
+#[derive(Default, Debug)]
+pub struct FrameStatistics {
+    frame_time: f32,
+    pub render: RenderStatistics,
+}
+
+impl FrameStatistics {
+    /// Accounts for a new frame which took `delta_time` seconds
+    pub fn record_frame(&mut self, delta_time: f32) {
+        if delta_time <= 0. {
+            return;
+        }
+        if self.frame_time == 0. {
+            self.frame_time = delta_time;
+        } else {
+            self.frame_time += (delta_time - self.frame_time) * FRAME_TIME_SMOOTHING;
+        }
+    }
+
+    /// Smoothed frame time, in milliseconds
+    pub fn frame_time_ms(&self) -> f32 {
+        self.frame_time * 1000.
+    }
+
+    pub fn fps(&self) -> f32 {
+        if self.frame_time == 0. {
+            0.
+        } else {
+            1. / self.frame_time
+        }
+    }
+}
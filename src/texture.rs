@@ -0,0 +1,161 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+    rc::Rc,
+};
+
+use gl::types::GLenum;
+
+use crate::{
+    error::{EngineError, EngineResult},
+    gl_check, gl_checked, gl_debug,
+    gl_types::TextureIdType,
+};
+
+pub type TexturePointer = Rc<Texture>;
+
+/// How a texture is sampled outside of [0, 1] and between its texels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sampling {
+    pub wrap_s: GLenum,
+    pub wrap_t: GLenum,
+    pub mag_filter: GLenum,
+    pub min_filter: GLenum,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Self {
+            wrap_s: gl::REPEAT,
+            wrap_t: gl::REPEAT,
+            mag_filter: gl::LINEAR,
+            min_filter: gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+}
+
+/// Immutable 2D RGBA image with a full mipmap chain
+#[derive(Debug)]
+pub struct Texture {
+    id: TextureIdType,
+    width: i32,
+    height: i32,
+}
+
+impl Texture {
+    /// Uploads `pixels`, 4 bytes per texel from the bottom left corner
+    pub fn from_rgba8(
+        name: &str,
+        width: i32,
+        height: i32,
+        pixels: &[u8],
+        sampling: Sampling,
+    ) -> EngineResult<Self> {
+        let size = (width.max(0) as usize)
+            .checked_mul(height.max(0) as usize)
+            .and_then(|texels| texels.checked_mul(4));
+        if width <= 0 || height <= 0 || size != Some(pixels.len()) {
+            return Err(EngineError::InvalidBuilderConfig {
+                builder: "texture",
+                problems: vec![format!(
+                    "{} bytes for a {width}x{height} RGBA image",
+                    pixels.len()
+                )],
+            });
+        }
+
+        let levels = 1 + width.max(height).ilog2() as i32;
+        let mut id: TextureIdType = 0;
+        unsafe {
+            gl_checked! {
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut id);
+                gl::TextureStorage2D(id, levels, gl::RGBA8, width, height);
+                gl::TextureSubImage2D(
+                    id,
+                    0,
+                    0,
+                    0,
+                    width,
+                    height,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_ptr() as _,
+                );
+                gl::GenerateTextureMipmap(id);
+                gl::TextureParameteri(id, gl::TEXTURE_WRAP_S, sampling.wrap_s as _);
+                gl::TextureParameteri(id, gl::TEXTURE_WRAP_T, sampling.wrap_t as _);
+                gl::TextureParameteri(id, gl::TEXTURE_MAG_FILTER, sampling.mag_filter as _);
+                gl::TextureParameteri(id, gl::TEXTURE_MIN_FILTER, sampling.min_filter as _);
+            };
+        }
+        gl_debug::label_object(gl::TEXTURE, id, name);
+        Ok(Self { id, width, height })
+    }
+
+    /// Decodes and uploads a PNG file, named after it, whose first row is uploaded at the top
+    pub fn from_png(path: &Path, sampling: Sampling) -> EngineResult<Self> {
+        let file = File::open(path).map_err(|e| EngineError::io(path, e))?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        // palettes and bit depths other than 8 are converted to 8 bit gray or color channels
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder
+            .read_info()
+            .map_err(|e| EngineError::io(path, e.into()))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|e| EngineError::io(path, e.into()))?;
+        let row_size = info.line_size;
+        let rows = buffer[..info.buffer_size()].chunks_exact(row_size).rev();
+        let pixels: Vec<u8> = match info.color_type {
+            png::ColorType::Rgba => rows.flatten().copied().collect(),
+            png::ColorType::Rgb => rows
+                .flat_map(|row| row.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]))
+                .collect(),
+            png::ColorType::GrayscaleAlpha => rows
+                .flat_map(|row| row.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]))
+                .collect(),
+            png::ColorType::Grayscale => rows
+                .flat_map(|row| row.iter().flat_map(|l| [*l, *l, *l, 255]))
+                .collect(),
+            png::ColorType::Indexed => {
+                return Err(EngineError::io(
+                    path,
+                    io::Error::new(io::ErrorKind::InvalidData, "unexpanded palette"),
+                ))
+            }
+        };
+        Self::from_rgba8(
+            &path.display().to_string(),
+            info.width as _,
+            info.height as _,
+            &pixels,
+            sampling,
+        )
+    }
+
+    pub fn id(&self) -> TextureIdType {
+        self.id
+    }
+
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::BindTextureUnit(unit, self.id);
+            gl_check!();
+        }
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+            gl_check!();
+        }
+    }
+}
@@ -1,11 +1,61 @@
-use crate::engine::Engine;
+use crate::{bounds::Aabb, statistics::RenderStatistics, view::DrawContext};
 
 pub trait Drawable {
-    fn draw(&self, engine: &Engine);
+    /// Draws the object as seen by the context's camera
+    fn draw(&self, context: &DrawContext);
+
+    /// Name used to label the drawable's GPU work in debug tools
+    fn name(&self) -> &str {
+        "drawable"
+    }
+
+    /// Adds the drawable's contribution to the frame's rendering statistics
+    fn collect_statistics(&self, statistics: &mut RenderStatistics) {
+        statistics.drawables += 1;
+        statistics.draw_calls += 1;
+    }
+
+    /// World space box containing what the drawable renders, if known
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+
+    /// Draws only what the depth buffer needs, into the shadow maps, with a full draw by default
+    fn draw_depth(&self, context: &DrawContext) {
+        self.draw(context);
+    }
+
+    /// Whether the drawable is rendered into the shadow maps, hiding lights from what's behind
+    fn casts_shadows(&self) -> bool {
+        false
+    }
+
+    fn set_casts_shadows(&mut self, _casts: bool) -> Result<(), String> {
+        Err(format!("`{}` can't cast shadows", self.name()))
+    }
+
+    /// Whether the shadows of the casters darken the drawable
+    fn receives_shadows(&self) -> bool {
+        false
+    }
+
+    fn set_receives_shadows(&mut self, _receives: bool) -> Result<(), String> {
+        Err(format!("`{}` can't receive shadows", self.name()))
+    }
 }
 
 pub trait Updatable {
     fn update(&mut self, delta_time: f32);
+
+    /// Name used to label the object's work in debug tools and profiles
+    fn name(&self) -> &str {
+        "updatable"
+    }
+
+    /// Whether the object stops updating while the simulation is paused
+    fn pausable(&self) -> bool {
+        true
+    }
 }
 
 pub trait ParticleLike {
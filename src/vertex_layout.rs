@@ -0,0 +1,207 @@
+use std::mem::size_of;
+
+use gl::types::{GLenum, GLint, GLuint};
+
+/// Type of the components of a vertex attribute as stored in its buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComponentType {
+    F32,
+    /// Half float, given as the `u16` bits of an IEEE 754 binary16 value
+    F16,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+}
+
+impl ComponentType {
+    /// Size of a component in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            Self::F32 | Self::I32 | Self::U32 => 4,
+            Self::F16 | Self::I16 | Self::U16 => 2,
+            Self::I8 | Self::U8 => 1,
+        }
+    }
+
+    pub fn gl_type(&self) -> GLenum {
+        match self {
+            Self::F32 => gl::FLOAT,
+            Self::F16 => gl::HALF_FLOAT,
+            Self::I8 => gl::BYTE,
+            Self::U8 => gl::UNSIGNED_BYTE,
+            Self::I16 => gl::SHORT,
+            Self::U16 => gl::UNSIGNED_SHORT,
+            Self::I32 => gl::INT,
+            Self::U32 => gl::UNSIGNED_INT,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !matches!(self, Self::F32 | Self::F16)
+    }
+}
+
+/// How the shader sees the components of an attribute
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Conversion {
+    /// Converted to floats as is, e.g. 255u8 is 255.0
+    #[default]
+    Float,
+    /// Integers mapped to [0, 1], or [-1, 1] if signed, e.g. 255u8 is 1.0
+    Normalized,
+    /// Kept as integers, for `int`, `uint`, `ivecN` and `uvecN` inputs
+    Integer,
+}
+
+/// Attribute of a vertex, read from a buffer at an offset of each vertex
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VertexAttribute {
+    /// Name of the shader input, looked up unless the location is given
+    pub name: String,
+    pub location: Option<GLuint>,
+    /// Number of components, from 1 to 4
+    pub components: GLint,
+    pub component_type: ComponentType,
+    pub conversion: Conversion,
+    /// Offset in bytes from the start of the vertex, after the previous attribute if `None`
+    pub offset: Option<usize>,
+}
+
+impl VertexAttribute {
+    pub fn new(name: impl Into<String>, components: GLint, component_type: ComponentType) -> Self {
+        Self {
+            name: name.into(),
+            location: None,
+            components,
+            component_type,
+            conversion: Conversion::Float,
+            offset: None,
+        }
+    }
+
+    /// Attribute of `components` 32 bit floats
+    pub fn float(name: impl Into<String>, components: GLint) -> Self {
+        Self::new(name, components, ComponentType::F32)
+    }
+
+    pub fn normalized(mut self) -> Self {
+        self.conversion = Conversion::Normalized;
+        self
+    }
+
+    pub fn integer(mut self) -> Self {
+        self.conversion = Conversion::Integer;
+        self
+    }
+
+    /// Binds the attribute to a location, e.g. one set with `layout(location = N)`
+    pub fn location(mut self, location: GLuint) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// Offset in bytes from the start of the vertex, e.g. `std::mem::offset_of!(Vertex, normal)`
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Size of the attribute in bytes
+    pub fn size(&self) -> usize {
+        self.components as usize * self.component_type.size()
+    }
+}
+
+/// Attributes of the vertices of a buffer, possibly interleaved
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+    /// Size of a vertex in bytes, the end of the last attribute if `None`
+    stride: Option<usize>,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Layout of a vertex struct, whose size is the stride, e.g.
+    /// `VertexLayout::of::<Vertex>().attribute(VertexAttribute::float("position", 3))`
+    pub fn of<T>() -> Self {
+        Self {
+            attributes: Vec::new(),
+            stride: Some(size_of::<T>()),
+        }
+    }
+
+    pub fn attribute(mut self, attribute: VertexAttribute) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn stride(mut self, stride: usize) -> Self {
+        self.stride = Some(stride);
+        self
+    }
+
+    /// Attributes with their offset, those without one following the previous attribute
+    pub fn attributes(&self) -> impl Iterator<Item = (&VertexAttribute, usize)> {
+        let mut end = 0;
+        self.attributes.iter().map(move |attribute| {
+            let offset = attribute.offset.unwrap_or(end);
+            end = offset + attribute.size();
+            (attribute, offset)
+        })
+    }
+
+    pub fn attribute_named(&self, name: &str) -> Option<(&VertexAttribute, usize)> {
+        self.attributes()
+            .find(|(attribute, _)| attribute.name == name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty()
+    }
+
+    /// Size of a vertex in bytes
+    pub fn vertex_size(&self) -> usize {
+        self.stride.unwrap_or_else(|| {
+            self.attributes()
+                .map(|(attribute, offset)| offset + attribute.size())
+                .max()
+                .unwrap_or_default()
+        })
+    }
+
+    /// Problems making the layout unusable
+    pub fn problems(&self) -> Vec<String> {
+        let stride = self.vertex_size();
+        let mut problems = Vec::new();
+        for (attribute, offset) in self.attributes() {
+            let name = &attribute.name;
+            if !(1..=4).contains(&attribute.components) {
+                problems.push(format!(
+                    "attribute `{name}` has {} components, expected 1 to 4",
+                    attribute.components
+                ));
+            }
+            if offset + attribute.size() > stride {
+                problems.push(format!(
+                    "attribute `{name}` ends at byte {}, past the {stride} bytes of a vertex",
+                    offset + attribute.size()
+                ));
+            }
+            if attribute.conversion == Conversion::Integer && !attribute.component_type.is_integer()
+            {
+                problems.push(format!(
+                    "attribute `{name}` of {:?} components can't be kept as integers",
+                    attribute.component_type
+                ));
+            }
+        }
+        problems
+    }
+}
@@ -0,0 +1,312 @@
+use std::{fmt::Display, str::FromStr};
+
+use clap::ValueEnum;
+use gl::types::GLfloat;
+
+use crate::{
+    engine::Engine,
+    error::{EngineError, EngineResult},
+    gl_check, gl_checked, gl_debug,
+    gl_types::{FramebufferIdType, RenderbufferIdType, TextureIdType},
+    objects::{Camera, CameraPointer},
+};
+
+/// Rectangle of the window, in fractions of its size from its bottom left corner
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: GLfloat,
+    pub y: GLfloat,
+    pub width: GLfloat,
+    pub height: GLfloat,
+}
+
+impl Viewport {
+    pub const FULL: Self = Self::new(0., 0., 1., 1.);
+
+    pub const fn new(x: GLfloat, y: GLfloat, width: GLfloat, height: GLfloat) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Parses the `x y width height` fractions, which must fit in the window
+    pub fn parse(values: &[&str]) -> Result<Self, String> {
+        let [x, y, width, height] = values else {
+            return Err(format!("expected 4 values, got {}", values.len()));
+        };
+        let parse = |v: &str| -> Result<GLfloat, String> {
+            v.parse().map_err(|_| format!("invalid number `{v}`"))
+        };
+        let viewport = Self::new(parse(x)?, parse(y)?, parse(width)?, parse(height)?);
+        let fits = viewport.x >= 0.
+            && viewport.y >= 0.
+            && viewport.width > 0.
+            && viewport.height > 0.
+            && viewport.x + viewport.width <= 1.
+            && viewport.y + viewport.height <= 1.;
+        if !fits {
+            return Err(
+                "the viewport must be a non empty part of the window, in [0, 1]".to_owned(),
+            );
+        }
+        Ok(viewport)
+    }
+
+    /// Position and size in pixels, in a window of `window_size`
+    pub fn pixels(&self, window_size: (i32, i32)) -> (i32, i32, i32, i32) {
+        let (w, h) = (window_size.0 as GLfloat, window_size.1 as GLfloat);
+        let x = (self.x * w).round() as i32;
+        let y = (self.y * h).round() as i32;
+        let right = ((self.x + self.width) * w).round() as i32;
+        let top = ((self.y + self.height) * h).round() as i32;
+        (x, y, (right - x).max(1), (top - y).max(1))
+    }
+}
+
+impl Display for Viewport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {} {}", self.x, self.y, self.width, self.height)
+    }
+}
+
+/// Offscreen framebuffer a view is rendered into at its own resolution, whose color texture
+/// can be sampled by other programs
+pub struct RenderTarget {
+    framebuffer_id: FramebufferIdType,
+    color_texture_id: TextureIdType,
+    depth_renderbuffer_id: RenderbufferIdType,
+    width: i32,
+    height: i32,
+}
+
+impl RenderTarget {
+    pub fn new(name: &str, width: i32, height: i32) -> EngineResult<Self> {
+        if width <= 0 || height <= 0 {
+            return Err(EngineError::InvalidBuilderConfig {
+                builder: "render target",
+                problems: vec![format!("invalid size {width}x{height}")],
+            });
+        }
+
+        let mut framebuffer_id: FramebufferIdType = 0;
+        let mut color_texture_id: TextureIdType = 0;
+        let mut depth_renderbuffer_id: RenderbufferIdType = 0;
+        let status = unsafe {
+            gl_checked! {
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut color_texture_id);
+                gl::TextureStorage2D(color_texture_id, 1, gl::RGBA8, width, height);
+                gl::TextureParameteri(color_texture_id, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+                gl::TextureParameteri(color_texture_id, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+                gl::TextureParameteri(color_texture_id, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+                gl::TextureParameteri(color_texture_id, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+                gl::CreateRenderbuffers(1, &mut depth_renderbuffer_id);
+                gl::NamedRenderbufferStorage(depth_renderbuffer_id, gl::DEPTH_COMPONENT32F, width, height);
+                gl::CreateFramebuffers(1, &mut framebuffer_id);
+                gl::NamedFramebufferTexture(framebuffer_id, gl::COLOR_ATTACHMENT0, color_texture_id, 0);
+                gl::NamedFramebufferRenderbuffer(
+                    framebuffer_id,
+                    gl::DEPTH_ATTACHMENT,
+                    gl::RENDERBUFFER,
+                    depth_renderbuffer_id,
+                );
+            };
+            let status = gl::CheckNamedFramebufferStatus(framebuffer_id, gl::FRAMEBUFFER);
+            gl_check!();
+            status
+        };
+        gl_debug::label_object(gl::FRAMEBUFFER, framebuffer_id, &format!("{name} target"));
+        gl_debug::label_object(
+            gl::TEXTURE,
+            color_texture_id,
+            &format!("{name} target color"),
+        );
+        gl_debug::label_object(
+            gl::RENDERBUFFER,
+            depth_renderbuffer_id,
+            &format!("{name} target depth"),
+        );
+
+        let target = Self {
+            framebuffer_id,
+            color_texture_id,
+            depth_renderbuffer_id,
+            width,
+            height,
+        };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(EngineError::Context(format!(
+                "render target of view `{name}` is incomplete, status {status:#x}"
+            )));
+        }
+        Ok(target)
+    }
+
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    pub fn color_texture(&self) -> TextureIdType {
+        self.color_texture_id
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        unsafe {
+            gl_checked! {
+                gl::DeleteFramebuffers(1, &self.framebuffer_id);
+                gl::DeleteRenderbuffers(1, &self.depth_renderbuffer_id);
+                gl::DeleteTextures(1, &self.color_texture_id);
+            };
+        }
+    }
+}
+
+/// Camera rendered into a rectangle of the window, possibly through a render target
+pub struct View {
+    name: String,
+    camera: CameraPointer,
+    viewport: Viewport,
+    target: Option<RenderTarget>,
+}
+
+impl View {
+    pub fn new(name: impl Into<String>, camera: CameraPointer, viewport: Viewport) -> Self {
+        Self {
+            name: name.into(),
+            camera,
+            viewport,
+            target: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn camera(&self) -> &CameraPointer {
+        &self.camera
+    }
+
+    pub fn set_camera(&mut self, camera: CameraPointer) {
+        self.camera = camera;
+    }
+
+    pub fn viewport(&self) -> &Viewport {
+        &self.viewport
+    }
+
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
+    pub fn target(&self) -> Option<&RenderTarget> {
+        self.target.as_ref()
+    }
+
+    /// Renders the view offscreen before scaling it into its viewport, or directly if `None`
+    pub fn set_target(&mut self, target: Option<RenderTarget>) {
+        self.target = target;
+    }
+
+    /// Width to height ratio of the rendered image
+    pub fn aspect_ratio(&self, window_size: (i32, i32)) -> GLfloat {
+        let (width, height) = match self.target.as_ref() {
+            Some(target) => target.size(),
+            None => {
+                let (_, _, width, height) = self.viewport.pixels(window_size);
+                (width, height)
+            }
+        };
+        width as GLfloat / height as GLfloat
+    }
+
+    /// Binds the framebuffer and restricts the viewport and clears to the view's pixels
+    pub fn bind(&self, window_size: (i32, i32)) {
+        let (framebuffer, (x, y, width, height)) = match self.target.as_ref() {
+            Some(target) => (target.framebuffer_id, (0, 0, target.width, target.height)),
+            None => (0, self.viewport.pixels(window_size)),
+        };
+        unsafe {
+            gl_checked! {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+                gl::Viewport(x, y, width, height);
+                gl::Scissor(x, y, width, height);
+                gl::Enable(gl::SCISSOR_TEST);
+            };
+        }
+    }
+
+    /// Copies the render target into the viewport, leaving the window's framebuffer bound
+    pub fn present(&self, window_size: (i32, i32)) {
+        unsafe {
+            gl_checked! {
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            };
+        }
+        let Some(target) = self.target.as_ref() else {
+            return;
+        };
+        let (x, y, width, height) = self.viewport.pixels(window_size);
+        unsafe {
+            gl_checked! {
+                gl::BlitNamedFramebuffer(
+                    target.framebuffer_id,
+                    0,
+                    0,
+                    0,
+                    target.width,
+                    target.height,
+                    x,
+                    y,
+                    x + width,
+                    y + height,
+                    gl::COLOR_BUFFER_BIT,
+                    gl::LINEAR,
+                );
+            };
+        }
+    }
+}
+
+/// Arrangement of the views created at startup
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ViewLayout {
+    /// The main camera fills the window
+    #[default]
+    Single,
+    /// The main camera on the left, a camera looking from the side on the right
+    Split,
+    /// The main camera fills the window, an orthographic top view in its top right corner
+    PictureInPicture,
+}
+
+impl Display for ViewLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Single => write!(f, "single"),
+            Self::Split => write!(f, "split"),
+            Self::PictureInPicture => write!(f, "picture-in-picture"),
+        }
+    }
+}
+
+impl FromStr for ViewLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, false).map_err(|_| format!("unknown layout `{s}`"))
+    }
+}
+
+/// What a drawable needs to know about the view being rendered
+pub struct DrawContext<'a> {
+    pub engine: &'a Engine,
+    pub view: &'a View,
+    /// Camera of the view, already borrowed
+    pub camera: &'a Camera,
+}